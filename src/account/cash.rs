@@ -0,0 +1,139 @@
+//! ## 现金账户
+//! ----
+//!
+//! + 初始资金：回测开始时账户中的现金
+//! + 定期转入：每月固定日期转入的资金(如工资)，该月没有这一天时在月末转入
+//! + 资金不足：买入金额超过账户现金时拒绝买入，或者只用剩余现金部分成交
+//! + 闲置资金收益：账户现金按货币基金年化收益率逐日计息
+//!
+//! 收益率以百万分之一为单位保存，对外接口使用百分比
+
+use super::fee::RATE_SCALE;
+use crate::decimal::Money;
+use time::Date;
+
+/// 资金不足时的处理方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InsufficientFunds {
+    /// 拒绝买入
+    #[default]
+    Reject,
+    /// 用剩余现金部分成交
+    PartialFill,
+}
+
+/// 现金计划
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CashPlan {
+    pub(crate) initial: Money,                  // 初始资金
+    pub(crate) deposit: Money,                  // 每月转入金额
+    pub(crate) deposit_day: u8,                 // 每月转入日
+    pub(crate) yield_rate: u32,                 // 闲置资金年化收益率
+    pub(crate) insufficient: InsufficientFunds, // 资金不足时的处理方式
+}
+
+impl CashPlan {
+    /// 以指定初始资金开始，不转入、不计息，资金不足时拒绝买入
+    pub fn new(initial: Money) -> Self {
+        CashPlan {
+            initial,
+            deposit_day: 1,
+            ..Default::default()
+        }
+    }
+
+    /// 每月第day日转入指定金额
+    pub fn with_deposit(mut self, amount: Money, day: u8) -> Self {
+        self.deposit = amount;
+        self.deposit_day = day.clamp(1, 31);
+        self
+    }
+
+    /// 设置闲置资金年化收益率(百分比)
+    pub fn with_yield(mut self, rate: f32) -> Self {
+        self.yield_rate = (rate as f64 * RATE_SCALE as f64 / 100.0).round() as u32;
+        self
+    }
+
+    /// 设置资金不足时的处理方式
+    pub fn with_insufficient(mut self, insufficient: InsufficientFunds) -> Self {
+        self.insufficient = insufficient;
+        self
+    }
+
+    /// 指定月份的转入日，该月没有这一天时取月末
+    fn deposit_date(&self, year: i32, month: time::Month) -> Date {
+        let day = self.deposit_day.min(month.length(year));
+        Date::from_calendar_date(year, month, day).unwrap()
+    }
+
+    /// (from, to]期间的转入次数
+    pub(crate) fn deposits_between(&self, from: Date, to: Date) -> u32 {
+        if self.deposit <= Money::ZERO || to <= from {
+            return 0;
+        }
+        let (mut year, mut month) = (from.year(), from.month());
+        let mut count = 0;
+        loop {
+            let date = self.deposit_date(year, month);
+            if date > to {
+                break;
+            }
+            if date > from {
+                count += 1;
+            }
+            if month == time::Month::December {
+                year += 1;
+            }
+            month = month.next();
+        }
+        count
+    }
+
+    /// 现金余额持有指定天数的利息，余额为负时不计息
+    pub(crate) fn interest(&self, balance: Money, days: u32) -> Money {
+        balance
+            .max(Money::ZERO)
+            .mul_ratio(self.yield_rate as u64 * days as u64, RATE_SCALE * 365)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_deposits_between() {
+        let plan = CashPlan::new(Money::ZERO).with_deposit("1000".parse().unwrap(), 31);
+        assert_eq!(plan.deposit, Money::from_raw(1_000_000_000));
+        // 2月没有31日，在月末转入
+        assert_eq!(
+            plan.deposits_between(date!(2021 - 1 - 31), date!(2021 - 2 - 28)),
+            1
+        );
+        assert_eq!(
+            plan.deposits_between(date!(2021 - 1 - 30), date!(2021 - 3 - 30)),
+            2
+        );
+        // 跨年
+        assert_eq!(
+            plan.deposits_between(date!(2021 - 11 - 1), date!(2022 - 2 - 1)),
+            3
+        );
+        assert_eq!(
+            CashPlan::new(Money::ZERO).deposits_between(date!(2021 - 1 - 1), date!(2022 - 1 - 1)),
+            0
+        );
+    }
+
+    #[test]
+    fn test_interest() {
+        let plan = CashPlan::new(Money::ZERO).with_yield(2.0);
+        assert_eq!(
+            plan.interest(Money::from_raw(365_000_000), 1),
+            Money::from_raw(20_000)
+        );
+        assert_eq!(plan.interest(Money::from_raw(-365_000_000), 1), Money::ZERO);
+    }
+}
@@ -0,0 +1,159 @@
+//! ## 资产曲线
+//! ----
+//!
+//! 回测期间每个交易日收盘后账户的快照，回撤、波动率、图表等分析都基于资产曲线
+//! + market_value: 持仓市值
+//! + invested: 投入本金，累计买入成本减去卖出所得
+//! + cash: 账户余额，没有现金计划时为负数，表示从外部投入的资金
+//! + total_value: 总资产，有现金计划时为持仓市值加账户余额，否则为持仓市值
+//! + contribution: 外部净投入，有现金计划时为累计转入资金，否则为从外部投入的资金减去现金分红
+//! + holdings / prices: 各标的的持仓市值和当日价格(单位净值)
+
+use super::{Account, UpdateAccountItem};
+use crate::decimal::{Money, Price};
+use std::collections::BTreeMap;
+use time::Date;
+
+/// 某一交易日的账户快照
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquityPoint {
+    pub(crate) date: Date,
+    pub(crate) total_value: Money,
+    pub(crate) market_value: Money,
+    pub(crate) invested: Money,
+    pub(crate) cash: Money,
+    pub(crate) contribution: Money,
+    pub(crate) holdings: BTreeMap<u32, Money>, // 各标的的持仓市值
+    pub(crate) prices: BTreeMap<u32, Price>,   // 各标的的当日价格
+}
+
+/// 按日期先后排列的账户快照
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EquityCurve {
+    points: Vec<EquityPoint>,
+}
+
+impl EquityCurve {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录快照，与最后一个快照同一天时替换之
+    pub(crate) fn record(&mut self, point: EquityPoint) {
+        match self.points.last_mut() {
+            Some(last) if last.date == point.date => *last = point,
+            _ => self.points.push(point),
+        }
+    }
+
+    pub(crate) fn points(&self) -> &[EquityPoint] {
+        &self.points
+    }
+
+    pub(crate) fn last(&self) -> Option<&EquityPoint> {
+        self.points.last()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// 显示逐日资产曲线
+    pub(crate) fn show(&self) {
+        for point in &self.points {
+            println!(
+                "{}: total {}, market value {}, invested {}, cash {}",
+                point.date, point.total_value, point.market_value, point.invested, point.cash
+            );
+        }
+    }
+}
+
+impl<T> Account<T>
+where
+    T: UpdateAccountItem + Default + Clone,
+{
+    /// 按当前持仓生成指定日期的快照
+    pub(crate) fn snapshot(&self, date: Date) -> EquityPoint {
+        let holdings: BTreeMap<_, _> = self
+            .hold_detail
+            .iter()
+            .map(|(code, item)| (*code, item.get_current_asset()))
+            .collect();
+        let prices = self
+            .hold_detail
+            .iter()
+            .map(|(code, item)| (*code, item.get_current_value()))
+            .collect();
+        let market_value = holdings.values().copied().sum();
+        let (total_value, contribution) = match self.cash_plan {
+            Some(_) => (market_value + self.balance_price, self.total_deposit),
+            None => (market_value, -self.balance_price),
+        };
+        EquityPoint {
+            date,
+            total_value,
+            market_value,
+            invested: self.invested,
+            cash: self.balance_price,
+            contribution,
+            holdings,
+            prices,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::cash::CashPlan;
+    use crate::account::fund_account::FundAccount;
+    use crate::market::fund_market::FundData;
+    use time::macros::date;
+
+    #[test]
+    fn test_snapshot() {
+        let mut account = Account::<FundAccount>::new();
+        let data = FundData::new(date!(2021 - 9 - 1), 20000, 20000, None);
+        account.buy_with_cost(1, &data, Money::from_raw(100_000_000));
+        account.update_account(2, FundData::new(date!(2021 - 9 - 1), 10000, 10000, None));
+        let point = account.snapshot(date!(2021 - 9 - 1));
+        assert_eq!(point.market_value, Money::from_raw(100_000_000));
+        assert_eq!(point.total_value, Money::from_raw(100_000_000));
+        assert_eq!(point.invested, Money::from_raw(100_000_000));
+        assert_eq!(point.cash, Money::from_raw(-100_000_000));
+        assert_eq!(point.contribution, Money::from_raw(100_000_000));
+        assert_eq!(
+            point.holdings,
+            BTreeMap::from([(1, Money::from_raw(100_000_000)), (2, Money::ZERO)])
+        );
+        assert_eq!(
+            point.prices,
+            BTreeMap::from([(1, Price::from_raw(20000)), (2, Price::from_raw(10000))])
+        );
+
+        // 有现金计划时总资产包含账户余额
+        let mut account =
+            Account::<FundAccount>::with_cash(CashPlan::new(Money::from_raw(300_000_000)));
+        account.buy_with_cost(1, &data, Money::from_raw(100_000_000));
+        let point = account.snapshot(date!(2021 - 9 - 1));
+        assert_eq!(point.cash, Money::from_raw(200_000_000));
+        assert_eq!(point.total_value, Money::from_raw(300_000_000));
+        assert_eq!(point.contribution, Money::from_raw(300_000_000));
+    }
+
+    #[test]
+    fn test_record_same_day() {
+        let account = Account::<FundAccount>::new();
+        let mut curve = EquityCurve::new();
+        curve.record(account.snapshot(date!(2021 - 9 - 1)));
+        curve.record(account.snapshot(date!(2021 - 9 - 2)));
+        curve.record(account.snapshot(date!(2021 - 9 - 2)));
+        assert_eq!(curve.len(), 2);
+        assert_eq!(curve.last().unwrap().date, date!(2021 - 9 - 2));
+    }
+}
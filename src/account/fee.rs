@@ -0,0 +1,180 @@
+//! ## 基金费率
+//! ----
+//!
+//! + 申购费：前端收费，按外扣法计算，平台折扣直接作用于申购费率
+//! + 赎回费：按持有天数分档，超过最后一档的持有期免收
+//! + 管理费：已在每日净值中扣除，这里只用于估算持有期间承担的管理费
+//!
+//! 费率以百万分之一为单位保存，对外接口使用百分比
+
+use crate::decimal::Money;
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+/// 费率放大倍数
+pub(crate) const RATE_SCALE: u64 = 1_000_000;
+
+fn percent_to_rate(percent: f32) -> u32 {
+    (percent as f64 * RATE_SCALE as f64 / 100.0).round() as u32
+}
+
+/// 赎回费分档，持有天数小于max_days时适用该档费率
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RedemptionTier {
+    pub(crate) max_days: u32,
+    pub(crate) rate: u32,
+}
+
+/// 基金费率表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub(crate) subscription_rate: u32,          // 申购费率(原费率)
+    pub(crate) discount: u32,                   // 平台折扣，RATE_SCALE表示不打折
+    pub(crate) redemption: Vec<RedemptionTier>, // 赎回费分档，按持有天数升序
+    pub(crate) management_rate: u32,            // 管理费年费率
+}
+
+impl Default for FeeSchedule {
+    /// 不收取任何费用
+    fn default() -> Self {
+        FeeSchedule {
+            subscription_rate: 0,
+            discount: RATE_SCALE as u32,
+            redemption: Vec::new(),
+            management_rate: 0,
+        }
+    }
+}
+
+impl FeeSchedule {
+    /// 设置申购费率(百分比)及平台折扣(如0.1表示一折)
+    pub fn with_subscription(mut self, rate: f32, discount: f32) -> Self {
+        self.subscription_rate = percent_to_rate(rate);
+        self.discount = percent_to_rate(discount * 100.0);
+        self
+    }
+
+    /// 设置赎回费分档，每档为(持有天数上限, 费率百分比)
+    pub fn with_redemption(mut self, tiers: &[(u32, f32)]) -> Self {
+        self.redemption = tiers
+            .iter()
+            .map(|&(max_days, rate)| RedemptionTier {
+                max_days,
+                rate: percent_to_rate(rate),
+            })
+            .collect();
+        self.redemption.sort_by_key(|x| x.max_days);
+        self
+    }
+
+    /// 设置管理费年费率(百分比)
+    pub fn with_management(mut self, rate: f32) -> Self {
+        self.management_rate = percent_to_rate(rate);
+        self
+    }
+
+    /// 折扣后的实际申购费率
+    pub(crate) fn effective_subscription_rate(&self) -> u64 {
+        self.subscription_rate as u64 * self.discount as u64 / RATE_SCALE
+    }
+
+    /// 以申购金额(含申购费)买入时的申购费，外扣法：申购费 = 申购金额 - 申购金额 / (1 + 费率)
+    pub(crate) fn subscription_fee_on_budget(&self, budget: Money) -> Money {
+        let rate = self.effective_subscription_rate();
+        budget - budget.mul_ratio(RATE_SCALE, RATE_SCALE + rate)
+    }
+
+    /// 以净申购金额买入时的申购费
+    pub(crate) fn subscription_fee_on_amount(&self, amount: Money) -> Money {
+        amount.mul_ratio(self.effective_subscription_rate(), RATE_SCALE)
+    }
+
+    /// 持有指定天数后的赎回费率
+    pub(crate) fn redemption_rate(&self, hold_days: u32) -> u64 {
+        self.redemption
+            .iter()
+            .find(|x| hold_days < x.max_days)
+            .map_or(0, |x| x.rate as u64)
+    }
+
+    /// 持有指定天数后赎回指定金额的赎回费
+    pub(crate) fn redemption_fee(&self, amount: Money, hold_days: u32) -> Money {
+        amount.mul_ratio(self.redemption_rate(hold_days), RATE_SCALE)
+    }
+
+    /// 持有指定金额若干天所承担的管理费
+    pub(crate) fn management_fee(&self, value: Money, days: u32) -> Money {
+        value.mul_ratio(self.management_rate as u64 * days as u64, RATE_SCALE * 365)
+    }
+}
+
+/// 解析赎回费分档，格式为`天数:费率百分比`，以逗号分隔，如`7:1.5,365:0.5,730:0.25`
+pub fn parse_redemption_tiers(s: &str) -> Result<Vec<(u32, f32)>> {
+    s.split(',')
+        .filter(|x| !x.trim().is_empty())
+        .map(|tier| {
+            let (days, rate) = tier
+                .split_once(':')
+                .ok_or_else(|| anyhow!("redemption tier should be <DAYS>:<RATE>, got {}", tier))?;
+            Ok((days.trim().parse()?, rate.trim().parse()?))
+        })
+        .collect()
+}
+
+impl FromStr for FeeSchedule {
+    type Err = anyhow::Error;
+
+    /// 只包含赎回费分档的费率表
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(FeeSchedule::default().with_redemption(&parse_redemption_tiers(s)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_fee() {
+        // 1.5%的申购费打一折，实际0.15%
+        let fee = FeeSchedule::default().with_subscription(1.5, 0.1);
+        assert_eq!(fee.effective_subscription_rate(), 1500);
+        // 申购100元，净申购金额 100 / 1.0015 = 99.85
+        let budget = Money::from_raw(100_000_000);
+        assert_eq!(
+            fee.subscription_fee_on_budget(budget),
+            Money::from_raw(149_776)
+        );
+        assert_eq!(
+            fee.subscription_fee_on_amount(budget),
+            Money::from_raw(150_000)
+        );
+        assert_eq!(
+            FeeSchedule::default().subscription_fee_on_budget(budget),
+            Money::ZERO
+        );
+    }
+
+    #[test]
+    fn test_redemption_fee() {
+        let fee: FeeSchedule = "7:1.5,365:0.5,730:0.25".parse().unwrap();
+        assert_eq!(fee.redemption_rate(0), 15000);
+        assert_eq!(fee.redemption_rate(6), 15000);
+        assert_eq!(fee.redemption_rate(7), 5000);
+        assert_eq!(fee.redemption_rate(400), 2500);
+        assert_eq!(fee.redemption_rate(730), 0);
+        assert_eq!(
+            fee.redemption_fee(Money::from_raw(100_000_000), 3),
+            Money::from_raw(1_500_000)
+        );
+        assert!("7-1.5".parse::<FeeSchedule>().is_err());
+    }
+
+    #[test]
+    fn test_management_fee() {
+        let fee = FeeSchedule::default().with_management(1.5);
+        let value = Money::from_raw(365_000_000);
+        assert_eq!(fee.management_fee(value, 365), Money::from_raw(5_475_000));
+        assert_eq!(fee.management_fee(value, 1), Money::from_raw(15_000));
+    }
+}
@@ -1,91 +1,282 @@
-use super::{TradeDetail, TradeItem, UpdateAccountItem};
-use crate::market::fund_market::FundData;
-use time::macros::*;
+use super::fee::FeeSchedule;
+use super::{DividendItem, DividendPolicy, TradeDetail, TradeItem, UpdateAccountItem};
+use crate::decimal::{Money, Price, Shares};
+use crate::market::fund_market::{FundData, FundEvent};
+use std::collections::VecDeque;
+use time::{macros::*, Date};
 
-/// 基金账户信息，为避免浮点运算，部分值乘以一定倍数。
-/// 对外接口使用真实值
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
+/// 一次买入形成的持仓批次，卖出时按先进先出消耗
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HoldingLot {
+    pub(crate) date: Date,     // 确认日期，赎回费的持有期从该日起算
+    pub(crate) shares: Shares, // 剩余份额
+    pub(crate) cost: Money,    // 剩余份额的成本(含申购费)
+}
+
+/// 基金账户信息
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct FundAccount {
     // fund_code: u32,        // 基金代码
-    // 净值是其真实价值乘以10000,避免浮点数运算
-    pub(crate) net_value: u32,        // 单位净值
-    pub(crate) accumulate_value: u32, //累计净值
-    // 持有份额是其真实值乘以100,避免浮点数运算
-    pub(crate) shares: u32,     //持有份额
-    pub(crate) cash_bonus: u64, //现金分红，默认为红利再投
-    // 账面价值是真实值乘以1000000
-    pub(crate) total_value: u64,          //基金总价值
-    pub(crate) avg_price: Option<u32>,    //平均持仓价格
-    pub(crate) lowest_price: Option<u32>, //最低持仓价格
+    pub(crate) net_value: Price,                       // 单位净值
+    pub(crate) accumulate_value: Price,                //累计净值
+    pub(crate) shares: Shares,                         //持有份额
+    pub(crate) cash_bonus: Money,                      //累计现金分红
+    pub(crate) total_value: Money,                     //基金总价值
+    pub(crate) avg_price: Option<Price>,               //平均持仓价格
+    pub(crate) lowest_price: Option<Price>,            //最低持仓价格
+    pub(crate) fee: FeeSchedule,                       //费率
+    pub(crate) lots: VecDeque<HoldingLot>,             //持仓批次，按买入先后排序
+    pub(crate) last_date: Option<Date>,                //最近一次更新净值的日期
+    pub(crate) paid_fee: Money,                        //已支付的申购费和赎回费
+    pub(crate) management_fee: Money,                  //估算的已承担管理费，已体现在净值中
+    pub(crate) realized_pnl: Money,                    //已实现盈亏(扣除申购费和赎回费，含现金分红)
+    pub(crate) dividend_policy: DividendPolicy,        //分红方式，默认为红利再投
+    pub(crate) event_date: Option<Date>, //最近一次分红或拆分的日期，避免同一天重复处理
+    pub(crate) pending_dividend: Option<DividendItem>, //尚未记入交易记录的分红
+}
+
+/// 基金持仓设置
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FundSetting {
+    pub(crate) fee: FeeSchedule,                // 费率
+    pub(crate) dividend_policy: DividendPolicy, // 分红方式
 }
 
 impl FundAccount {
-    fn check_dividend(&mut self, data: &FundData) {
-        if data.dividend.is_some() {
-            // 红利再投
-            self.cash_bonus += data.dividend.unwrap() as u64 * self.shares as u64;
-            self.shares +=
-                (data.dividend.unwrap() as u64 * self.shares as u64 / data.unit_nav as u64) as u32;
-            #[cfg(test)]
-            println!(
-                "{}{}{} increase {}",
-                data.date.year(),
-                data.date.month(),
-                data.date.day(),
-                (data.dividend.unwrap() as u64 * self.shares as u64 / data.unit_nav as u64) as u32
-            )
+    /// 使用指定费率的空账户
+    pub(crate) fn with_fee(fee: FeeSchedule) -> Self {
+        FundAccount {
+            fee,
+            ..Default::default()
+        }
+    }
+
+    /// 处理净值日期发生的分红或拆分，同一天只处理一次
+    fn check_event(&mut self, data: &FundData) {
+        if data.event.is_none() || self.event_date == Some(data.date) {
+            return;
+        }
+        self.event_date = Some(data.date);
+        match data.event {
+            Some(FundEvent::Dividend(dividend)) => self.apply_dividend(data, dividend),
+            Some(FundEvent::Split(ratio)) => self.apply_split(data, ratio),
+            None => {}
+        }
+    }
+
+    /// 份额折算或拆分：各批次份额按比例增加、成本不变，平均持仓价格和最低持仓价格按比例降低
+    fn apply_split(&mut self, data: &FundData, ratio: u64) {
+        let scale = FundEvent::SPLIT_SCALE;
+        let untracked = self.untracked_shares().mul_ratio(ratio, scale);
+        for lot in self.lots.iter_mut() {
+            lot.shares = lot.shares.mul_ratio(ratio, scale);
+        }
+        self.shares = untracked + self.lots.iter().map(|x| x.shares).sum();
+        self.avg_price = self.avg_price.map(|x| x.mul_ratio(scale, ratio));
+        self.update_avg_price(data.date);
+        self.lowest_price = self.lowest_price.map(|x| x.mul_ratio(scale, ratio));
+    }
+
+    /// 除息日按分红方式处理分红：红利再投按除息日净值折算为成本为0的新批次，
+    /// 现金分红计入已实现盈亏，由Account计入账户余额
+    fn apply_dividend(&mut self, data: &FundData, dividend: Price) {
+        let amount = dividend * self.shares;
+        if amount.is_zero() {
+            return;
+        }
+        let reinvest_volume = match self.dividend_policy {
+            DividendPolicy::Reinvest => {
+                let increment = amount.shares_at(data.unit_nav);
+                self.shares += increment;
+                if !increment.is_zero() {
+                    self.lots.push_back(HoldingLot {
+                        date: data.date,
+                        shares: increment,
+                        cost: Money::ZERO,
+                    });
+                    self.update_avg_price(data.date);
+                }
+                increment
+            }
+            DividendPolicy::Cash => {
+                self.cash_bonus += amount;
+                self.realized_pnl += amount;
+                Shares::ZERO
+            }
+        };
+        self.pending_dividend = Some(DividendItem {
+            per_share: dividend,
+            amount,
+            policy: self.dividend_policy,
+            reinvest_volume,
+        });
+        #[cfg(test)]
+        println!(
+            "{}{}{} increase {}",
+            data.date.year(),
+            data.date.month(),
+            data.date.day(),
+            reinvest_volume
+        )
+    }
+
+    /// 按前一次净值日期到本次之间的天数估算管理费
+    fn accrue_management_fee(&mut self, data: &FundData) {
+        if let Some(last_date) = self.last_date {
+            let days = (data.date - last_date).whole_days().max(0) as u32;
+            self.management_fee += self.fee.management_fee(self.total_value, days);
+        }
+        self.last_date = Some(data.date);
+    }
+
+    /// 按净值更新持仓价值
+    fn update_value(&mut self, data: &FundData) {
+        self.net_value = data.unit_nav;
+        self.accumulate_value = data.accumulate_nav;
+        self.total_value = self.net_value * self.shares;
+    }
+
+    /// 没有批次记录的份额，如直接构造的账户中已有的持仓
+    fn untracked_shares(&self) -> Shares {
+        self.shares - self.lots.iter().map(|x| x.shares).sum()
+    }
+
+    /// 当前持仓的成本，没有批次记录的份额按平均持仓价格计算
+    fn holding_cost(&self) -> Money {
+        self.lots.iter().map(|x| x.cost).sum::<Money>()
+            + self.avg_price.unwrap_or_default() * self.untracked_shares()
+    }
+
+    /// 按持仓成本(含申购费)计算平均持仓价格，清仓后为None。
+    /// 没有批次记录的份额先按原平均持仓价格记为当日的批次，之后的成本不再随平均持仓价格变化
+    fn update_avg_price(&mut self, date: Date) {
+        let untracked = self.untracked_shares();
+        if !untracked.is_zero() {
+            self.lots.push_front(HoldingLot {
+                date,
+                shares: untracked,
+                cost: self.avg_price.unwrap_or_default() * untracked,
+            });
+        }
+        self.avg_price = self.holding_cost().price_per(self.shares);
+    }
+
+    /// 记录新的持仓批次，更新平均持仓价格和最低持仓价格
+    fn record_buy(&mut self, data: &FundData, volume: Shares, fee: Money) {
+        self.shares += volume;
+        self.update_value(data);
+        if self.lowest_price.is_none() || self.lowest_price.unwrap() > self.net_value {
+            self.lowest_price = Some(self.net_value)
+        }
+        self.paid_fee += fee;
+        if !volume.is_zero() {
+            self.lots.push_back(HoldingLot {
+                date: data.date,
+                shares: volume,
+                cost: data.unit_nav * volume + fee,
+            });
+        }
+        self.update_avg_price(data.date);
+    }
+
+    /// 按先进先出消耗持仓批次，每个批次按各自的持有天数计算赎回费，
+    /// 返回赎回费并累计已实现盈亏。没有批次记录的份额视为最早持有，按持有0天计算赎回费
+    fn record_sell(&mut self, data: &FundData, volume: Shares) -> Money {
+        let untracked = volume.min(self.untracked_shares());
+        let mut fee = self.fee.redemption_fee(data.unit_nav * untracked, 0);
+        let mut cost = self.avg_price.unwrap_or_default() * untracked;
+        let mut remain = volume - untracked;
+        while !remain.is_zero() {
+            let lot = match self.lots.front_mut() {
+                Some(lot) => lot,
+                None => break,
+            };
+            let take = remain.min(lot.shares);
+            let take_cost = lot
+                .cost
+                .mul_ratio(take.raw() as u64, lot.shares.raw() as u64);
+            let hold_days = (data.date - lot.date).whole_days().max(0) as u32;
+            fee += self.fee.redemption_fee(data.unit_nav * take, hold_days);
+            cost += take_cost;
+            lot.shares -= take;
+            lot.cost -= take_cost;
+            if lot.shares.is_zero() {
+                self.lots.pop_front();
+            }
+            remain -= take;
         }
+        self.realized_pnl += data.unit_nav * volume - fee - cost;
+        self.shares -= volume;
+        self.update_value(data);
+        self.update_avg_price(data.date);
+        self.paid_fee += fee;
+        fee
     }
 }
 
 impl UpdateAccountItem for FundAccount {
     type MarketData = FundData;
+    type Setting = FundSetting;
     // fn default() -> Self {}
     fn get_account_name(&self) -> String {
         String::from("fund")
     }
     fn update_account(&mut self, data: &FundData) {
-        self.check_dividend(data);
-        self.net_value = data.unit_nav;
-        self.accumulate_value = data.accumulate_nav;
-        self.total_value = self.net_value as u64 * self.shares as u64;
+        self.accrue_management_fee(data);
+        self.check_event(data);
+        self.update_value(data);
     }
 
-    fn get_current_volume(&self) -> u32 {
+    fn get_current_volume(&self) -> Shares {
         self.shares
     }
-    fn get_current_value(&self) -> u32 {
+    fn get_current_value(&self) -> Price {
         self.net_value
     }
 
-    fn get_current_asset(&self) -> u64 {
+    fn get_current_asset(&self) -> Money {
         self.total_value
     }
-    fn get_average_price(&self) -> Option<u32> {
+    fn get_average_price(&self) -> Option<Price> {
         self.avg_price
     }
-    fn get_lowest_price(&self) -> Option<u32> {
+    fn get_lowest_price(&self) -> Option<Price> {
         self.lowest_price
     }
+    fn get_realized_pnl(&self) -> Money {
+        self.realized_pnl
+    }
+    fn get_unrealized_pnl(&self) -> Money {
+        self.total_value - self.holding_cost()
+    }
+    fn get_management_fee(&self) -> Money {
+        self.management_fee
+    }
+    fn take_dividend(&mut self) -> Option<TradeDetail> {
+        self.pending_dividend.take().map(TradeDetail::Dividend)
+    }
+    fn apply_setting(&mut self, setting: &FundSetting) {
+        self.fee = setting.fee.clone();
+        self.dividend_policy = setting.dividend_policy;
+    }
 
-    fn buy_with_volume(&mut self, data: &FundData, volume: f32) -> TradeDetail {
-        self.check_dividend(data);
-        self.shares += (volume * 100.0) as u32;
-        self.net_value = data.unit_nav;
-        self.accumulate_value = data.accumulate_nav;
-        self.total_value = (self.net_value * self.shares) as u64;
-        self.avg_price = Some((self.total_value / self.shares as u64) as u32);
-        if self.lowest_price.is_none() || self.lowest_price.unwrap() > self.net_value {
-            self.lowest_price = Some(self.net_value)
-        }
+    fn buy_with_volume(&mut self, data: &FundData, volume: Shares) -> TradeDetail {
+        self.accrue_management_fee(data);
+        self.check_event(data);
+        let fee = self.fee.subscription_fee_on_amount(data.unit_nav * volume);
+        self.record_buy(data, volume, fee);
         TradeDetail::Buy(TradeItem {
             deal_price: self.net_value,
-            deal_volume: (volume * 100.0) as u32,
+            deal_volume: volume,
+            fee,
         })
     }
-    fn buy_with_cost(&mut self, data: &Self::MarketData, price: f32) -> TradeDetail {
-        self.check_dividend(data);
-        let increment = ((price / ((data.unit_nav as f32) * 0.0001)) * 100.0) as u32;
+    /// 申购金额包含申购费，扣除申购费后的净申购金额按净值折算份额，份额向下取整
+    fn buy_with_cost(&mut self, data: &Self::MarketData, budget: Money) -> TradeDetail {
+        self.accrue_management_fee(data);
+        self.check_event(data);
+        let fee = self.fee.subscription_fee_on_budget(budget);
+        let increment = (budget - fee).shares_at(data.unit_nav);
         #[cfg(test)]
         println!(
             "{}{}{} buy {}",
@@ -94,62 +285,45 @@ impl UpdateAccountItem for FundAccount {
             data.date.day(),
             increment
         );
-        self.shares += increment;
-        self.net_value = data.unit_nav;
-        self.accumulate_value = data.accumulate_nav;
-
-        self.total_value = self.net_value as u64 * self.shares as u64;
-        self.avg_price = Some((self.total_value / self.shares as u64) as u32);
-        if self.lowest_price.is_none() || self.lowest_price.unwrap() > self.net_value {
-            self.lowest_price = Some(self.net_value)
-        }
+        self.record_buy(data, increment, fee);
 
         TradeDetail::Buy(TradeItem {
             deal_price: self.net_value,
             deal_volume: increment,
+            fee,
         })
     }
-    fn sell_with_volume(&mut self, data: &FundData, volume: f32) -> TradeDetail {
+    fn sell_with_volume(&mut self, data: &FundData, volume: Shares) -> TradeDetail {
         // Todo :卖出当天能享受分红否？
-        self.check_dividend(data);
-        let decrement = if volume < self.shares as f32 * 0.01 {
-            (volume * 100.0) as u32
-        } else {
-            self.shares
-        };
-        self.shares -= decrement;
-        self.net_value = data.unit_nav;
-        self.accumulate_value = data.accumulate_nav;
-        self.total_value = (self.net_value * self.shares) as u64;
+        self.accrue_management_fee(data);
+        self.check_event(data);
+        let decrement = volume.min(self.shares);
+        let fee = self.record_sell(data, decrement);
         TradeDetail::Sell(TradeItem {
             deal_price: self.net_value,
             deal_volume: decrement,
+            fee,
         })
     }
 
     fn sell_with_proportion(&mut self, data: &FundData, proportion: f32) -> TradeDetail {
-        self.check_dividend(data);
-        self.net_value = data.unit_nav;
-        self.accumulate_value = data.accumulate_nav;
-        let sell_proportion = if proportion > 1.0 { 1.0 } else { proportion };
-        match sell_proportion {
-            f if (0.0..=1.0).contains(&f) => {
-                let sell_volume = (self.shares as f32 * proportion) as u32;
-                self.shares -= sell_volume;
-                self.total_value = (self.net_value * self.shares) as u64;
-                TradeDetail::Sell(TradeItem {
-                    deal_price: self.net_value,
-                    deal_volume: sell_volume,
-                })
-            }
-            _ => {
-                self.total_value = (self.net_value * self.shares) as u64;
-                TradeDetail::Sell(TradeItem {
-                    deal_price: 0,
-                    deal_volume: 0,
-                })
-            }
+        self.accrue_management_fee(data);
+        self.check_event(data);
+        if proportion < 0.0 {
+            self.update_value(data);
+            return TradeDetail::Sell(TradeItem {
+                deal_price: Price::ZERO,
+                deal_volume: Shares::ZERO,
+                fee: Money::ZERO,
+            });
         }
+        let sell_volume = self.shares.proportion(proportion as f64);
+        let fee = self.record_sell(data, sell_volume);
+        TradeDetail::Sell(TradeItem {
+            deal_price: self.net_value,
+            deal_volume: sell_volume,
+            fee,
+        })
     }
 }
 
@@ -160,77 +334,112 @@ mod tests {
     #[test]
     fn test_update_account_without_dividend_rise() {
         let mut account = FundAccount {
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
         account.update_account(&fund_data);
-        assert_eq!(account.total_value, 200000000);
+        assert_eq!(account.total_value, Money::from_raw(200000000));
     }
 
     #[test]
     fn test_update_account_without_dividend_fall() {
         let mut account = FundAccount {
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 10000, 30000, None);
         account.update_account(&fund_data);
-        assert_eq!(account.total_value, 100000000);
+        assert_eq!(account.total_value, Money::from_raw(100000000));
     }
 
     #[test]
     fn test_update_account_with_dividend() {
         let mut account = FundAccount {
             // fund_code: 002021,
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, Some(100));
         account.update_account(&fund_data);
-        assert_eq!(account.total_value, 201000000);
+        assert_eq!(account.total_value, Money::from_raw(201000000));
+    }
+
+    #[test]
+    fn test_update_account_with_cash_dividend() {
+        let mut account = FundAccount {
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            total_value: Money::from_raw(128800000),
+            dividend_policy: DividendPolicy::Cash,
+            ..Default::default()
+        };
+
+        let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, Some(100));
+        account.update_account(&fund_data);
+        // 同一天再次更新不重复分红
+        account.update_account(&fund_data);
+        assert_eq!(account.shares, Shares::from_raw(10000));
+        assert_eq!(account.total_value, Money::from_raw(200000000));
+        assert_eq!(account.cash_bonus, Money::from_raw(1000000));
+        assert_eq!(
+            account.take_dividend(),
+            Some(TradeDetail::Dividend(DividendItem {
+                per_share: Price::from_raw(100),
+                amount: Money::from_raw(1000000),
+                policy: DividendPolicy::Cash,
+                reinvest_volume: Shares::ZERO,
+            }))
+        );
+        assert_eq!(account.take_dividend(), None);
     }
 
     #[test]
     fn test_account_after_buy_volume() {
         let mut account = FundAccount {
             // fund_code: 002021,
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
 
         let expect = TradeDetail::Buy(TradeItem {
-            deal_price: 20000,
-            deal_volume: 10000,
+            deal_price: Price::from_raw(20000),
+            deal_volume: Shares::from_raw(10000),
+            fee: Money::ZERO,
         });
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
-        let detail = account.buy_with_volume(&fund_data, 100.0);
-        assert_eq!(account.shares, 20000);
-        assert_eq!(account.total_value, 400000000);
+        let detail = account.buy_with_volume(&fund_data, Shares::from_raw(10000));
+        assert_eq!(account.shares, Shares::from_raw(20000));
+        assert_eq!(account.total_value, Money::from_raw(400000000));
         assert_eq!(detail, expect);
     }
 
@@ -238,23 +447,25 @@ mod tests {
     fn test_account_after_buy_price() {
         let mut account = FundAccount {
             // fund_code: 002021,
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
         let expect = TradeDetail::Buy(TradeItem {
-            deal_price: 20000,
-            deal_volume: 10000,
+            deal_price: Price::from_raw(20000),
+            deal_volume: Shares::from_raw(10000),
+            fee: Money::ZERO,
         });
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
-        let detail = account.buy_with_cost(&fund_data, 200.0);
-        assert_eq!(account.shares, 20000);
-        assert_eq!(account.total_value, 400000000);
+        let detail = account.buy_with_cost(&fund_data, Money::from_raw(200000000));
+        assert_eq!(account.shares, Shares::from_raw(20000));
+        assert_eq!(account.total_value, Money::from_raw(400000000));
         assert_eq!(detail, expect);
     }
 
@@ -262,23 +473,25 @@ mod tests {
     fn test_account_after_sell_volume() {
         let mut account = FundAccount {
             // fund_code: 002021,
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
         let expect = TradeDetail::Sell(TradeItem {
-            deal_price: 20000,
-            deal_volume: 5000,
+            deal_price: Price::from_raw(20000),
+            deal_volume: Shares::from_raw(5000),
+            fee: Money::ZERO,
         });
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
-        let detail = account.sell_with_volume(&fund_data, 50.0);
-        assert_eq!(account.shares, 5000);
-        assert_eq!(account.total_value, 100000000);
+        let detail = account.sell_with_volume(&fund_data, Shares::from_raw(5000));
+        assert_eq!(account.shares, Shares::from_raw(5000));
+        assert_eq!(account.total_value, Money::from_raw(100000000));
         assert_eq!(detail, expect);
     }
 
@@ -286,24 +499,26 @@ mod tests {
     fn test_account_after_sell_proportion() {
         let mut account = FundAccount {
             // fund_code: 002021,
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
         let expect = TradeDetail::Sell(TradeItem {
-            deal_price: 20000,
-            deal_volume: 5000,
+            deal_price: Price::from_raw(20000),
+            deal_volume: Shares::from_raw(5000),
+            fee: Money::ZERO,
         });
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
         let detail = account.sell_with_proportion(&fund_data, 0.5);
         // println!("{:?}", account);
-        assert_eq!(account.shares, 5000);
-        assert_eq!(account.total_value, 100000000);
+        assert_eq!(account.shares, Shares::from_raw(5000));
+        assert_eq!(account.total_value, Money::from_raw(100000000));
         assert_eq!(detail, expect);
     }
 
@@ -311,27 +526,187 @@ mod tests {
     fn test_calc_hold_price() {
         let mut account = FundAccount {
             // fund_code: 002021,
-            net_value: 12880,
-            accumulate_value: 22880,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 128800000,
+            net_value: Price::from_raw(12880),
+            accumulate_value: Price::from_raw(22880),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(128800000),
             avg_price: None,
             lowest_price: None,
+            ..Default::default()
         };
         let expect = TradeDetail::Buy(TradeItem {
-            deal_price: 20000,
-            deal_volume: 10000,
+            deal_price: Price::from_raw(20000),
+            deal_volume: Shares::from_raw(10000),
+            fee: Money::ZERO,
         });
 
         let fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
-        let detail = account.buy_with_cost(&fund_data, 200.0);
-        assert_eq!(account.shares, 20000);
-        assert_eq!(account.total_value, 400000000);
+        let detail = account.buy_with_cost(&fund_data, Money::from_raw(200000000));
+        assert_eq!(account.shares, Shares::from_raw(20000));
+        assert_eq!(account.total_value, Money::from_raw(400000000));
         assert_eq!(detail, expect);
         let fund_data = FundData::new(date!(2021 - 9 - 30), 10000, 50000, None);
-        account.buy_with_cost(&fund_data, 200.0);
-        assert_eq!(account.lowest_price, Some(10000));
-        assert_eq!(account.avg_price, Some(10000));
+        account.buy_with_cost(&fund_data, Money::from_raw(200000000));
+        assert_eq!(account.lowest_price, Some(Price::from_raw(10000)));
+        assert_eq!(account.avg_price, Some(Price::from_raw(10000)));
+    }
+
+    #[test]
+    fn test_avg_price_from_cost() {
+        let mut account = FundAccount::default();
+        account.buy_with_cost(
+            &FundData::new(date!(2021 - 1 - 4), 20000, 20000, None),
+            Money::from_raw(100000000),
+        );
+        // 净值涨跌不影响平均持仓价格
+        account.update_account(&FundData::new(date!(2021 - 1 - 5), 25000, 25000, None));
+        assert_eq!(account.avg_price, Some(Price::from_raw(20000)));
+        account.buy_with_cost(
+            &FundData::new(date!(2021 - 1 - 6), 10000, 10000, None),
+            Money::from_raw(100000000),
+        );
+        // 200元买入150份
+        assert_eq!(account.shares, Shares::from_raw(15000));
+        assert_eq!(account.avg_price, Some(Price::from_raw(13333)));
+        // 先进先出卖出第一批，剩余第二批的成本
+        account.sell_with_volume(
+            &FundData::new(date!(2021 - 1 - 7), 12000, 12000, None),
+            Shares::from_raw(5000),
+        );
+        assert_eq!(account.avg_price, Some(Price::from_raw(10000)));
+    }
+
+    #[test]
+    fn test_buy_and_sell_with_fee() {
+        let fee = FeeSchedule::default()
+            .with_subscription(1.5, 0.1)
+            .with_redemption(&[(7, 1.5), (365, 0.5)]);
+        let mut account = FundAccount::with_fee(fee);
+
+        // 100元申购，申购费 100 - 100 / 1.0015 = 0.149776元
+        let fund_data = FundData::new(date!(2021 - 9 - 1), 20000, 30000, None);
+        let detail = account.buy_with_cost(&fund_data, Money::from_raw(100000000));
+        assert_eq!(
+            detail,
+            TradeDetail::Buy(TradeItem {
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(4992),
+                fee: Money::from_raw(149776),
+            })
+        );
+        assert_eq!(
+            detail.calc_cost_or_earning(),
+            Money::from_raw(-(99840000 + 149776))
+        );
+
+        // 持有不足7天，赎回费1.5%
+        let fund_data = FundData::new(date!(2021 - 9 - 3), 20000, 30000, None);
+        let detail = account.sell_with_volume(&fund_data, Shares::from_raw(1000));
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(1000),
+                fee: Money::from_raw(300000),
+            })
+        );
+        // 持有超过7天，赎回费0.5%
+        let fund_data = FundData::new(date!(2021 - 9 - 8), 20000, 30000, None);
+        let detail = account.sell_with_volume(&fund_data, Shares::from_raw(1000));
+        assert_eq!(
+            detail.calc_cost_or_earning(),
+            Money::from_raw(20000000 - 100000)
+        );
+        // 超过最后一档免赎回费
+        let fund_data = FundData::new(date!(2022 - 9 - 8), 20000, 30000, None);
+        let detail = account.sell_with_proportion(&fund_data, 1.0);
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(2992),
+                fee: Money::ZERO,
+            })
+        );
+        assert_eq!(account.paid_fee, Money::from_raw(549776));
+        assert!(account.lots.is_empty());
+        // 净值不变，亏损的正是申购费和赎回费
+        assert_eq!(account.get_realized_pnl(), Money::from_raw(-549776));
+    }
+
+    #[test]
+    fn test_sell_lots_fifo() {
+        let fee = FeeSchedule::default().with_redemption(&[(7, 1.5), (30, 0.5)]);
+        let mut account = FundAccount::with_fee(fee);
+        account.buy_with_cost(
+            &FundData::new(date!(2021 - 1 - 1), 10000, 10000, None),
+            Money::from_raw(100000000),
+        );
+        account.buy_with_cost(
+            &FundData::new(date!(2021 - 1 - 20), 10000, 10000, None),
+            Money::from_raw(100000000),
+        );
+        assert_eq!(account.lots.len(), 2);
+
+        // 第一批持有35天免赎回费，第二批持有16天按0.5%收取
+        let fund_data = FundData::new(date!(2021 - 2 - 5), 12500, 12500, None);
+        let detail = account.sell_with_volume(&fund_data, Shares::from_raw(15000));
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(12500),
+                deal_volume: Shares::from_raw(15000),
+                fee: Money::from_raw(312500),
+            })
+        );
+        assert_eq!(
+            account.lots,
+            VecDeque::from(vec![HoldingLot {
+                date: date!(2021 - 1 - 20),
+                shares: Shares::from_raw(5000),
+                cost: Money::from_raw(50000000),
+            }])
+        );
+        assert_eq!(
+            account.get_realized_pnl(),
+            Money::from_raw(187500000 - 312500 - 150000000)
+        );
+        assert_eq!(
+            account.get_unrealized_pnl(),
+            Money::from_raw(62500000 - 50000000)
+        );
+    }
+
+    #[test]
+    fn test_split_shares() {
+        let mut account = FundAccount::default();
+        account.buy_with_cost(
+            &FundData::new(date!(2021 - 1 - 4), 20000, 20000, None),
+            Money::from_raw(100000000),
+        );
+        // 每份折算为2份，净值减半，持仓价值和成本不变
+        let mut split = FundData::new(date!(2021 - 2 - 1), 10000, 20000, None);
+        split.event = Some(FundEvent::Split(2 * FundEvent::SPLIT_SCALE));
+        account.update_account(&split);
+        account.update_account(&split);
+        assert_eq!(account.shares, Shares::from_raw(10000));
+        assert_eq!(account.lots[0].shares, Shares::from_raw(10000));
+        assert_eq!(account.total_value, Money::from_raw(100000000));
+        assert_eq!(account.avg_price, Some(Price::from_raw(10000)));
+        assert_eq!(account.lowest_price, Some(Price::from_raw(10000)));
+        assert_eq!(account.get_unrealized_pnl(), Money::ZERO);
+    }
+
+    #[test]
+    fn test_management_fee_estimate() {
+        let mut account = FundAccount::with_fee(FeeSchedule::default().with_management(1.5));
+        account.buy_with_cost(
+            &FundData::new(date!(2021 - 1 - 1), 10000, 10000, None),
+            Money::from_raw(365000000),
+        );
+        account.update_account(&FundData::new(date!(2021 - 1 - 2), 10000, 10000, None));
+        account.update_account(&FundData::new(date!(2021 - 1 - 5), 10000, 10000, None));
+        assert_eq!(account.management_fee, Money::from_raw(60000));
     }
 }
@@ -17,6 +17,11 @@
 //! + get_current_asset: 获取当前资产,即持仓数量×持仓价格
 //! + get_average_price: 获取当前持仓均价,根据买入成本计算
 //! + get_lowest_price: 获取当前最低买入价格
+//! + get_realized_pnl: 获取已实现盈亏
+//! + get_unrealized_pnl: 获取持仓浮动盈亏，即当前资产减去持仓成本
+//! + get_management_fee: 获取估算的已承担管理费，只有基金有
+//! + take_dividend: 取出最近一次更新或交易时发生的分红
+//! + apply_setting: 按持仓设置(如基金的费率和分红方式)初始化持仓
 //! + buy_with_volume: 以指定数量买入，适用于股票账户
 //! + buy_with_cost: 以指定价格买入，适用于基金账户
 //! + sell_with_volume: 以指定数量卖出
@@ -29,21 +34,37 @@
 //! + hold_detail: 持仓详情，支持多个交易标的，key是股票/基金代码，value是具体信息，必须实现UpdateAccountItem
 //! + trade_history: 交易历史，支持多个交易标的，key是股票/基金代码，value是Vec<TradeHistory>，以时间先后排序
 //! + account_value: 持仓账面总价值
-//! + balance_value：账户余额,可能为负(一直买入未卖出)
+//! + balance_value：账户余额,没有现金计划(见cash模块)时假设资金无限，可能为负(一直买入未卖出)
+//! + cash_plan: 现金计划，包括初始资金、定期转入、资金不足时的处理方式和闲置资金收益
+//! + settings: 各标的的持仓设置，清仓后再次买入时新建的持仓仍按该设置初始化
+//! + closed_pnl: 已清仓标的的已实现盈亏，清仓后持仓详情被移除，盈亏仍然保留
+//! + closed_management_fee: 已清仓标的估算的已承担管理费，同closed_pnl一样保留
+//! + blocked_trades: 因行情的交易限制(见QuantitativeMarket::buy_limit/can_sell)未能成交的次数，
+//!   限制买入金额时按上限买入，不计入
 
+pub mod cash;
+pub mod equity;
+pub mod fee;
 pub mod fund_account;
 pub mod stock_account;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 // use serde::de;
-use crate::account::TradeDetail::{Buy, Sell};
-use time::{macros::*, PrimitiveDateTime};
+use crate::account::TradeDetail::{Buy, Dividend, Sell};
+use time::{macros::*, Date, PrimitiveDateTime};
 
+use crate::account::cash::{CashPlan, InsufficientFunds};
+use crate::account::fee::FeeSchedule;
+use crate::account::fund_account::{FundAccount, FundSetting};
+use crate::decimal::{Money, Price, Shares};
 use crate::market::QuantitativeMarket;
+use serde::Deserialize;
 
 /// 所有账户实现的方法，变更账户信息
 pub trait UpdateAccountItem {
     type MarketData: QuantitativeMarket;
+    /// 持仓设置，新建持仓时应用
+    type Setting: std::fmt::Debug + Default + Clone;
     /// 默认账户
     // fn default() -> Self;
     ///
@@ -51,45 +72,95 @@ pub trait UpdateAccountItem {
     /// 根据行情更新当前持仓信息
     fn update_account(&mut self, data: &Self::MarketData);
     /// 获取当前持仓数量
-    fn get_current_volume(&self) -> u32;
+    fn get_current_volume(&self) -> Shares;
     /// 获取当前持仓单价
-    fn get_current_value(&self) -> u32;
+    fn get_current_value(&self) -> Price;
     /// 获取当前资产
-    fn get_current_asset(&self) -> u64;
+    fn get_current_asset(&self) -> Money;
     /// 获取平均持仓价格
-    fn get_average_price(&self) -> Option<u32>;
+    fn get_average_price(&self) -> Option<Price>;
     /// 获取最低持仓价格
-    fn get_lowest_price(&self) -> Option<u32>;
+    fn get_lowest_price(&self) -> Option<Price>;
+    /// 获取已实现盈亏
+    fn get_realized_pnl(&self) -> Money;
+    /// 获取持仓浮动盈亏
+    fn get_unrealized_pnl(&self) -> Money;
+    /// 获取估算的已承担管理费
+    fn get_management_fee(&self) -> Money;
+    /// 取出最近一次更新或交易时发生的分红，没有分红时返回None
+    fn take_dividend(&mut self) -> Option<TradeDetail>;
+    /// 应用持仓设置
+    fn apply_setting(&mut self, setting: &Self::Setting);
     /// 以指定数量买入（适用于股票）,返回交易信息
-    fn buy_with_volume(&mut self, data: &Self::MarketData, volume: f32) -> TradeDetail;
+    fn buy_with_volume(&mut self, data: &Self::MarketData, volume: Shares) -> TradeDetail;
     /// 以总价买入（适用于基金），返回交易信息
-    fn buy_with_cost(&mut self, data: &Self::MarketData, price: f32) -> TradeDetail;
+    fn buy_with_cost(&mut self, data: &Self::MarketData, budget: Money) -> TradeDetail;
     /// 以指定数量卖出，返回交易信息
-    fn sell_with_volume(&mut self, data: &Self::MarketData, volume: f32) -> TradeDetail;
+    fn sell_with_volume(&mut self, data: &Self::MarketData, volume: Shares) -> TradeDetail;
     /// 以持仓比例卖出，返回交易信息
     fn sell_with_proportion(&mut self, data: &Self::MarketData, proportion: f32) -> TradeDetail;
 }
 /// 交易信息
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct TradeItem {
-    // 成交价格, * 10000
-    deal_price: u32,
-    // 成交数量, * 100
-    deal_volume: u32,
+    // 成交价格
+    pub(crate) deal_price: Price,
+    // 成交数量
+    pub(crate) deal_volume: Shares,
+    // 手续费(申购费、赎回费)
+    pub(crate) fee: Money,
+}
+
+/// 分红方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DividendPolicy {
+    /// 红利再投，按除息日净值折算为份额
+    #[default]
+    Reinvest,
+    /// 现金分红，计入账户余额
+    Cash,
+}
+
+/// 分红信息
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct DividendItem {
+    // 每份(股)分红
+    pub(crate) per_share: Price,
+    // 分红总额
+    pub(crate) amount: Money,
+    // 分红方式
+    pub(crate) policy: DividendPolicy,
+    // 红利再投增加的份额，现金分红时为0
+    pub(crate) reinvest_volume: Shares,
 }
 
 #[derive(Debug, PartialEq, PartialOrd)]
 pub enum TradeDetail {
     Buy(TradeItem),
     Sell(TradeItem),
+    Dividend(DividendItem),
 }
 
 impl TradeDetail {
-    //Todo :考虑手续费
-    fn calc_cost_or_earning(&self) -> i64 {
+    /// 买入支出成交金额和手续费，卖出收入成交金额减去手续费
+    pub(crate) fn calc_cost_or_earning(&self) -> Money {
         match self {
-            Self::Buy(detail) => -(detail.deal_price as i64 * detail.deal_volume as i64),
-            Self::Sell(detail) => (detail.deal_price as i64 * detail.deal_volume as i64),
+            Self::Buy(detail) => -(detail.deal_price * detail.deal_volume + detail.fee),
+            Self::Sell(detail) => detail.deal_price * detail.deal_volume - detail.fee,
+            // 红利再投不产生现金流
+            Self::Dividend(detail) => match detail.policy {
+                DividendPolicy::Cash => detail.amount,
+                DividendPolicy::Reinvest => Money::ZERO,
+            },
+        }
+    }
+
+    /// 手续费
+    pub(crate) fn get_fee(&self) -> Money {
+        match self {
+            Self::Buy(detail) | Self::Sell(detail) => detail.fee,
+            Self::Dividend(_) => Money::ZERO,
         }
     }
 }
@@ -97,11 +168,11 @@ impl TradeDetail {
 #[derive(Debug, PartialEq, PartialOrd)]
 pub struct TradeHistory {
     // 成交时间
-    trade_time: PrimitiveDateTime,
+    pub(crate) trade_time: PrimitiveDateTime,
     // 成交标的代码
-    trade_obj: u32,
+    pub(crate) trade_obj: u32,
     // 成交详情
-    trade_detail: TradeDetail,
+    pub(crate) trade_detail: TradeDetail,
 }
 
 /// ## 账户详情
@@ -113,67 +184,173 @@ pub struct Account<T: UpdateAccountItem> {
     pub(crate) hold_detail: HashMap<u32, T>,
     // 交易记录
     trade_history: HashMap<u32, Vec<TradeHistory>>,
-    // 账面价值, * 1000000
-    pub(crate) account_value: u64,
-    // 账户余额, * 1000000 Todo：对于回测，暂时先假设资金无限
-    pub(crate) balance_price: i64,
+    // 账面价值
+    pub(crate) account_value: Money,
+    // 账户余额，没有现金计划时假设资金无限
+    pub(crate) balance_price: Money,
+    // 投入本金，即累计买入成本减去卖出所得
+    pub(crate) invested: Money,
+    // 各标的的持仓设置
+    settings: HashMap<u32, T::Setting>,
+    // 已清仓标的的已实现盈亏
+    closed_pnl: HashMap<u32, Money>,
+    // 已清仓标的估算的已承担管理费
+    closed_management_fee: HashMap<u32, Money>,
+    // 现金计划，None表示资金无限
+    cash_plan: Option<CashPlan>,
+    // 最近一次结算现金的日期
+    cash_date: Option<Date>,
+    // 累计转入资金(含初始资金)
+    pub(crate) total_deposit: Money,
+    // 累计闲置资金收益
+    pub(crate) total_interest: Money,
+    // 因资金不足被拒绝的买入次数
+    pub(crate) rejected_buys: u32,
+    // 因暂停申购、暂停赎回等状态未能成交的次数
+    pub(crate) blocked_trades: u32,
 }
 
 impl<T> Account<T>
 where
-    T: UpdateAccountItem + Default,
+    T: UpdateAccountItem + Default + Clone,
 {
     /// 新建账户
     pub(crate) fn new() -> Self {
         Account {
             hold_detail: HashMap::<u32, T>::new(),
             trade_history: HashMap::<u32, Vec<TradeHistory>>::new(),
-            account_value: 0,
-            balance_price: 0,
+            account_value: Money::ZERO,
+            balance_price: Money::ZERO,
+            invested: Money::ZERO,
+            settings: HashMap::new(),
+            closed_pnl: HashMap::new(),
+            closed_management_fee: HashMap::new(),
+            cash_plan: None,
+            cash_date: None,
+            total_deposit: Money::ZERO,
+            total_interest: Money::ZERO,
+            rejected_buys: 0,
+            blocked_trades: 0,
+        }
+    }
+
+    /// 新建按现金计划管理资金的账户
+    pub(crate) fn with_cash(plan: CashPlan) -> Self {
+        let mut account = Self::new();
+        account.balance_price = plan.initial;
+        account.total_deposit = plan.initial;
+        account.cash_plan = Some(plan);
+        account
+    }
+
+    /// 结算到指定日期为止的闲置资金收益和定期转入
+    pub(crate) fn settle_cash(&mut self, date: Date) {
+        let plan = match &self.cash_plan {
+            Some(plan) => plan,
+            None => return,
+        };
+        if let Some(last) = self.cash_date {
+            if date <= last {
+                return;
+            }
+            let interest = plan.interest(self.balance_price, (date - last).whole_days() as u32);
+            let deposit = plan
+                .deposit
+                .mul_ratio(plan.deposits_between(last, date) as u64, 1);
+            self.balance_price += interest + deposit;
+            self.total_interest += interest;
+            self.total_deposit += deposit;
         }
+        self.cash_date = Some(date);
+    }
+
+    /// 可用于买入的现金，资金无限时返回None
+    fn available_cash(&self) -> Option<Money> {
+        self.cash_plan
+            .as_ref()
+            .map(|_| self.balance_price.max(Money::ZERO))
+    }
+
+    /// 资金不足时的处理方式
+    fn insufficient_funds(&self) -> InsufficientFunds {
+        self.cash_plan
+            .as_ref()
+            .map_or(InsufficientFunds::Reject, |x| x.insufficient)
     }
     /// 获取持仓单价
-    fn get_object_price(&self, code: u32) -> Option<u32> {
+    fn get_object_price(&self, code: u32) -> Option<Price> {
         self.hold_detail.get(&code).map(|x| x.get_current_value())
         // .map_or(None, |k| Some(k.get_current_value()))
     }
 
     /// 获取持仓数量
-    fn get_object_volume(&self, code: u32) -> Option<u32> {
+    pub(crate) fn get_object_volume(&self, code: u32) -> Option<Shares> {
         self.hold_detail.get(&code).map(|x| x.get_current_volume())
         // .map_or(None, |k| Some(k.get_current_volume()))
     }
 
     /// 获取持仓资产总价
-    fn get_object_assets(&self, code: u32) -> Option<u64> {
+    pub(crate) fn get_object_assets(&self, code: u32) -> Option<Money> {
         self.hold_detail.get(&code).map(|x| x.get_current_asset())
         // .map_or(None, |k| Some(k.get_current_asset()))
     }
+    /// 按标的的持仓设置新建持仓
+    fn new_holding(&self, code: u32) -> T {
+        let mut item = T::default();
+        if let Some(setting) = self.settings.get(&code) {
+            item.apply_setting(setting);
+        }
+        item
+    }
+
+    /// 获取持仓详情，没有持仓时新建
+    fn holding_mut(&mut self, code: u32) -> &mut T {
+        if !self.hold_detail.contains_key(&code) {
+            let item = self.new_holding(code);
+            self.hold_detail.insert(code, item);
+        }
+        self.hold_detail
+            .get_mut(&code)
+            .expect("holding created above")
+    }
+
     /// 更新资产价格
     pub(crate) fn update_account(&mut self, code: u32, info: T::MarketData) {
-        let item = self.hold_detail.entry(code).or_insert_with(T::default);
+        self.settle_cash(info.get_info_datetime().date());
+        let item = self.holding_mut(code);
         item.update_account(&info);
+        self.record_dividend(code, &info);
+    }
+
+    /// 按各标的的最新行情计算账面价值
+    pub(crate) fn update_account_value(&mut self) {
+        self.account_value = self
+            .hold_detail
+            .values()
+            .map(|x| x.get_current_asset())
+            .sum();
     }
 
     /// 获取平均持仓价格
-    pub(crate) fn get_object_average_price(&self, code: u32) -> Option<u32> {
+    pub(crate) fn get_object_average_price(&self, code: u32) -> Option<Price> {
         self.hold_detail
             .get(&code)
-            .map(|x| x.get_average_price().unwrap())
+            .and_then(|x| x.get_average_price())
     }
     /// 获取最低持仓价格
-    pub(crate) fn get_object_lowest_price(&self, code: u32) -> Option<u32> {
+    pub(crate) fn get_object_lowest_price(&self, code: u32) -> Option<Price> {
         self.hold_detail
             .get(&code)
-            .map(|x| x.get_lowest_price().unwrap())
+            .and_then(|x| x.get_lowest_price())
     }
-    /// 以指定数量标的买入
-    fn buy_with_volume(&mut self, code: u32, info: &T::MarketData, volume: f32) {
-        let item = self.hold_detail.entry(code).or_insert_with(T::default);
-        let detail = item.buy_with_volume(info, volume);
+
+    /// 记录交易信息并更新账户余额
+    fn record_trade(&mut self, code: u32, info: &T::MarketData, detail: TradeDetail) {
         // 更新账户余额
         self.balance_price += detail.calc_cost_or_earning();
-        // 更新账户资产
+        if let Buy(_) | Sell(_) = detail {
+            self.invested -= detail.calc_cost_or_earning();
+        }
         // 记录交易信息
         let history = self
             .trade_history
@@ -186,70 +363,204 @@ where
         });
     }
 
-    /// 以指定总价买入
-    pub(crate) fn buy_with_cost(&mut self, code: u32, info: &T::MarketData, price: f32) {
-        let item = self.hold_detail.entry(code).or_insert_with(T::default);
-        let detail = item.buy_with_cost(info, price);
-        // 更新账户余额
-        self.balance_price += detail.calc_cost_or_earning();
-        // 记录交易信息
-        let history = self
-            .trade_history
-            .entry(code)
-            .or_insert(Vec::<TradeHistory>::new());
-        history.push(TradeHistory {
-            trade_time: info.get_info_datetime(),
-            trade_obj: code,
-            trade_detail: detail,
-        });
+    /// 记录持仓在本次更新或交易时发生的分红，现金分红计入账户余额
+    fn record_dividend(&mut self, code: u32, info: &T::MarketData) {
+        if let Some(detail) = self
+            .hold_detail
+            .get_mut(&code)
+            .and_then(|item| item.take_dividend())
+        {
+            self.record_trade(code, info, detail);
+        }
     }
 
-    /// 以当前价格卖出指定数量
-    fn sell_with_volume(&mut self, code: u32, info: &T::MarketData, volume: f32) {
-        if let Some(item) = self.hold_detail.get_mut(&code) {
+    /// 资金不足拒绝买入，仍然按行情更新持仓
+    fn reject_buy(&mut self, code: u32, info: &T::MarketData) {
+        self.rejected_buys += 1;
+        self.update_account(code, *info);
+    }
+
+    /// 行情不允许交易，仍然按行情更新持仓
+    fn block_trade(&mut self, code: u32, info: &T::MarketData) {
+        self.blocked_trades += 1;
+        self.update_account(code, *info);
+    }
+
+    /// 以指定数量标的买入，超过单笔买入上限或资金不足时先试算成交金额，再按比例减少买入数量
+    pub(crate) fn buy_with_volume(&mut self, code: u32, info: &T::MarketData, volume: Shares) {
+        self.settle_cash(info.get_info_datetime().date());
+        let volume = match info.buy_limit() {
+            Some(limit) if limit <= Money::ZERO => {
+                self.block_trade(code, info);
+                return;
+            }
+            Some(limit) => {
+                let mut trial = match self.hold_detail.get(&code) {
+                    Some(item) => item.clone(),
+                    None => self.new_holding(code),
+                };
+                let cost = -trial.buy_with_volume(info, volume).calc_cost_or_earning();
+                if cost > limit {
+                    volume.mul_ratio(limit.raw() as u64, cost.raw() as u64)
+                } else {
+                    volume
+                }
+            }
+            None => volume,
+        };
+        let available = self.available_cash();
+        let insufficient = self.insufficient_funds();
+        let item = self.holding_mut(code);
+        let detail = match available {
+            None => item.buy_with_volume(info, volume),
+            Some(available) => {
+                let mut trial = item.clone();
+                let detail = trial.buy_with_volume(info, volume);
+                let cost = -detail.calc_cost_or_earning();
+                if cost <= available {
+                    *item = trial;
+                    detail
+                } else if insufficient == InsufficientFunds::PartialFill && available > Money::ZERO
+                {
+                    let volume = volume.mul_ratio(available.raw() as u64, cost.raw() as u64);
+                    item.buy_with_volume(info, volume)
+                } else {
+                    self.reject_buy(code, info);
+                    return;
+                }
+            }
+        };
+        self.record_dividend(code, info);
+        self.record_trade(code, info, detail);
+    }
+
+    /// 以指定总价买入，超过单笔买入上限时按上限买入，资金不足时拒绝买入或用剩余现金买入
+    pub(crate) fn buy_with_cost(&mut self, code: u32, info: &T::MarketData, budget: Money) {
+        self.settle_cash(info.get_info_datetime().date());
+        let budget = match info.buy_limit() {
+            Some(limit) if limit <= Money::ZERO => {
+                self.block_trade(code, info);
+                return;
+            }
+            Some(limit) => budget.min(limit),
+            None => budget,
+        };
+        let budget = match self.available_cash() {
+            Some(available) if budget > available => {
+                if self.insufficient_funds() == InsufficientFunds::PartialFill
+                    && available > Money::ZERO
+                {
+                    available
+                } else {
+                    self.reject_buy(code, info);
+                    return;
+                }
+            }
+            _ => budget,
+        };
+        let item = self.holding_mut(code);
+        let detail = item.buy_with_cost(info, budget);
+        self.record_dividend(code, info);
+        self.record_trade(code, info, detail);
+    }
+
+    /// 以当前价格卖出指定数量，不能卖出时只更新持仓
+    pub(crate) fn sell_with_volume(&mut self, code: u32, info: &T::MarketData, volume: Shares) {
+        self.settle_cash(info.get_info_datetime().date());
+        if !info.can_sell() && self.hold_detail.contains_key(&code) {
+            self.block_trade(code, info);
+        } else if let Some(item) = self.hold_detail.get_mut(&code) {
             let detail = item.sell_with_volume(info, volume);
-            // 更新账户余额
-            self.balance_price += detail.calc_cost_or_earning();
-            // 记录交易信息
-            let history = self
-                .trade_history
-                .entry(code)
-                .or_insert(Vec::<TradeHistory>::new());
-            history.push(TradeHistory {
-                trade_time: info.get_info_datetime(),
-                trade_obj: code,
-                trade_detail: detail,
-            });
+            self.record_dividend(code, info);
+            self.record_trade(code, info, detail);
             // 检查是否全部卖出
-            if item.get_current_volume() == 0 {
-                self.hold_detail.remove_entry(&code);
+            if self.get_object_volume(code) == Some(Shares::ZERO) {
+                self.close_position(code);
             }
         }
     }
 
-    /// 以持仓比例卖出
-    fn sell_with_proportion(&mut self, code: u32, info: &T::MarketData, proportion: f32) {
-        if let Some(item) = self.hold_detail.get_mut(&code) {
+    /// 以持仓比例卖出，不能卖出时只更新持仓
+    pub(crate) fn sell_with_proportion(
+        &mut self,
+        code: u32,
+        info: &T::MarketData,
+        proportion: f32,
+    ) {
+        self.settle_cash(info.get_info_datetime().date());
+        if !info.can_sell() && self.hold_detail.contains_key(&code) {
+            self.block_trade(code, info);
+        } else if let Some(item) = self.hold_detail.get_mut(&code) {
             let detail = item.sell_with_proportion(info, proportion);
-            // 更新账户余额
-            self.balance_price += detail.calc_cost_or_earning();
-            // 记录交易信息
-            let history = self
-                .trade_history
-                .entry(code)
-                .or_insert(Vec::<TradeHistory>::new());
-            history.push(TradeHistory {
-                trade_time: info.get_info_datetime(),
-                trade_obj: code,
-                trade_detail: detail,
-            });
+            self.record_dividend(code, info);
+            self.record_trade(code, info, detail);
             // 检查是否全部卖出
-            if (proportion - 1.0).abs() < 0.0001 {
-                self.hold_detail.remove_entry(&code);
+            if self.get_object_volume(code) == Some(Shares::ZERO) {
+                self.close_position(code);
             }
         }
     }
 
+    /// 清仓，移除持仓详情并保留已实现盈亏和已承担的管理费
+    fn close_position(&mut self, code: u32) {
+        if let Some(item) = self.hold_detail.remove(&code) {
+            *self.closed_pnl.entry(code).or_default() += item.get_realized_pnl();
+            *self.closed_management_fee.entry(code).or_default() += item.get_management_fee();
+        }
+    }
+
+    /// 获取标的的已实现盈亏，包括已清仓的部分
+    pub(crate) fn get_object_realized_pnl(&self, code: u32) -> Option<Money> {
+        let closed = self.closed_pnl.get(&code).copied();
+        let holding = self.hold_detail.get(&code).map(|x| x.get_realized_pnl());
+        match (closed, holding) {
+            (None, None) => None,
+            (closed, holding) => Some(closed.unwrap_or_default() + holding.unwrap_or_default()),
+        }
+    }
+
+    /// 获取标的的持仓浮动盈亏
+    pub(crate) fn get_object_unrealized_pnl(&self, code: u32) -> Option<Money> {
+        self.hold_detail.get(&code).map(|x| x.get_unrealized_pnl())
+    }
+
+    /// 按日期汇总的买卖净额，买入成本为正，卖出所得为负，不含分红
+    pub(crate) fn trade_flows(&self) -> BTreeMap<Date, Money> {
+        let mut flows = BTreeMap::new();
+        for history in self.trade_history.values().flatten() {
+            if let Buy(_) | Sell(_) = history.trade_detail {
+                *flows.entry(history.trade_time.date()).or_default() -=
+                    history.trade_detail.calc_cost_or_earning();
+            }
+        }
+        flows
+    }
+
+    /// 按时间先后排列的全部交易记录
+    pub(crate) fn trade_history(&self) -> Vec<&TradeHistory> {
+        let mut history: Vec<_> = self.trade_history.values().flatten().collect();
+        history.sort_by_key(|x| (x.trade_time, x.trade_obj));
+        history
+    }
+
+    /// 估算的已承担管理费总额，包括已清仓的部分
+    pub(crate) fn get_management_fee(&self) -> Money {
+        self.hold_detail
+            .values()
+            .map(|x| x.get_management_fee())
+            .chain(self.closed_management_fee.values().copied())
+            .sum()
+    }
+
+    /// 已支付的手续费总额
+    pub(crate) fn get_total_fee(&self) -> Money {
+        self.trade_history
+            .values()
+            .flatten()
+            .map(|x| x.trade_detail.get_fee())
+            .sum()
+    }
+
     /// 显示详细持仓情况
     pub(crate) fn show_hold_detail(&self) {
         let _account = T::default();
@@ -260,11 +571,22 @@ where
         );
         for (k, v) in &self.hold_detail {
             println!(
-                "{code:0>6}: {value:.2}",
+                "{code:0>6}: {value}, realized {realized}, unrealized {unrealized}",
                 code = k,
-                value = v.get_current_asset() as f64 * 0.000001
+                value = v.get_current_asset(),
+                realized = self.get_object_realized_pnl(*k).unwrap_or_default(),
+                unrealized = v.get_unrealized_pnl()
             );
         }
+        for (k, v) in &self.closed_pnl {
+            if !self.hold_detail.contains_key(k) {
+                println!(
+                    "{code:0>6}: closed, realized {realized}",
+                    code = k,
+                    realized = v
+                );
+            }
+        }
     }
 
     /// 显示详细交易信息
@@ -287,20 +609,30 @@ where
                     match &x.trade_detail {
                         Buy(item) => {
                             format!(
-                                "buy {:.2} with {:.2}",
-                                item.deal_volume as f32 * 0.01,
-                                (item.deal_price as u64 * item.deal_volume as u64) as f64
-                                    * 0.000001
+                                "buy {} with {}, fee {}",
+                                item.deal_volume,
+                                item.deal_price * item.deal_volume,
+                                item.fee
                             )
                         }
                         Sell(item) => {
                             format!(
-                                "sell {:.2} at {:.2}",
-                                item.deal_volume as f32 * 0.01,
-                                (item.deal_price as u64 * item.deal_volume as u64) as f64
-                                    * 0.000001
+                                "sell {} at {}, fee {}",
+                                item.deal_volume,
+                                item.deal_price * item.deal_volume,
+                                item.fee
                             )
                         }
+                        Dividend(item) => match item.policy {
+                            DividendPolicy::Reinvest => format!(
+                                "dividend {} per share, reinvest {} into {} shares",
+                                item.per_share, item.amount, item.reinvest_volume
+                            ),
+                            DividendPolicy::Cash => format!(
+                                "dividend {} per share, receive {} in cash",
+                                item.per_share, item.amount
+                            ),
+                        },
                     },
                 )
             })
@@ -308,12 +640,41 @@ where
     }
 }
 
+impl Account<FundAccount> {
+    /// 设置基金费率，之后的交易(包括清仓后再次买入)按该费率收费
+    pub(crate) fn set_fee_schedule(&mut self, code: u32, fee: FeeSchedule) {
+        if let Some(item) = self.hold_detail.get_mut(&code) {
+            item.fee = fee.clone();
+        }
+        self.settings.entry(code).or_default().fee = fee;
+    }
+
+    /// 设置基金的分红方式，之后的分红(包括清仓后再次买入)按该方式处理
+    pub(crate) fn set_dividend_policy(&mut self, code: u32, policy: DividendPolicy) {
+        if let Some(item) = self.hold_detail.get_mut(&code) {
+            item.dividend_policy = policy;
+        }
+        self.settings.entry(code).or_default().dividend_policy = policy;
+    }
+
+    /// 按基金设置(费率及分红方式)初始化持仓
+    pub(crate) fn apply_setting(&mut self, code: u32, setting: &FundSetting) {
+        self.set_fee_schedule(code, setting.fee.clone());
+        self.set_dividend_policy(code, setting.dividend_policy);
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use crate::market::fund_market::FundData;
 
-    use super::{fund_account::FundAccount, *};
+    use super::{
+        cash::{CashPlan, InsufficientFunds},
+        fund_account::{FundAccount, HoldingLot},
+        *,
+    };
+    use std::collections::VecDeque;
 
     /// ### 基金账户测试
     /// ----
@@ -335,22 +696,30 @@ mod test {
         let expect_hold_detail = FundAccount {
             net_value: fund_data.unit_nav,
             accumulate_value: fund_data.accumulate_nav,
-            shares: 5000,
-            cash_bonus: 0,
-            total_value: 100000000,
-            avg_price: Some(20000),
-            lowest_price: Some(20000),
+            shares: Shares::from_raw(5000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(100000000),
+            avg_price: Some(Price::from_raw(20000)),
+            lowest_price: Some(Price::from_raw(20000)),
+            lots: VecDeque::from(vec![HoldingLot {
+                date: date!(2021 - 9 - 30),
+                shares: Shares::from_raw(5000),
+                cost: Money::from_raw(100000000),
+            }]),
+            last_date: Some(date!(2021 - 9 - 30)),
+            ..Default::default()
         };
         let expect_trade_history = TradeHistory {
             trade_time: fund_data.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 1,
             trade_detail: TradeDetail::Buy(TradeItem {
-                deal_price: 20000,
-                deal_volume: 5000,
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(5000),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data, 100.0);
+        account.buy_with_cost(000001, &fund_data, Money::from_raw(100000000));
 
         assert!(account.hold_detail.get(&000001).is_some());
         assert!(account.hold_detail.get(&000002).is_none());
@@ -369,23 +738,38 @@ mod test {
         let expect_hold_detail = FundAccount {
             net_value: fund_data2.unit_nav,
             accumulate_value: fund_data2.accumulate_nav,
-            shares: 10000,
-            cash_bonus: 0,
-            total_value: 200000000,
-            avg_price: Some(20000),
-            lowest_price: Some(20000),
+            shares: Shares::from_raw(10000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(200000000),
+            avg_price: Some(Price::from_raw(20000)),
+            lowest_price: Some(Price::from_raw(20000)),
+            lots: VecDeque::from(vec![
+                HoldingLot {
+                    date: date!(2021 - 9 - 30),
+                    shares: Shares::from_raw(5000),
+                    cost: Money::from_raw(100000000),
+                },
+                HoldingLot {
+                    date: date!(2021 - 10 - 1),
+                    shares: Shares::from_raw(5000),
+                    cost: Money::from_raw(100000000),
+                },
+            ]),
+            last_date: Some(date!(2021 - 10 - 1)),
+            ..Default::default()
         };
         let expect_trade_history = TradeHistory {
             trade_time: fund_data2.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 1,
             trade_detail: TradeDetail::Buy(TradeItem {
-                deal_price: 20000,
-                deal_volume: 5000,
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(5000),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
-        account.buy_with_cost(000001, &fund_data2, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
+        account.buy_with_cost(000001, &fund_data2, Money::from_raw(100000000));
 
         assert!(account.hold_detail.get(&000001).is_some());
         assert!(account.hold_detail.get(&000002).is_none());
@@ -404,23 +788,31 @@ mod test {
         let expect_hold_detail = FundAccount {
             net_value: fund_data2.unit_nav,
             accumulate_value: fund_data2.accumulate_nav,
-            shares: 5000,
-            cash_bonus: 0,
-            total_value: 100000000,
-            avg_price: Some(20000),
-            lowest_price: Some(20000),
+            shares: Shares::from_raw(5000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(100000000),
+            avg_price: Some(Price::from_raw(20000)),
+            lowest_price: Some(Price::from_raw(20000)),
+            lots: VecDeque::from(vec![HoldingLot {
+                date: date!(2021 - 10 - 1),
+                shares: Shares::from_raw(5000),
+                cost: Money::from_raw(100000000),
+            }]),
+            last_date: Some(date!(2021 - 10 - 1)),
+            ..Default::default()
         };
         let expect_trade_history = TradeHistory {
             trade_time: fund_data2.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 2,
             trade_detail: TradeDetail::Buy(TradeItem {
-                deal_price: 20000,
-                deal_volume: 5000,
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(5000),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
-        account.buy_with_cost(000002, &fund_data2, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
+        account.buy_with_cost(000002, &fund_data2, Money::from_raw(100000000));
 
         assert!(account.hold_detail.get(&000001).is_some());
         assert!(account.hold_detail.get(&000002).is_some());
@@ -437,15 +829,33 @@ mod test {
         let fund_data1 = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
         let fund_data2 = FundData::new(date!(2021 - 10 - 1), 20000, 30000, None);
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
-        account.buy_with_cost(000002, &fund_data2, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
+        account.buy_with_cost(000002, &fund_data2, Money::from_raw(100000000));
 
-        assert_eq!(Some(5000), account.get_object_volume(000001));
-        assert_eq!(Some(20000), account.get_object_price(000001));
-        assert_eq!(Some(100000000), account.get_object_assets(000001));
-        assert_eq!(Some(5000), account.get_object_volume(000002));
-        assert_eq!(Some(20000), account.get_object_price(000002));
-        assert_eq!(Some(100000000), account.get_object_assets(000002));
+        assert_eq!(
+            Some(Shares::from_raw(5000)),
+            account.get_object_volume(000001)
+        );
+        assert_eq!(
+            Some(Price::from_raw(20000)),
+            account.get_object_price(000001)
+        );
+        assert_eq!(
+            Some(Money::from_raw(100000000)),
+            account.get_object_assets(000001)
+        );
+        assert_eq!(
+            Some(Shares::from_raw(5000)),
+            account.get_object_volume(000002)
+        );
+        assert_eq!(
+            Some(Price::from_raw(20000)),
+            account.get_object_price(000002)
+        );
+        assert_eq!(
+            Some(Money::from_raw(100000000)),
+            account.get_object_assets(000002)
+        );
     }
 
     #[test]
@@ -456,24 +866,32 @@ mod test {
         let expect_hold_detail = FundAccount {
             net_value: fund_data1.unit_nav,
             accumulate_value: fund_data1.accumulate_nav,
-            shares: 5000,
-            cash_bonus: 0,
-            total_value: 100000000,
-            avg_price: Some(20000),
-            lowest_price: Some(20000),
+            shares: Shares::from_raw(5000),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(100000000),
+            avg_price: Some(Price::from_raw(20000)),
+            lowest_price: Some(Price::from_raw(20000)),
+            lots: VecDeque::from(vec![HoldingLot {
+                date: date!(2021 - 9 - 30),
+                shares: Shares::from_raw(5000),
+                cost: Money::from_raw(100000000),
+            }]),
+            last_date: Some(date!(2021 - 9 - 30)),
+            ..Default::default()
         };
         let expect_trade_history = TradeHistory {
             trade_time: fund_data1.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 1,
             trade_detail: TradeDetail::Buy(TradeItem {
-                deal_price: 20000,
-                deal_volume: 5000,
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(5000),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
 
-        account.sell_with_volume(000002, &fund_data2, 50.0);
+        account.sell_with_volume(000002, &fund_data2, Shares::from_raw(5000));
         assert_eq!(expect_hold_detail, account.hold_detail[&000001]);
         assert_eq!(
             expect_trade_history,
@@ -489,30 +907,38 @@ mod test {
         let expect_hold_detail = FundAccount {
             net_value: fund_data2.unit_nav,
             accumulate_value: fund_data2.accumulate_nav,
-            shares: 2500,
-            cash_bonus: 0,
-            total_value: 50000000,
-            avg_price: Some(20000),
-            lowest_price: Some(20000),
+            shares: Shares::from_raw(2500),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(50000000),
+            avg_price: Some(Price::from_raw(20000)),
+            lowest_price: Some(Price::from_raw(20000)),
+            lots: VecDeque::from(vec![HoldingLot {
+                date: date!(2021 - 9 - 30),
+                shares: Shares::from_raw(2500),
+                cost: Money::from_raw(50000000),
+            }]),
+            last_date: Some(date!(2021 - 10 - 1)),
+            ..Default::default()
         };
         let expect_trade_history = TradeHistory {
             trade_time: fund_data2.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 1,
             trade_detail: TradeDetail::Sell(TradeItem {
-                deal_price: 20000,
-                deal_volume: 2500,
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(2500),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
 
-        account.sell_with_volume(000001, &fund_data2, 25.0);
+        account.sell_with_volume(000001, &fund_data2, Shares::from_raw(2500));
         assert_eq!(expect_hold_detail, account.hold_detail[&000001]);
         assert_eq!(
             &expect_trade_history,
             account.trade_history.get_mut(&1).unwrap().last().unwrap()
         );
-        assert_eq!(-50000000, account.balance_price);
+        assert_eq!(Money::from_raw(-50000000), account.balance_price);
     }
 
     #[test]
@@ -523,22 +949,30 @@ mod test {
         let expect_hold_detail = FundAccount {
             net_value: fund_data2.unit_nav,
             accumulate_value: fund_data2.accumulate_nav,
-            shares: 2500,
-            cash_bonus: 0,
-            total_value: 50000000,
-            avg_price: Some(20000),
-            lowest_price: Some(20000),
+            shares: Shares::from_raw(2500),
+            cash_bonus: Money::ZERO,
+            total_value: Money::from_raw(50000000),
+            avg_price: Some(Price::from_raw(20000)),
+            lowest_price: Some(Price::from_raw(20000)),
+            lots: VecDeque::from(vec![HoldingLot {
+                date: date!(2021 - 9 - 30),
+                shares: Shares::from_raw(2500),
+                cost: Money::from_raw(50000000),
+            }]),
+            last_date: Some(date!(2021 - 10 - 1)),
+            ..Default::default()
         };
         let expect_trade_history = TradeHistory {
             trade_time: fund_data2.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 1,
             trade_detail: TradeDetail::Sell(TradeItem {
-                deal_price: 20000,
-                deal_volume: 2500,
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(2500),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
 
         account.sell_with_proportion(000001, &fund_data2, 0.5);
         assert_eq!(expect_hold_detail, account.hold_detail[&000001]);
@@ -546,7 +980,7 @@ mod test {
             &expect_trade_history,
             account.trade_history.get_mut(&1).unwrap().last().unwrap()
         );
-        assert_eq!(-50000000, account.balance_price);
+        assert_eq!(Money::from_raw(-50000000), account.balance_price);
     }
 
     #[test]
@@ -558,12 +992,13 @@ mod test {
             trade_time: fund_data2.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 1,
             trade_detail: TradeDetail::Sell(TradeItem {
-                deal_price: 20000,
-                deal_volume: 5000,
+                deal_price: Price::from_raw(20000),
+                deal_volume: Shares::from_raw(5000),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
 
         account.sell_with_proportion(000001, &fund_data2, 1.0);
         assert_eq!(None, account.hold_detail.get(&000001));
@@ -571,7 +1006,218 @@ mod test {
             &expect_trade_history,
             account.trade_history.get_mut(&1).unwrap().last().unwrap()
         );
-        assert_eq!(0, account.balance_price);
+        assert_eq!(Money::ZERO, account.balance_price);
+    }
+
+    #[test]
+    fn test_realized_and_unrealized_pnl() {
+        let mut account = Account::<FundAccount>::new();
+        account.buy_with_cost(
+            1,
+            &FundData::new(date!(2021 - 9 - 30), 20000, 30000, None),
+            Money::from_raw(100000000),
+        );
+        account.sell_with_proportion(
+            1,
+            &FundData::new(date!(2021 - 10 - 8), 25000, 35000, None),
+            0.5,
+        );
+        assert_eq!(
+            Some(Money::from_raw(12500000)),
+            account.get_object_realized_pnl(1)
+        );
+        assert_eq!(
+            Some(Money::from_raw(12500000)),
+            account.get_object_unrealized_pnl(1)
+        );
+
+        // 清仓后已实现盈亏仍然保留
+        account.sell_with_proportion(
+            1,
+            &FundData::new(date!(2021 - 10 - 11), 30000, 40000, None),
+            1.0,
+        );
+        assert_eq!(
+            Some(Money::from_raw(37500000)),
+            account.get_object_realized_pnl(1)
+        );
+        assert_eq!(None, account.get_object_unrealized_pnl(1));
+        assert_eq!(None, account.get_object_realized_pnl(2));
+    }
+
+    #[test]
+    fn test_dividend_policy() {
+        let fund_data1 = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
+        let fund_data2 = FundData::new(date!(2021 - 10 - 8), 20000, 30000, Some(1000));
+
+        // 现金分红计入账户余额
+        let mut account = Account::<FundAccount>::new();
+        account.set_dividend_policy(1, DividendPolicy::Cash);
+        account.buy_with_cost(1, &fund_data1, Money::from_raw(100000000));
+        account.update_account(1, fund_data2);
+        assert_eq!(Money::from_raw(-95000000), account.balance_price);
+        assert_eq!(Some(Shares::from_raw(5000)), account.get_object_volume(1));
+        assert_eq!(
+            TradeDetail::Dividend(DividendItem {
+                per_share: Price::from_raw(1000),
+                amount: Money::from_raw(5000000),
+                policy: DividendPolicy::Cash,
+                reinvest_volume: Shares::ZERO,
+            }),
+            account.trade_history[&1].last().unwrap().trade_detail
+        );
+
+        // 红利再投按除息日净值折算份额，不影响账户余额
+        let mut account = Account::<FundAccount>::new();
+        account.buy_with_cost(1, &fund_data1, Money::from_raw(100000000));
+        account.update_account(1, fund_data2);
+        assert_eq!(Money::from_raw(-100000000), account.balance_price);
+        assert_eq!(Some(Shares::from_raw(5250)), account.get_object_volume(1));
+        assert_eq!(
+            TradeDetail::Dividend(DividendItem {
+                per_share: Price::from_raw(1000),
+                amount: Money::from_raw(5000000),
+                policy: DividendPolicy::Reinvest,
+                reinvest_volume: Shares::from_raw(250),
+            }),
+            account.trade_history[&1].last().unwrap().trade_detail
+        );
+    }
+
+    #[test]
+    fn test_setting_survives_close() {
+        let mut account = Account::<FundAccount>::new();
+        account.apply_setting(
+            1,
+            &FundSetting {
+                fee: FeeSchedule::default()
+                    .with_subscription(1.5, 1.0)
+                    .with_management(1.5),
+                dividend_policy: DividendPolicy::Cash,
+            },
+        );
+        let fee_of = |account: &Account<FundAccount>| {
+            account.trade_history[&1]
+                .last()
+                .unwrap()
+                .trade_detail
+                .get_fee()
+        };
+        account.buy_with_cost(
+            1,
+            &FundData::new(date!(2021 - 1 - 4), 10000, 10000, None),
+            Money::from_raw(100000000),
+        );
+        let first_fee = fee_of(&account);
+        assert!(first_fee > Money::ZERO);
+        account.update_account(1, FundData::new(date!(2021 - 1 - 8), 10000, 10000, None));
+        account.sell_with_proportion(
+            1,
+            &FundData::new(date!(2021 - 1 - 11), 10000, 10000, None),
+            1.0,
+        );
+        assert_eq!(None, account.get_object_volume(1));
+        // 清仓后已承担的管理费仍然计入
+        let management_fee = account.get_management_fee();
+        assert!(management_fee > Money::ZERO);
+
+        // 再次买入仍按设置的费率收费，分红仍为现金分红
+        account.buy_with_cost(
+            1,
+            &FundData::new(date!(2021 - 2 - 1), 10000, 10000, None),
+            Money::from_raw(100000000),
+        );
+        assert_eq!(first_fee, fee_of(&account));
+        assert_eq!(
+            DividendPolicy::Cash,
+            account.hold_detail[&1].dividend_policy
+        );
+        account.update_account(1, FundData::new(date!(2021 - 2 - 2), 10000, 10000, None));
+        assert!(account.get_management_fee() > management_fee);
+    }
+
+    #[test]
+    fn test_trade_flows() {
+        let fund_data1 = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
+        let fund_data2 = FundData::new(date!(2021 - 10 - 8), 20000, 30000, Some(1000));
+        let mut account = Account::<FundAccount>::new();
+        account.buy_with_cost(1, &fund_data1, Money::from_raw(100000000));
+        account.buy_with_cost(2, &fund_data1, Money::from_raw(50000000));
+        // 分红不计入买卖净额
+        account.sell_with_proportion(1, &fund_data2, 0.5);
+        assert_eq!(
+            account.trade_flows(),
+            BTreeMap::from([
+                (date!(2021 - 9 - 30), Money::from_raw(150000000)),
+                (date!(2021 - 10 - 8), Money::from_raw(-52500000)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_insufficient_cash() {
+        let fund_data1 = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
+        let fund_data2 = FundData::new(date!(2021 - 10 - 8), 20000, 30000, None);
+
+        let mut account =
+            Account::<FundAccount>::with_cash(CashPlan::new(Money::from_raw(150_000_000)));
+        account.buy_with_cost(1, &fund_data1, Money::from_raw(100000000));
+        account.buy_with_cost(1, &fund_data2, Money::from_raw(100000000));
+        assert_eq!(1, account.rejected_buys);
+        assert_eq!(Some(Shares::from_raw(5000)), account.get_object_volume(1));
+        assert_eq!(Money::from_raw(50000000), account.balance_price);
+
+        let plan = CashPlan::new(Money::from_raw(150_000_000))
+            .with_insufficient(InsufficientFunds::PartialFill);
+        let mut account = Account::<FundAccount>::with_cash(plan);
+        account.buy_with_cost(1, &fund_data1, Money::from_raw(100000000));
+        account.buy_with_cost(1, &fund_data2, Money::from_raw(100000000));
+        assert_eq!(0, account.rejected_buys);
+        assert_eq!(Some(Shares::from_raw(7500)), account.get_object_volume(1));
+        assert_eq!(Money::ZERO, account.balance_price);
+    }
+
+    #[test]
+    fn test_fund_status_blocks_trades() {
+        use crate::market::fund_market::FundStatus;
+
+        let mut account = Account::<FundAccount>::new();
+        let mut fund_data = FundData::new(date!(2021 - 9 - 30), 20000, 30000, None);
+        fund_data.buy_status = Some(FundStatus::BuySuspended);
+        account.buy_with_cost(1, &fund_data, Money::from_raw(100000000));
+        assert_eq!(1, account.blocked_trades);
+        assert_eq!(Some(Shares::ZERO), account.get_object_volume(1));
+
+        // 限制大额申购时按上限买入
+        fund_data.buy_status = Some(FundStatus::BuyLimited(Some(Money::from_raw(10000000))));
+        fund_data.sell_status = Some(FundStatus::SellSuspended);
+        account.buy_with_cost(1, &fund_data, Money::from_raw(100000000));
+        assert_eq!(Some(Shares::from_raw(500)), account.get_object_volume(1));
+        account.buy_with_volume(1, &fund_data, Shares::from_raw(1000));
+        assert_eq!(Some(Shares::from_raw(1000)), account.get_object_volume(1));
+        assert_eq!(1, account.blocked_trades);
+
+        account.sell_with_proportion(1, &fund_data, 1.0);
+        account.sell_with_volume(1, &fund_data, Shares::from_raw(500));
+        assert_eq!(3, account.blocked_trades);
+        assert_eq!(Some(Shares::from_raw(1000)), account.get_object_volume(1));
+
+        fund_data.sell_status = Some(FundStatus::SellOpen);
+        account.sell_with_proportion(1, &fund_data, 1.0);
+        assert_eq!(None, account.get_object_volume(1));
+    }
+
+    #[test]
+    fn test_cash_deposit_and_interest() {
+        let plan = CashPlan::new(Money::from_raw(365_000_000))
+            .with_deposit(Money::from_raw(100_000_000), 15)
+            .with_yield(2.0);
+        let mut account = Account::<FundAccount>::with_cash(plan);
+        account.update_account(1, FundData::new(date!(2021 - 9 - 30), 20000, 30000, None));
+        account.update_account(1, FundData::new(date!(2021 - 10 - 31), 20000, 30000, None));
+        assert_eq!(Money::from_raw(620000), account.total_interest);
+        assert_eq!(Money::from_raw(465000000), account.total_deposit);
+        assert_eq!(Money::from_raw(465620000), account.balance_price);
     }
 
     #[test]
@@ -583,12 +1229,13 @@ mod test {
             trade_time: fund_data2.date.with_hms(19, 0, 0).unwrap(),
             trade_obj: 1,
             trade_detail: TradeDetail::Sell(TradeItem {
-                deal_price: 2000000,
-                deal_volume: 50000000,
+                deal_price: Price::from_raw(2000000),
+                deal_volume: Shares::from_raw(50000000),
+                fee: Money::ZERO,
             }),
         };
 
-        account.buy_with_cost(000001, &fund_data1, 100.0);
+        account.buy_with_cost(000001, &fund_data1, Money::from_raw(100000000));
 
         account.sell_with_proportion(000001, &fund_data2, 1.0);
         account.show_hold_detail();
@@ -1 +1,387 @@
+use super::{DividendItem, DividendPolicy, TradeDetail, TradeItem, UpdateAccountItem};
+use crate::decimal::{Money, Price, Shares};
+use crate::market::stock_market::StockData;
+use time::{macros::*, Date};
 
+/// 一手的股数
+const BOARD_LOT: Shares = Shares::from_raw(100 * Shares::SCALE);
+
+/// 股票账户信息
+/// A股实行T+1交易，当日买入的股票下一交易日才能卖出
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StockAccount {
+    pub(crate) price: Price,                // 最新价格
+    pub(crate) shares: Shares,              // 持股数量
+    pub(crate) today_shares: Shares,        // 当日买入，不可卖出的数量
+    pub(crate) last_date: Option<Date>,     // 最近一次更新行情的日期
+    pub(crate) cash_bonus: Money,           // 现金分红
+    pub(crate) total_value: Money,          // 股票总价值
+    pub(crate) avg_price: Option<Price>,    // 平均持仓价格
+    pub(crate) lowest_price: Option<Price>, // 最低持仓价格
+    pub(crate) realized_pnl: Money,         // 已实现盈亏，含现金分红
+    pending_dividend: Option<DividendItem>, // 尚未记入交易记录的现金分红
+    cost: Money,                            // 持仓成本，用于计算平均持仓价格
+}
+
+impl StockAccount {
+    /// 进入新的交易日时，前一交易日买入的股票变为可卖，并处理当日的除权除息
+    fn check_date(&mut self, data: &StockData) {
+        if self.last_date != Some(data.date) {
+            self.today_shares = Shares::ZERO;
+            self.last_date = Some(data.date);
+            self.check_ex_rights(data);
+        }
+    }
+
+    /// 除权除息：派现计入现金分红，送转股增加持股数量
+    fn check_ex_rights(&mut self, data: &StockData) {
+        if let Some(ex_rights) = data.ex_rights {
+            let amount = ex_rights.cash_dividend * self.shares;
+            if !amount.is_zero() {
+                self.cash_bonus += amount;
+                self.realized_pnl += amount;
+                self.pending_dividend = Some(DividendItem {
+                    per_share: ex_rights.cash_dividend,
+                    amount,
+                    policy: DividendPolicy::Cash,
+                    reinvest_volume: Shares::ZERO,
+                });
+            }
+            self.shares += self.shares.mul_ratio(ex_rights.bonus_ratio as u64, 10000);
+        }
+    }
+
+    fn update_price(&mut self, data: &StockData) {
+        self.price = data.close;
+        self.total_value = self.price * self.shares;
+    }
+
+    /// 可卖出的数量
+    fn sellable_shares(&self) -> Shares {
+        self.shares - self.today_shares
+    }
+
+    fn buy_lots(&mut self, data: &StockData, lots: u32) -> TradeDetail {
+        self.check_date(data);
+        let increment = BOARD_LOT.mul_ratio(lots as u64, 1);
+        self.shares += increment;
+        self.today_shares += increment;
+        self.cost += data.close * increment;
+        self.update_price(data);
+        if let Some(avg_price) = self.cost.price_per(self.shares) {
+            self.avg_price = Some(avg_price);
+        }
+        if !increment.is_zero()
+            && (self.lowest_price.is_none() || self.lowest_price > Some(data.close))
+        {
+            self.lowest_price = Some(data.close)
+        }
+        TradeDetail::Buy(TradeItem {
+            deal_price: data.close,
+            deal_volume: increment,
+            fee: Money::ZERO,
+        })
+    }
+
+    /// 卖出指定数量，不足一手的零股只能一次性卖出
+    fn sell_shares(&mut self, data: &StockData, volume: Shares) -> TradeDetail {
+        let sellable = self.sellable_shares();
+        let decrement = if volume >= sellable {
+            sellable
+        } else {
+            BOARD_LOT.mul_ratio((volume.raw() / BOARD_LOT.raw()) as u64, 1)
+        };
+        if !self.shares.is_zero() {
+            let sold_cost = self
+                .cost
+                .mul_ratio(decrement.raw() as u64, self.shares.raw() as u64);
+            self.cost -= sold_cost;
+            self.realized_pnl += data.close * decrement - sold_cost;
+        }
+        self.shares -= decrement;
+        self.update_price(data);
+        TradeDetail::Sell(TradeItem {
+            deal_price: data.close,
+            deal_volume: decrement,
+            fee: Money::ZERO,
+        })
+    }
+}
+
+impl UpdateAccountItem for StockAccount {
+    type MarketData = StockData;
+    type Setting = ();
+
+    fn get_account_name(&self) -> String {
+        String::from("stock")
+    }
+
+    fn update_account(&mut self, data: &StockData) {
+        self.check_date(data);
+        self.update_price(data);
+    }
+
+    fn get_current_volume(&self) -> Shares {
+        self.shares
+    }
+
+    fn get_current_value(&self) -> Price {
+        self.price
+    }
+
+    fn get_current_asset(&self) -> Money {
+        self.total_value
+    }
+
+    fn get_average_price(&self) -> Option<Price> {
+        self.avg_price
+    }
+
+    fn get_lowest_price(&self) -> Option<Price> {
+        self.lowest_price
+    }
+
+    fn get_realized_pnl(&self) -> Money {
+        self.realized_pnl
+    }
+
+    fn get_unrealized_pnl(&self) -> Money {
+        self.total_value - self.cost
+    }
+
+    fn get_management_fee(&self) -> Money {
+        Money::ZERO
+    }
+
+    fn take_dividend(&mut self) -> Option<TradeDetail> {
+        self.pending_dividend.take().map(TradeDetail::Dividend)
+    }
+
+    fn apply_setting(&mut self, _setting: &()) {}
+
+    /// 按收盘价买入，数量向下取整到整手
+    fn buy_with_volume(&mut self, data: &StockData, volume: Shares) -> TradeDetail {
+        self.buy_lots(data, volume.raw() / BOARD_LOT.raw())
+    }
+
+    /// 按收盘价买入，在总价之内尽可能多买整手
+    fn buy_with_cost(&mut self, data: &StockData, budget: Money) -> TradeDetail {
+        let lot_price = data.close * BOARD_LOT;
+        let lots = if lot_price > Money::ZERO && budget > Money::ZERO {
+            (budget.raw() / lot_price.raw()) as u32
+        } else {
+            0
+        };
+        self.buy_lots(data, lots)
+    }
+
+    /// 以指定数量卖出，当日买入的部分不可卖出
+    fn sell_with_volume(&mut self, data: &StockData, volume: Shares) -> TradeDetail {
+        self.check_date(data);
+        self.sell_shares(data, volume)
+    }
+
+    /// 以持仓比例卖出，当日买入的部分不可卖出
+    fn sell_with_proportion(&mut self, data: &StockData, proportion: f32) -> TradeDetail {
+        self.check_date(data);
+        let volume = self.shares.proportion(proportion as f64);
+        self.sell_shares(data, volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+
+    fn bar(date: Date, close: u32) -> StockData {
+        StockData::new(date, close, close, close, close, 1000000)
+    }
+
+    #[test]
+    fn test_buy_with_volume_round_to_lot() {
+        let mut account = StockAccount::default();
+        let detail =
+            account.buy_with_volume(&bar(date!(2021 - 9 - 1), 100000), Shares::from_raw(25000));
+        assert_eq!(
+            detail,
+            TradeDetail::Buy(TradeItem {
+                deal_price: Price::from_raw(100000),
+                deal_volume: Shares::from_raw(20000),
+                fee: Money::ZERO,
+            })
+        );
+        assert_eq!(account.shares, Shares::from_raw(20000));
+        assert_eq!(account.total_value, Money::from_raw(2000000000));
+    }
+
+    #[test]
+    fn test_buy_with_cost_round_to_lot() {
+        let mut account = StockAccount::default();
+        // 10.00元一股，一手1000元，3500元只能买3手
+        let detail = account.buy_with_cost(
+            &bar(date!(2021 - 9 - 1), 100000),
+            Money::from_raw(3500000000),
+        );
+        assert_eq!(
+            detail,
+            TradeDetail::Buy(TradeItem {
+                deal_price: Price::from_raw(100000),
+                deal_volume: Shares::from_raw(30000),
+                fee: Money::ZERO,
+            })
+        );
+        let detail = account.buy_with_cost(
+            &bar(date!(2021 - 9 - 2), 100000),
+            Money::from_raw(999000000),
+        );
+        assert_eq!(
+            detail,
+            TradeDetail::Buy(TradeItem {
+                deal_price: Price::from_raw(100000),
+                deal_volume: Shares::ZERO,
+                fee: Money::ZERO,
+            })
+        );
+        assert_eq!(account.shares, Shares::from_raw(30000));
+    }
+
+    #[test]
+    fn test_t_plus_one() {
+        let mut account = StockAccount::default();
+        account.buy_with_volume(&bar(date!(2021 - 9 - 1), 100000), Shares::from_raw(30000));
+        // 当日买入不可卖出
+        let detail =
+            account.sell_with_volume(&bar(date!(2021 - 9 - 1), 100000), Shares::from_raw(10000));
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(100000),
+                deal_volume: Shares::ZERO,
+                fee: Money::ZERO,
+            })
+        );
+        // 次日再买入，只有前一日的持仓可卖
+        account.buy_with_volume(&bar(date!(2021 - 9 - 2), 110000), Shares::from_raw(10000));
+        let detail = account.sell_with_proportion(&bar(date!(2021 - 9 - 2), 110000), 1.0);
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(110000),
+                deal_volume: Shares::from_raw(30000),
+                fee: Money::ZERO,
+            })
+        );
+        assert_eq!(account.shares, Shares::from_raw(10000));
+        account.update_account(&bar(date!(2021 - 9 - 3), 120000));
+        let detail =
+            account.sell_with_volume(&bar(date!(2021 - 9 - 3), 120000), Shares::from_raw(10000));
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(120000),
+                deal_volume: Shares::from_raw(10000),
+                fee: Money::ZERO,
+            })
+        );
+        assert_eq!(account.shares, Shares::ZERO);
+        assert_eq!(
+            account.get_realized_pnl(),
+            Money::from_raw(30000 * 10000 + 10000 * 10000)
+        );
+    }
+
+    #[test]
+    fn test_sell_odd_lot() {
+        let mut account = StockAccount::default();
+        account.buy_with_volume(&bar(date!(2021 - 9 - 1), 100000), Shares::from_raw(10000));
+        let mut data = bar(date!(2021 - 9 - 2), 100000);
+        // 10送3
+        data.ex_rights = Some(crate::market::stock_market::ExRights {
+            cash_dividend: Price::from_raw(1000),
+            bonus_ratio: 3000,
+        });
+        account.update_account(&data);
+        assert_eq!(account.shares, Shares::from_raw(13000));
+        assert_eq!(account.cash_bonus, Money::from_raw(10000000));
+        // 零股不足一手时向下取整
+        let detail =
+            account.sell_with_volume(&bar(date!(2021 - 9 - 3), 100000), Shares::from_raw(12000));
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(100000),
+                deal_volume: Shares::from_raw(10000),
+                fee: Money::ZERO,
+            })
+        );
+        // 剩余零股一次性卖出
+        let detail =
+            account.sell_with_volume(&bar(date!(2021 - 9 - 3), 100000), Shares::from_raw(3000));
+        assert_eq!(
+            detail,
+            TradeDetail::Sell(TradeItem {
+                deal_price: Price::from_raw(100000),
+                deal_volume: Shares::from_raw(3000),
+                fee: Money::ZERO,
+            })
+        );
+    }
+
+    #[test]
+    fn test_ex_rights_once_per_day() {
+        let mut account = Account::<StockAccount>::new();
+        account.buy_with_volume(
+            1,
+            &bar(date!(2021 - 9 - 1), 100000),
+            Shares::from_raw(10000),
+        );
+        let mut data = bar(date!(2021 - 9 - 2), 100000);
+        // 10派1送3，同一根K线上先更新行情再买卖，只除权除息一次
+        data.ex_rights = Some(crate::market::stock_market::ExRights {
+            cash_dividend: Price::from_raw(1000),
+            bonus_ratio: 3000,
+        });
+        account.update_account(1, data);
+        account.buy_with_volume(1, &data, Shares::from_raw(10000));
+        account.sell_with_volume(1, &data, Shares::from_raw(10000));
+        let item = account.hold_detail[&1];
+        assert_eq!(item.shares, Shares::from_raw(13000));
+        assert_eq!(item.cash_bonus, Money::from_raw(10000000));
+        let dividends = account
+            .trade_history()
+            .into_iter()
+            .filter(|x| matches!(x.trade_detail, TradeDetail::Dividend(_)))
+            .count();
+        assert_eq!(dividends, 1);
+    }
+
+    #[test]
+    fn test_sell_all_keeps_today_shares() {
+        let mut account = Account::<StockAccount>::new();
+        account.buy_with_volume(
+            1,
+            &bar(date!(2021 - 9 - 1), 100000),
+            Shares::from_raw(10000),
+        );
+        account.buy_with_volume(
+            1,
+            &bar(date!(2021 - 9 - 2), 100000),
+            Shares::from_raw(10000),
+        );
+        // 全部卖出时当日买入的部分不能卖出，不能当作清仓
+        account.sell_with_proportion(1, &bar(date!(2021 - 9 - 2), 100000), 1.0);
+        assert_eq!(account.get_object_volume(1), Some(Shares::from_raw(10000)));
+        account.sell_with_proportion(1, &bar(date!(2021 - 9 - 3), 100000), 1.0);
+        assert_eq!(account.get_object_volume(1), None);
+    }
+
+    #[test]
+    fn test_calc_hold_price() {
+        let mut account = StockAccount::default();
+        account.buy_with_volume(&bar(date!(2021 - 9 - 1), 100000), Shares::from_raw(10000));
+        account.buy_with_volume(&bar(date!(2021 - 9 - 2), 50000), Shares::from_raw(10000));
+        assert_eq!(account.avg_price, Some(Price::from_raw(75000)));
+        assert_eq!(account.lowest_price, Some(Price::from_raw(50000)));
+    }
+}
@@ -0,0 +1,160 @@
+//! ## 业绩基准比较
+//! ----
+//!
+//! 业绩基准以与策略相同的买卖净额进行回测(见fund_strategy::ReplayStrategy)，
+//! 再按日期对齐两条资产曲线的逐日收益率进行比较：
+//! + excess_return: 策略与基准时间加权收益率之差
+//! + excess_xirr: 策略与基准资金加权收益率之差
+//! + tracking_error: 逐日超额收益率的年化标准差
+//! + information_ratio: 年化超额收益率与跟踪误差之比
+//! + beta: 策略逐日收益率对基准逐日收益率的回归系数
+//! + alpha: 年化的詹森alpha，即扣除beta部分后的超额收益
+
+use super::performance::{
+    daily_returns, mean, number, percent, std_dev, Performance, TRADING_DAYS,
+};
+use crate::account::equity::EquityCurve;
+use std::collections::HashMap;
+
+/// 策略相对业绩基准的表现，数据不足以计算的指标为None
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub(crate) benchmark: Performance,
+    pub(crate) excess_return: f64,
+    pub(crate) excess_xirr: Option<f64>,
+    pub(crate) tracking_error: Option<f64>,
+    pub(crate) information_ratio: Option<f64>,
+    pub(crate) alpha: Option<f64>,
+    pub(crate) beta: Option<f64>,
+}
+
+impl Comparison {
+    /// 比较策略与基准的资产曲线，risk_free为年化无风险利率，如0.02
+    pub fn evaluate(strategy: &EquityCurve, benchmark: &EquityCurve, risk_free: f64) -> Self {
+        let own = Performance::evaluate(strategy, risk_free);
+        let other = Performance::evaluate(benchmark, risk_free);
+        let benchmark_returns: HashMap<_, _> = daily_returns(benchmark).into_iter().collect();
+        let (own_returns, other_returns): (Vec<f64>, Vec<f64>) = daily_returns(strategy)
+            .into_iter()
+            .filter_map(|(date, r)| benchmark_returns.get(&date).map(|b| (r, *b)))
+            .unzip();
+        let active: Vec<f64> = own_returns
+            .iter()
+            .zip(&other_returns)
+            .map(|(r, b)| r - b)
+            .collect();
+        let tracking_error = std_dev(&active).map(|x| x * TRADING_DAYS.sqrt());
+        let information_ratio = match (mean(&active), tracking_error) {
+            (Some(active), Some(error)) if error > 0.0 => Some(active * TRADING_DAYS / error),
+            _ => None,
+        };
+        let beta = beta(&own_returns, &other_returns);
+        let daily_risk_free = risk_free / TRADING_DAYS;
+        let alpha = match (beta, mean(&own_returns), mean(&other_returns)) {
+            (Some(beta), Some(own), Some(other)) => {
+                Some((own - daily_risk_free - beta * (other - daily_risk_free)) * TRADING_DAYS)
+            }
+            _ => None,
+        };
+        Comparison {
+            excess_return: own.twr - other.twr,
+            excess_xirr: own.xirr.zip(other.xirr).map(|(a, b)| a - b),
+            tracking_error,
+            information_ratio,
+            alpha,
+            beta,
+            benchmark: other,
+        }
+    }
+
+    /// 显示比较结果，code为业绩基准的代码
+    pub(crate) fn show(&self, code: u32) {
+        println!(
+            "benchmark {:0>6}: xirr: {}, time-weighted return: {}, annualized return: {}",
+            code,
+            percent(self.benchmark.xirr),
+            percent(Some(self.benchmark.twr)),
+            percent(self.benchmark.annualized_return)
+        );
+        println!(
+            "excess return: {}, excess xirr: {}, tracking error: {}, information ratio: {}",
+            percent(Some(self.excess_return)),
+            percent(self.excess_xirr),
+            percent(self.tracking_error),
+            number(self.information_ratio)
+        );
+        println!(
+            "alpha: {}, beta: {}",
+            percent(self.alpha),
+            number(self.beta)
+        );
+    }
+}
+
+/// 协方差与基准方差之比，基准没有波动时返回None
+fn beta(own: &[f64], other: &[f64]) -> Option<f64> {
+    let (own_mean, other_mean) = (mean(own)?, mean(other)?);
+    let covariance: f64 = own
+        .iter()
+        .zip(other)
+        .map(|(r, b)| (r - own_mean) * (b - other_mean))
+        .sum();
+    let variance: f64 = other.iter().map(|b| (b - other_mean).powi(2)).sum();
+    if variance > 0.0 {
+        Some(covariance / variance)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::equity::EquityPoint;
+    use crate::decimal::Money;
+    use std::collections::BTreeMap;
+    use time::{macros::date, Date};
+
+    // 每日资产依次为values，期初投入100元
+    fn curve(values: &[i64]) -> EquityCurve {
+        let mut curve = EquityCurve::new();
+        let mut date: Date = date!(2021 - 1 - 1);
+        for value in values {
+            curve.record(EquityPoint {
+                date,
+                total_value: Money::from_raw(value * 1_000_000),
+                market_value: Money::from_raw(value * 1_000_000),
+                invested: Money::from_raw(100_000_000),
+                cash: Money::from_raw(-100_000_000),
+                contribution: Money::from_raw(100_000_000),
+                holdings: BTreeMap::new(),
+                prices: BTreeMap::new(),
+            });
+            date = date.next_day().unwrap();
+        }
+        curve
+    }
+
+    #[test]
+    fn test_same_as_benchmark() {
+        let curve = curve(&[100, 110, 99, 120]);
+        let comparison = Comparison::evaluate(&curve, &curve, 0.0);
+        assert_eq!(comparison.excess_return, 0.0);
+        assert_eq!(comparison.tracking_error, Some(0.0));
+        assert_eq!(comparison.information_ratio, None);
+        assert!((comparison.beta.unwrap() - 1.0).abs() < 1e-12);
+        assert!(comparison.alpha.unwrap().abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_leveraged_strategy() {
+        // 策略每日涨跌幅是基准的两倍
+        let benchmark = curve(&[100, 120, 90, 99]);
+        let strategy = curve(&[100, 140, 70, 84]);
+        let comparison = Comparison::evaluate(&strategy, &benchmark, 0.0);
+        assert!((comparison.beta.unwrap() - 2.0).abs() < 1e-9);
+        assert!(comparison.alpha.unwrap().abs() < 1e-9);
+        assert!(comparison.tracking_error.unwrap() > 0.0);
+        assert!((comparison.excess_return - (-0.16 + 0.01)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,9 @@
+//! ## 回测结果分析
+//! ----
+//!
+//! 基于资产曲线(见account::equity)计算回测的收益和风险指标
+//! + performance: 资金加权收益率(XIRR)、时间加权收益率、年化收益率、最大回撤、年化波动率、夏普比率和索提诺比率
+//! + benchmark: 与业绩基准比较的超额收益、跟踪误差、信息比率、alpha和beta
+
+pub mod benchmark;
+pub mod performance;
@@ -0,0 +1,361 @@
+//! ## 收益与风险指标
+//! ----
+//!
+//! 外部现金流取自资产曲线中外部净投入(contribution)的逐日变化，期末总资产视为最后一笔流入：
+//! + xirr: 资金加权收益率，使各笔现金流按年化收益率折现后的净现值为0
+//! + twr: 时间加权收益率，剔除现金流影响后逐日收益率的连乘，现金流视为在当日收盘时发生
+//! + annualized_return: 按自然日将时间加权收益率年化
+//! + max_drawdown: 按时间加权收益率构造的单位净值的最大回撤，及回撤开始(峰值)和结束(谷底)的日期
+//! + volatility: 逐日收益率的年化标准差，每年按252个交易日计算
+//! + sharpe / sortino: 以年化无风险利率为基准的夏普比率和索提诺比率
+
+use crate::account::equity::EquityCurve;
+use time::Date;
+
+/// 每年的交易日数，用于年化波动率
+pub(crate) const TRADING_DAYS: f64 = 252.0;
+/// 每年的自然日数，用于年化收益率
+const DAYS_PER_YEAR: f64 = 365.0;
+
+/// 最大回撤
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Drawdown {
+    pub(crate) ratio: f64, // 回撤幅度，正数
+    pub(crate) peak: Date,
+    pub(crate) trough: Date,
+}
+
+/// 回测的收益与风险指标，数据不足以计算的指标为None
+#[derive(Debug, Clone, PartialEq)]
+pub struct Performance {
+    pub(crate) xirr: Option<f64>,
+    pub(crate) twr: f64,
+    pub(crate) annualized_return: Option<f64>,
+    pub(crate) max_drawdown: Option<Drawdown>,
+    pub(crate) volatility: Option<f64>,
+    pub(crate) sharpe: Option<f64>,
+    pub(crate) sortino: Option<f64>,
+}
+
+impl Performance {
+    /// 根据资产曲线计算各项指标，risk_free为年化无风险利率，如0.02
+    pub fn evaluate(curve: &EquityCurve, risk_free: f64) -> Self {
+        let returns = daily_returns(curve);
+        let twr = returns.iter().map(|(_, r)| 1.0 + r).product::<f64>() - 1.0;
+        let points = curve.points();
+        let annualized_return = match (points.first(), points.last()) {
+            (Some(first), Some(last)) if last.date > first.date => {
+                let days = (last.date - first.date).whole_days() as f64;
+                Some((1.0 + twr).powf(DAYS_PER_YEAR / days) - 1.0)
+            }
+            _ => None,
+        };
+        let rates: Vec<f64> = returns.iter().map(|(_, r)| *r).collect();
+        let daily_risk_free = risk_free / TRADING_DAYS;
+        let excess = mean(&rates).map(|x| x - daily_risk_free);
+        let deviation = std_dev(&rates);
+        let downside = downside_deviation(&rates, daily_risk_free);
+        Performance {
+            xirr: xirr(&cash_flows(curve)),
+            twr,
+            annualized_return,
+            max_drawdown: points.first().and_then(|x| max_drawdown(x.date, &returns)),
+            volatility: deviation.map(|x| x * TRADING_DAYS.sqrt()),
+            sharpe: ratio(excess, deviation),
+            sortino: ratio(excess, downside),
+        }
+    }
+
+    /// 显示各项指标
+    pub(crate) fn show(&self) {
+        println!(
+            "xirr: {}, time-weighted return: {}, annualized return: {}",
+            percent(self.xirr),
+            percent(Some(self.twr)),
+            percent(self.annualized_return)
+        );
+        match &self.max_drawdown {
+            Some(drawdown) => println!(
+                "max drawdown: {} ({} -> {})",
+                percent(Some(drawdown.ratio)),
+                drawdown.peak,
+                drawdown.trough
+            ),
+            None => println!("max drawdown: n/a"),
+        }
+        println!(
+            "volatility: {}, sharpe: {}, sortino: {}",
+            percent(self.volatility),
+            number(self.sharpe),
+            number(self.sortino)
+        );
+    }
+}
+
+pub(crate) fn percent(value: Option<f64>) -> String {
+    value.map_or_else(|| "n/a".into(), |x| format!("{:.2}%", x * 100.0))
+}
+
+pub(crate) fn number(value: Option<f64>) -> String {
+    value.map_or_else(|| "n/a".into(), |x| format!("{:.2}", x))
+}
+
+/// 外部现金流，流出(投入)为负，期末总资产为正
+pub(crate) fn cash_flows(curve: &EquityCurve) -> Vec<(Date, f64)> {
+    let mut flows = Vec::new();
+    let mut contributed = 0.0;
+    for point in curve.points() {
+        let contribution = point.contribution.to_f64();
+        if contribution != contributed {
+            flows.push((point.date, contributed - contribution));
+            contributed = contribution;
+        }
+    }
+    if let Some(last) = curve.last() {
+        flows.push((last.date, last.total_value.to_f64()));
+    }
+    flows
+}
+
+/// 剔除现金流影响的逐日收益率，前一日总资产为0时(尚未建仓)不计入
+pub(crate) fn daily_returns(curve: &EquityCurve) -> Vec<(Date, f64)> {
+    curve
+        .points()
+        .windows(2)
+        .filter(|x| x[0].total_value.to_f64() > 0.0)
+        .map(|x| {
+            let flow = (x[1].contribution - x[0].contribution).to_f64();
+            let value = x[1].total_value.to_f64() - flow;
+            (x[1].date, value / x[0].total_value.to_f64() - 1.0)
+        })
+        .collect()
+}
+
+/// 资金加权收益率，用二分法求净现值为0的年化收益率，现金流没有正负两个方向时返回None
+pub(crate) fn xirr(flows: &[(Date, f64)]) -> Option<f64> {
+    let first = flows.first()?.0;
+    let npv = |rate: f64| {
+        flows
+            .iter()
+            .map(|(date, amount)| {
+                let years = (*date - first).whole_days() as f64 / DAYS_PER_YEAR;
+                amount / (1.0 + rate).powf(years)
+            })
+            .sum::<f64>()
+    };
+    if !flows.iter().any(|x| x.1 > 0.0) || !flows.iter().any(|x| x.1 < 0.0) {
+        return None;
+    }
+    // 先投入后收回时净现值随收益率递减，据此向两侧扩大区间直到净现值变号
+    let (mut low, mut high) = (-0.9, 1.0);
+    while npv(low).signum() == npv(high).signum() {
+        if npv(high) > 0.0 {
+            high *= 2.0;
+        } else {
+            low = -1.0 + (1.0 + low) / 10.0;
+        }
+        if high > 1e6 || low <= -1.0 + 1e-12 {
+            return None;
+        }
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(mid).signum() == npv(low).signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+/// 按逐日收益率构造单位净值，求最大回撤，start为净值起点的日期
+pub(crate) fn max_drawdown(start: Date, returns: &[(Date, f64)]) -> Option<Drawdown> {
+    if returns.is_empty() {
+        return None;
+    }
+    let (mut value, mut peak, mut peak_date) = (1.0, 1.0, start);
+    let mut worst = Drawdown {
+        ratio: 0.0,
+        peak: start,
+        trough: start,
+    };
+    for (date, rate) in returns {
+        value *= 1.0 + rate;
+        if value > peak {
+            peak = value;
+            peak_date = *date;
+        }
+        let ratio = 1.0 - value / peak;
+        if ratio > worst.ratio {
+            worst = Drawdown {
+                ratio,
+                peak: peak_date,
+                trough: *date,
+            };
+        }
+    }
+    Some(worst)
+}
+
+/// 按逐日收益率构造单位净值，求每日相对此前峰值的回撤，首日为0
+pub(crate) fn drawdowns(curve: &EquityCurve) -> Vec<(Date, f64)> {
+    let returns: std::collections::HashMap<_, _> = daily_returns(curve).into_iter().collect();
+    let (mut value, mut peak) = (1.0, 1.0);
+    curve
+        .points()
+        .iter()
+        .map(|point| {
+            value *= 1.0 + returns.get(&point.date).unwrap_or(&0.0);
+            peak = f64::max(peak, value);
+            (point.date, 1.0 - value / peak)
+        })
+        .collect()
+}
+
+pub(crate) fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// 样本标准差，少于两个样本时返回None
+pub(crate) fn std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let avg = mean(values)?;
+    let variance =
+        values.iter().map(|x| (x - avg).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    Some(variance.sqrt())
+}
+
+/// 低于目标收益率部分的下行标准差
+fn downside_deviation(values: &[f64], target: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let sum = values
+        .iter()
+        .map(|x| (x - target).min(0.0).powi(2))
+        .sum::<f64>();
+    Some((sum / values.len() as f64).sqrt())
+}
+
+/// 年化的超额收益与波动之比，波动为0时返回None
+fn ratio(excess: Option<f64>, deviation: Option<f64>) -> Option<f64> {
+    match (excess, deviation) {
+        (Some(excess), Some(deviation)) if deviation > 0.0 => {
+            Some(excess / deviation * TRADING_DAYS.sqrt())
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::equity::EquityPoint;
+    use crate::decimal::Money;
+    use std::collections::BTreeMap;
+    use time::macros::date;
+
+    fn point(date: Date, total_value: i64, contribution: i64) -> EquityPoint {
+        EquityPoint {
+            date,
+            total_value: Money::from_raw(total_value * 1_000_000),
+            market_value: Money::from_raw(total_value * 1_000_000),
+            invested: Money::from_raw(contribution * 1_000_000),
+            cash: Money::from_raw(-contribution * 1_000_000),
+            contribution: Money::from_raw(contribution * 1_000_000),
+            holdings: BTreeMap::new(),
+            prices: BTreeMap::new(),
+        }
+    }
+
+    fn curve(points: Vec<EquityPoint>) -> EquityCurve {
+        let mut curve = EquityCurve::new();
+        points.into_iter().for_each(|x| curve.record(x));
+        curve
+    }
+
+    #[test]
+    fn test_xirr() {
+        let rate = xirr(&[
+            (date!(2021 - 1 - 1), -1000.0),
+            (date!(2022 - 1 - 1), 1100.0),
+        ])
+        .unwrap();
+        assert!((rate - 0.1).abs() < 1e-9);
+        // 两次投入，一年后共收回2100元
+        let rate = xirr(&[
+            (date!(2021 - 1 - 1), -1000.0),
+            (date!(2021 - 7 - 2), -1000.0),
+            (date!(2022 - 1 - 1), 2100.0),
+        ])
+        .unwrap();
+        let npv = -1000.0 * (1.0 + rate) - 1000.0 * (1.0 + rate).powf(183.0 / 365.0) + 2100.0;
+        assert!(npv.abs() < 1e-6);
+        assert_eq!(xirr(&[(date!(2021 - 1 - 1), -1000.0)]), None);
+    }
+
+    #[test]
+    fn test_returns_exclude_cash_flows() {
+        // 第二期追加投入100元且上涨10%，第三期下跌50%，第四期回升
+        let curve = curve(vec![
+            point(date!(2021 - 1 - 1), 100, 100),
+            point(date!(2021 - 4 - 1), 210, 200),
+            point(date!(2021 - 7 - 1), 105, 200),
+            point(date!(2021 - 10 - 1), 126, 200),
+        ]);
+        let returns: Vec<f64> = daily_returns(&curve).into_iter().map(|x| x.1).collect();
+        assert_eq!(returns.len(), 3);
+        assert!((returns[0] - 0.1).abs() < 1e-12);
+        assert!((returns[1] + 0.5).abs() < 1e-12);
+        assert!((returns[2] - 0.2).abs() < 1e-12);
+
+        let flows = cash_flows(&curve);
+        assert_eq!(
+            flows,
+            vec![
+                (date!(2021 - 1 - 1), -100.0),
+                (date!(2021 - 4 - 1), -100.0),
+                (date!(2021 - 10 - 1), 126.0)
+            ]
+        );
+
+        let performance = Performance::evaluate(&curve, 0.0);
+        assert!((performance.twr - (1.1 * 0.5 * 1.2 - 1.0)).abs() < 1e-12);
+        let drawdown = performance.max_drawdown.unwrap();
+        assert!((drawdown.ratio - 0.5).abs() < 1e-12);
+        assert_eq!(drawdown.peak, date!(2021 - 4 - 1));
+        assert_eq!(drawdown.trough, date!(2021 - 7 - 1));
+        let series: Vec<f64> = drawdowns(&curve).into_iter().map(|x| x.1).collect();
+        assert_eq!(series.len(), 4);
+        assert_eq!(series[..2], [0.0, 0.0]);
+        assert!((series[2] - 0.5).abs() < 1e-12);
+        assert!((series[3] - 0.4).abs() < 1e-12);
+        assert!(performance.volatility.unwrap() > 0.0);
+        assert!(performance.sharpe.unwrap() < 0.0);
+        assert!(performance.sortino.unwrap() < 0.0);
+        assert!(performance.xirr.unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_flat_curve() {
+        let curve = curve(vec![
+            point(date!(2021 - 1 - 1), 100, 100),
+            point(date!(2021 - 1 - 2), 100, 100),
+            point(date!(2021 - 1 - 3), 100, 100),
+        ]);
+        let performance = Performance::evaluate(&curve, 0.02);
+        assert_eq!(performance.twr, 0.0);
+        assert_eq!(performance.annualized_return, Some(0.0));
+        assert_eq!(performance.volatility, Some(0.0));
+        assert_eq!(performance.sharpe, None);
+        assert_eq!(performance.max_drawdown.unwrap().ratio, 0.0);
+        assert!(performance.xirr.unwrap().abs() < 1e-9);
+    }
+}
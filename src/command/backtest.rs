@@ -0,0 +1,113 @@
+//! ## backtest子命令
+//! ----
+//!
+//! 运行选定的策略，显示持仓和收益指标，可选地导出结果
+
+use super::PlanOpt;
+use crate::decimal::Money;
+use crate::report::export::OutputFormat;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct BacktestOpt {
+    #[clap(flatten)]
+    pub(crate) plan: PlanOpt,
+
+    /// [optional] the strategy to run: aip (fixed amount, default) or buy-more (buy more below the average price)
+    #[clap(long)]
+    strategy: Option<String>,
+
+    /// [optional] strategy parameters as <NAME>=<VALUE>, e.g. max_multiple=3 for buy-more
+    #[clap(long, min_values = 1)]
+    param: Vec<String>,
+
+    /// [optional] whether show the specific trade detail or not
+    #[clap(short, parse(from_flag))]
+    specific: bool,
+
+    /// [optional] export the result as json or html (a file) or csv (a directory of files)
+    #[clap(long)]
+    output: Option<OutputFormat>,
+
+    /// [optional] where to write the exported result
+    #[clap(long, parse(from_os_str))]
+    output_file: Option<PathBuf>,
+}
+
+pub fn run(opt: &BacktestOpt) -> Result<()> {
+    let mut plan = opt.plan.resolve()?;
+    let strategy = opt
+        .strategy
+        .clone()
+        .or_else(|| plan.strategy.clone())
+        .unwrap_or_else(|| "aip".into());
+    let params = plan.params.entry(strategy.clone()).or_default();
+    for param in &opt.param {
+        let (name, value) = param
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid parameter {}, expected <NAME>=<VALUE>", param))?;
+        let value = value
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid value of parameter {}", name))?;
+        params.insert(name.trim().to_string(), value);
+    }
+    let output = match (
+        opt.output.or(plan.output.format),
+        opt.output_file.clone().or_else(|| plan.output.file.clone()),
+    ) {
+        (Some(format), Some(path)) => Some((format, path)),
+        (None, None) => None,
+        _ => bail!("the output format and the output file must be given together"),
+    };
+
+    plan.load_profiles(plan.source.profiles().as_ref())?;
+    let provider = plan.source.provider();
+    let outcome = plan.run(&provider, &strategy, &plan.funds)?;
+    let account = &outcome.account;
+
+    println!("strategy: {}", outcome.strategy);
+    for profile in plan.funds.iter().filter_map(|x| x.profile.as_ref()) {
+        println!("{}", profile);
+    }
+    account.show_hold_detail();
+    if opt.specific {
+        account.show_transaction_detail();
+        outcome.equity.show();
+    }
+    println!(
+        "At last, account value: {value}",
+        value = account.account_value
+    );
+    println!("currency: {currency}", currency = account.balance_price);
+    println!(
+        "fee: {fee}, estimated management fee: {management}",
+        fee = account.get_total_fee(),
+        management = account.get_management_fee()
+    );
+    if account.total_deposit > Money::ZERO {
+        println!(
+            "deposit: {deposit}, interest: {interest}, rejected buys: {rejected}",
+            deposit = account.total_deposit,
+            interest = account.total_interest,
+            rejected = account.rejected_buys
+        );
+    }
+    if account.blocked_trades > 0 {
+        println!(
+            "blocked trades (purchase/redemption suspended): {blocked}",
+            blocked = account.blocked_trades
+        );
+    }
+    outcome.performance.show();
+    if let Some((code, comparison)) = &outcome.comparison {
+        comparison.show(*code);
+    }
+    if let Some((format, path)) = output {
+        outcome.report().write(format, &path)?;
+        println!("result exported to {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! ## compare子命令
+//! ----
+//!
+//! 以相同的回测计划运行多个策略，加上--separate时每只基金单独回测，结果在一张表中比较
+
+use super::{FundEntry, PlanOpt};
+use crate::analysis::performance::{number, percent};
+use crate::strategy::registry::fund_strategy_names;
+use anyhow::Result;
+use clap::Args;
+
+#[derive(Args, Debug)]
+pub struct CompareOpt {
+    #[clap(flatten)]
+    pub(crate) plan: PlanOpt,
+
+    /// [optional] the strategies to compare, default to all strategies
+    #[clap(long = "strategy", min_values = 1)]
+    strategies: Vec<String>,
+
+    /// [optional] backtest each fund on its own instead of all funds together
+    #[clap(long, parse(from_flag))]
+    separate: bool,
+}
+
+pub fn run(opt: &CompareOpt) -> Result<()> {
+    let (header, rows) = table(opt)?;
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| rows.iter().map(|x| x[i].len()).fold(name.len(), usize::max))
+        .collect();
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                // 策略名和基金代码左对齐，数值右对齐
+                if i < 2 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    println!("{}", line(header));
+    for row in &rows {
+        println!("{}", line(row.iter().map(String::as_str).collect()));
+    }
+    Ok(())
+}
+
+/// 比较表的表头和各行，每行为一个策略在一组基金上的回测结果
+fn table(opt: &CompareOpt) -> Result<(Vec<&'static str>, Vec<Vec<String>>)> {
+    let strategies: Vec<&str> = if opt.strategies.is_empty() {
+        fund_strategy_names()
+    } else {
+        opt.strategies.iter().map(String::as_str).collect()
+    };
+    let mut plan = opt.plan.resolve()?;
+    plan.load_profiles(plan.source.profiles().as_ref())?;
+    let groups: Vec<&[FundEntry]> = if opt.separate {
+        plan.funds.chunks(1).collect()
+    } else {
+        vec![&plan.funds]
+    };
+    let mut header = vec![
+        "strategy",
+        "funds",
+        "total value",
+        "contribution",
+        "xirr",
+        "twr",
+        "annualized",
+        "max drawdown",
+        "volatility",
+        "sharpe",
+    ];
+    if plan.benchmark.is_some() {
+        header.extend(["excess return", "alpha", "beta"]);
+    }
+    let provider = plan.source.provider();
+    let mut rows = Vec::new();
+    for strategy in strategies {
+        for group in &groups {
+            let outcome = plan.run(&provider, strategy, group)?;
+            let performance = &outcome.performance;
+            let last = outcome.equity.last();
+            let mut row = vec![
+                outcome.strategy.clone(),
+                group
+                    .iter()
+                    .map(|x| format!("{:0>6}", x.code))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                last.map_or_else(String::new, |x| x.total_value.to_string()),
+                last.map_or_else(String::new, |x| x.contribution.to_string()),
+                percent(performance.xirr),
+                percent(Some(performance.twr)),
+                percent(performance.annualized_return),
+                percent(performance.max_drawdown.map(|x| x.ratio)),
+                percent(performance.volatility),
+                number(performance.sharpe),
+            ];
+            if let Some((_, comparison)) = &outcome.comparison {
+                row.extend([
+                    percent(Some(comparison.excess_return)),
+                    percent(comparison.alpha),
+                    number(comparison.beta),
+                ]);
+            }
+            rows.push(row);
+        }
+    }
+    Ok((header, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+
+    #[derive(Parser)]
+    struct Wrapper {
+        #[clap(flatten)]
+        opt: CompareOpt,
+    }
+
+    #[test]
+    fn test_compare_table() {
+        let dir = std::env::temp_dir().join(format!("trade_helper_compare_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // 基金1先跌后涨，基金2净值不变
+        fs::write(
+            dir.join("000001.csv"),
+            "FSRQ,DWJZ,LJJZ,FHFCZ\n2021-01-04,1.0,1.0,\n2021-02-01,0.5,0.5,\n2021-03-01,1.0,1.0,\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("000002.csv"),
+            "FSRQ,DWJZ,LJJZ,FHFCZ\n2021-01-04,1.0,1.0,\n2021-02-01,1.0,1.0,\n2021-03-01,1.0,1.0,\n",
+        )
+        .unwrap();
+        let parse = |args: &[&str]| {
+            let mut argv = vec![
+                "compare",
+                "-b",
+                "20210101",
+                "-e",
+                "20210301",
+                "-f",
+                "1",
+                "2",
+                "-p",
+                "100",
+                "100",
+                "--data-dir",
+            ];
+            argv.push(dir.to_str().unwrap());
+            argv.extend(args);
+            Wrapper::try_parse_from(argv).unwrap().opt
+        };
+
+        let (header, rows) = table(&parse(&[])).unwrap();
+        assert_eq!(header.len(), 10);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][..4], ["aip", "000001,000002", "700.00", "600.00"]);
+        // 越跌越买在2月多买了一倍
+        assert_eq!(
+            rows[1][..4],
+            ["buy-more", "000001,000002", "900.00", "700.00"]
+        );
+
+        let (header, rows) = table(&parse(&[
+            "--strategy",
+            "aip",
+            "--separate",
+            "--benchmark",
+            "2",
+        ]))
+        .unwrap();
+        assert_eq!(header.len(), 13);
+        let funds: Vec<_> = rows.iter().map(|x| x[1].as_str()).collect();
+        assert_eq!(funds, ["000001", "000002"]);
+        assert_eq!(rows[1][5], "0.00%");
+        assert_eq!(rows[1][10], "0.00%");
+
+        // 设置现金计划和申购费后，与基金相同的基准仍然没有超额收益
+        let (_, rows) = table(&parse(&[
+            "--strategy",
+            "aip",
+            "--separate",
+            "--benchmark",
+            "1",
+            "--initial-cash",
+            "150",
+            "--deposit",
+            "100",
+            "--cash-yield",
+            "2",
+            "--subscription-rate",
+            "1.5",
+        ]))
+        .unwrap();
+        let excess: f64 = rows[0][10].trim_end_matches('%').parse().unwrap();
+        assert!(excess.abs() < 0.01, "{}", rows[0][10]);
+
+        assert!(table(&parse(&["--strategy", "unknown"])).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
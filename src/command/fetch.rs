@@ -0,0 +1,57 @@
+//! ## fetch子命令
+//! ----
+//!
+//! 从网络下载基金历史净值和基金资料并写入本地缓存(见market::cache)，已缓存的日期区间不会重复下载
+
+use super::parse_date;
+use crate::market::fund_market::FundData;
+use crate::market::fund_profile::{FundProfile, QueryFundProfile};
+use crate::market::{default_provider, QueryMarketInfo};
+use anyhow::Result;
+use clap::Args;
+use time::OffsetDateTime;
+
+#[derive(Args, Debug)]
+pub struct FetchOpt {
+    /// the first day of the history
+    #[clap(short, long, required = true)]
+    begin: u32,
+
+    /// [optional] the last day of the history, default to today
+    #[clap(short, long)]
+    end: Option<u32>,
+
+    /// the list of fund code
+    #[clap(name = "FUND LIST", short = 'f', long, required = true, min_values = 1)]
+    fund: Vec<u32>,
+}
+
+pub fn run(opt: &FetchOpt) -> Result<()> {
+    let start = parse_date(opt.begin)?;
+    let end = match opt.end {
+        Some(end) => parse_date(end)?,
+        None => OffsetDateTime::now_utc().date(),
+    };
+    let provider = default_provider::<FundData>();
+    let profiles = default_provider::<FundProfile>();
+    for &code in &opt.fund {
+        // 基金资料只用于标注，查询失败不影响下载净值
+        match profiles.query_profile(code) {
+            Ok(Some(profile)) => println!("{}", profile),
+            Ok(None) => {}
+            Err(e) => eprintln!("warning: no profile of {:0>6}: {:#}", code, e),
+        }
+        let records = provider.query_history_info(code, start, end)?;
+        match (records.first(), records.last()) {
+            (Some(first), Some(last)) => println!(
+                "{:0>6}: {} records from {} to {}",
+                code,
+                records.len(),
+                first.date,
+                last.date
+            ),
+            _ => println!("{:0>6}: no records from {} to {}", code, start, end),
+        }
+    }
+    Ok(())
+}
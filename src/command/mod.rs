@@ -0,0 +1,702 @@
+//! ## 命令行子命令
+//! ----
+//!
+//! + backtest: 运行选定的策略，输出持仓、收益指标，可导出结果(见report模块)
+//! + fetch: 下载基金历史净值并写入本地缓存，供之后离线回测
+//! + quote: 显示基金最新净值及日涨跌幅
+//! + compare: 以相同的计划运行多个策略(或分别运行每只基金)，在一张表中比较
+//! + report: 将backtest导出的json结果渲染为其他格式
+//! + search: 按代码、名称片段或拼音缩写查找基金，或列出某一类型的基金
+
+pub mod backtest;
+pub mod compare;
+pub mod fetch;
+pub mod quote;
+pub mod report;
+pub mod search;
+
+use crate::account::cash::{CashPlan, InsufficientFunds};
+use crate::account::equity::EquityCurve;
+use crate::account::fee::{parse_redemption_tiers, FeeSchedule};
+use crate::account::fund_account::{FundAccount, FundSetting};
+use crate::account::{Account, DividendPolicy};
+use crate::analysis::benchmark::Comparison;
+use crate::analysis::performance::Performance;
+use crate::config::{BacktestConfig, FeeConfig, FundConfig, OutputConfig};
+use crate::decimal::Money;
+use crate::market::error::MarketError;
+use crate::market::fund_market::FundData;
+use crate::market::fund_profile::{FundProfile, QueryFundProfile};
+use crate::market::fund_universe::{FundListing, QueryFundUniverse};
+use crate::market::provider::FileProvider;
+use crate::market::{default_provider, QueryMarketInfo};
+use crate::report::export::Report;
+use crate::strategy::fund_strategy::{run_benchmark_with, run_fund_strategy_with, FundPlan};
+use crate::strategy::registry::{build_fund_strategy, StrategyParams};
+use crate::strategy::schedule::Schedule;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use time::macros::format_description;
+use time::Date;
+
+/// A CLI APP FOR TRADING
+#[derive(Parser, Debug)]
+#[clap(author, about, version)]
+#[clap(name = "TRADER'S HELPER")]
+pub struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// run a strategy over the fund history
+    Backtest(backtest::BacktestOpt),
+    /// download fund history into the local cache
+    Fetch(fetch::FetchOpt),
+    /// print the latest nav and daily change of funds
+    Quote(quote::QuoteOpt),
+    /// run several strategies or funds side by side
+    Compare(compare::CompareOpt),
+    /// render a result saved by `backtest --output json`
+    Report(report::ReportOpt),
+    /// find fund codes by code, name or pinyin initials
+    Search(search::SearchOpt),
+}
+
+impl Cli {
+    pub fn run(&self) -> Result<()> {
+        match &self.command {
+            Command::Backtest(opt) => backtest::run(opt),
+            Command::Fetch(opt) => fetch::run(opt),
+            Command::Quote(opt) => quote::run(opt),
+            Command::Compare(opt) => compare::run(opt),
+            Command::Report(opt) => report::run(opt),
+            Command::Search(opt) => search::run(opt),
+        }
+    }
+}
+
+/// 回测计划，backtest和compare共用，可以从配置文件读取，命令行参数优先
+#[derive(Args, Debug)]
+pub struct PlanOpt {
+    /// [optional] read the plan from a .toml/.yaml file, flags given on the command line override it
+    #[clap(long, parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// the first day begin to buy fund
+    #[clap(short, long)]
+    begin: Option<u32>,
+
+    /// the last day stop to buy fund
+    #[clap(short, long)]
+    end: Option<u32>,
+
+    /// [optional] the nth day buying fund in a month, default to first day
+    #[clap(short, long)]
+    day: Option<u8>,
+
+    /// [optional] when to buy: weekly:<WEEKDAY>, biweekly:<WEEKDAY>, monthly:<DAY>, month-end or dates:<YYYYMMDD>,...
+    #[clap(long, conflicts_with = "day")]
+    schedule: Option<Schedule>,
+
+    /// the list of fund code
+    #[clap(name = "FUND LIST", short = 'f', long, min_values = 1)]
+    fund: Vec<u32>,
+
+    /// the buying amount of each fund
+    #[clap(name = "BUDGET PLAN FOR FUNDS", short = 'p', long, min_values = 1)]
+    budget: Vec<Money>,
+
+    /// [optional] subscription fee rate in percent, e.g. 1.5
+    #[clap(long)]
+    subscription_rate: Option<f32>,
+
+    /// [optional] platform discount on the subscription fee, e.g. 0.1 for 10%
+    #[clap(long)]
+    discount: Option<f32>,
+
+    /// [optional] redemption fee tiers as <DAYS>:<RATE>, e.g. 7:1.5,365:0.5,730:0.25
+    #[clap(long)]
+    redemption_fee: Option<String>,
+
+    /// [optional] annual management fee rate in percent, only used for estimation
+    #[clap(long)]
+    management_rate: Option<f32>,
+
+    /// [optional] funds held in cash-dividend mode, others reinvest dividends
+    #[clap(long, min_values = 1)]
+    cash_dividend: Vec<u32>,
+
+    /// [optional] starting cash, buys are limited by the cash balance once set
+    #[clap(long)]
+    initial_cash: Option<Money>,
+
+    /// [optional] cash deposited every month, e.g. salary
+    #[clap(long)]
+    deposit: Option<Money>,
+
+    /// [optional] the day of month the deposit arrives, default to first day
+    #[clap(long)]
+    deposit_day: Option<u8>,
+
+    /// [optional] annual yield of idle cash in percent, e.g. 2.0 for a money-market fund
+    #[clap(long)]
+    cash_yield: Option<f32>,
+
+    /// [optional] annual risk-free rate in percent for the sharpe and sortino ratios, e.g. 2.0
+    #[clap(long)]
+    risk_free: Option<f64>,
+
+    /// [optional] replay the same buys and sells into this fund, e.g. an index fund, for comparison
+    #[clap(long)]
+    benchmark: Option<u32>,
+
+    /// [optional] fill buys partially with the remaining cash instead of rejecting them
+    #[clap(long, parse(from_flag))]
+    partial_fill: bool,
+
+    #[clap(flatten)]
+    source: SourceOpt,
+}
+
+/// 行情来源
+#[derive(Args, Debug, Clone, Default)]
+pub struct SourceOpt {
+    /// [optional] read fund history from <CODE>.csv/<CODE>.json in this directory instead of the network
+    #[clap(long, parse(from_os_str))]
+    data_dir: Option<PathBuf>,
+}
+
+impl SourceOpt {
+    /// 指定了本地目录时从目录读取，否则使用带缓存的网络数据源
+    fn provider(&self) -> Box<dyn QueryMarketInfo<MarketInfo = FundData>> {
+        match &self.data_dir {
+            Some(dir) => Box::new(FileProvider::<FundData>::new(dir)),
+            None => Box::new(default_provider::<FundData>()),
+        }
+    }
+
+    /// 基金资料的来源，本地目录中没有资料文件时不联网查询
+    fn profiles(&self) -> Box<dyn QueryFundProfile> {
+        match &self.data_dir {
+            Some(dir) => Box::new(FileProvider::<FundProfile>::new(dir)),
+            None => Box::new(default_provider::<FundProfile>()),
+        }
+    }
+
+    /// 全部基金列表的来源，本地目录中为`funds.csv`
+    fn universe(&self) -> Box<dyn QueryFundUniverse> {
+        match &self.data_dir {
+            Some(dir) => Box::new(FileProvider::<FundListing>::new(dir)),
+            None => Box::new(default_provider::<FundListing>()),
+        }
+    }
+}
+
+/// 一次回测的结果及分析
+pub struct Outcome {
+    pub(crate) strategy: String,
+    pub(crate) account: Account<FundAccount>,
+    pub(crate) equity: EquityCurve,
+    pub(crate) performance: Performance,
+    pub(crate) comparison: Option<(u32, Comparison)>,
+    pub(crate) names: HashMap<u32, String>, // 有资料的基金的名称
+}
+
+impl Outcome {
+    pub(crate) fn report(&self) -> Report {
+        Report::new(
+            &self.strategy,
+            &self.account,
+            &self.equity,
+            &self.performance,
+            self.comparison.as_ref().map(|(code, x)| (*code, x)),
+        )
+        .with_names(&self.names)
+    }
+}
+
+/// 单只基金的定投设置
+#[derive(Debug, Clone)]
+pub struct FundEntry {
+    pub(crate) code: u32,
+    pub(crate) budget: Money,
+    pub(crate) schedule: Option<Schedule>, // 单独指定的买入计划
+    pub(crate) fee: FeeConfig,             // 命令行和配置文件中设置的费率
+    pub(crate) setting: FundSetting,
+    pub(crate) profile: Option<FundProfile>, // 基金资料，见Plan::load_profiles
+}
+
+/// 合并配置文件和命令行参数后的回测计划
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub(crate) start: Date,
+    pub(crate) end: Date,
+    pub(crate) schedule: Schedule,
+    pub(crate) funds: Vec<FundEntry>,
+    pub(crate) strategy: Option<String>,
+    pub(crate) params: HashMap<String, StrategyParams>,
+    pub(crate) cash: Option<CashPlan>,
+    pub(crate) risk_free: f64,
+    pub(crate) benchmark: Option<u32>,
+    pub(crate) benchmark_fee: FeeConfig, // 业绩基准不在基金中时使用的费率
+    pub(crate) source: SourceOpt,
+    pub(crate) output: OutputConfig,
+}
+
+impl PlanOpt {
+    /// 读取配置文件(如有)，逐项以命令行参数覆盖
+    fn resolve(&self) -> Result<Plan> {
+        let config = match &self.config {
+            Some(path) => BacktestConfig::load(path)?,
+            None => BacktestConfig::default(),
+        };
+        let begin = self
+            .begin
+            .or(config.begin)
+            .ok_or_else(|| anyhow!("missing the first day, use -b or set begin in the config"))?;
+        let end = self
+            .end
+            .or(config.end)
+            .ok_or_else(|| anyhow!("missing the last day, use -e or set end in the config"))?;
+        let (start, end_date) = (parse_date(begin)?, parse_date(end)?);
+        if start > end_date {
+            bail!(
+                "the end date {} should later than start date {}",
+                end,
+                begin
+            );
+        }
+
+        // 命令行给出基金时以命令行为准，配置文件中同一基金的其他设置仍然有效
+        let funds = if self.fund.is_empty() && self.budget.is_empty() {
+            config.funds.clone()
+        } else {
+            if self.fund.len() != self.budget.len() {
+                bail!(
+                    "the length of fund: {:?} and budget: {:?} must match",
+                    self.fund,
+                    self.budget
+                );
+            }
+            self.fund
+                .iter()
+                .zip(&self.budget)
+                .map(|(&code, &budget)| {
+                    let base = config.funds.iter().find(|x| x.code == code);
+                    FundConfig {
+                        code,
+                        budget,
+                        day: base.and_then(|x| x.day),
+                        schedule: base.and_then(|x| x.schedule.clone()),
+                        fee: base.map(|x| x.fee.clone()).unwrap_or_default(),
+                        dividend: base.and_then(|x| x.dividend),
+                    }
+                })
+                .collect()
+        };
+        if funds.is_empty() {
+            bail!("no fund to backtest, use -f and -p or add funds to the config");
+        }
+        let funds = funds
+            .into_iter()
+            .map(|fund| {
+                let dividend_policy = if self.cash_dividend.contains(&fund.code) {
+                    DividendPolicy::Cash
+                } else {
+                    fund.dividend.unwrap_or_default()
+                };
+                // 命令行指定的买入日或买入计划对所有基金生效
+                let schedule = if self.day.is_some() || self.schedule.is_some() {
+                    None
+                } else {
+                    pick_schedule(fund.day, fund.schedule)
+                        .with_context(|| format!("invalid schedule of fund {:0>6}", fund.code))?
+                };
+                let fee = self.fee(&fund.fee, &config.fee);
+                Ok(FundEntry {
+                    code: fund.code,
+                    budget: fund.budget,
+                    schedule,
+                    setting: FundSetting {
+                        fee: fee_schedule(&fee, None)?,
+                        dividend_policy,
+                    },
+                    fee,
+                    profile: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let cash = &config.cash;
+        let initial = self.initial_cash.or(cash.initial);
+        let deposit = self.deposit.or(cash.deposit).unwrap_or_default();
+        let cash = if initial.is_none() && deposit <= Money::ZERO {
+            None
+        } else {
+            let insufficient = if self.partial_fill || cash.partial_fill.unwrap_or(false) {
+                InsufficientFunds::PartialFill
+            } else {
+                InsufficientFunds::Reject
+            };
+            Some(
+                CashPlan::new(initial.unwrap_or_default())
+                    .with_deposit(deposit, self.deposit_day.or(cash.deposit_day).unwrap_or(1))
+                    .with_yield(self.cash_yield.or(cash.cash_yield).unwrap_or(0.0))
+                    .with_insufficient(insufficient),
+            )
+        };
+
+        Ok(Plan {
+            start,
+            end: end_date,
+            schedule: match pick_schedule(self.day, self.schedule.clone())? {
+                Some(schedule) => schedule,
+                None => pick_schedule(config.day, config.schedule)?.unwrap_or_default(),
+            },
+            funds,
+            strategy: config.strategy,
+            params: config.params,
+            cash,
+            risk_free: self.risk_free.or(config.risk_free).unwrap_or(0.0),
+            benchmark: self.benchmark.or(config.benchmark),
+            benchmark_fee: self.fee(&FeeConfig::default(), &config.fee),
+            source: SourceOpt {
+                data_dir: self.source.data_dir.clone().or(config.data_dir),
+            },
+            output: config.output,
+        })
+    }
+
+    /// 依次取命令行、基金单独的设置、配置文件中默认的设置
+    fn fee(&self, fund: &FeeConfig, default: &FeeConfig) -> FeeConfig {
+        FeeConfig {
+            subscription_rate: self
+                .subscription_rate
+                .or(fund.subscription_rate)
+                .or(default.subscription_rate),
+            discount: self.discount.or(fund.discount).or(default.discount),
+            redemption_fee: self
+                .redemption_fee
+                .clone()
+                .or_else(|| fund.redemption_fee.clone())
+                .or_else(|| default.redemption_fee.clone()),
+            management_rate: self
+                .management_rate
+                .or(fund.management_rate)
+                .or(default.management_rate),
+        }
+    }
+}
+
+/// 没有设置的费率使用基金资料中公布的费率，都没有时使用默认费率(不收费、不打折)
+fn fee_schedule(fee: &FeeConfig, profile: Option<&FundProfile>) -> Result<FeeSchedule> {
+    let redemption = fee
+        .redemption_fee
+        .as_ref()
+        .or_else(|| profile.and_then(|x| x.redemption_fee.as_ref()))
+        .map_or_else(|| Ok(Vec::new()), |x| parse_redemption_tiers(x))?;
+    let subscription_rate = fee
+        .subscription_rate
+        .or_else(|| profile.and_then(|x| x.subscription_rate))
+        .unwrap_or(0.0);
+    let management_rate = fee
+        .management_rate
+        .or_else(|| profile.and_then(|x| x.management_rate))
+        .unwrap_or(0.0);
+    Ok(FeeSchedule::default()
+        .with_subscription(subscription_rate, fee.discount.unwrap_or(1.0))
+        .with_redemption(&redemption)
+        .with_management(management_rate))
+}
+
+impl Plan {
+    /// 查询各基金的资料：基金代码不存在时报错，回测区间早于成立日时给出警告，
+    /// 没有设置的费率使用公布的费率。其他原因查询失败时给出警告并跳过
+    pub(crate) fn load_profiles<P>(&mut self, profiles: &P) -> Result<()>
+    where
+        P: QueryFundProfile + ?Sized,
+    {
+        for fund in &mut self.funds {
+            let profile = match profiles.query_profile(fund.code) {
+                Ok(Some(profile)) => profile,
+                Ok(None) => continue,
+                Err(e) if matches!(e.downcast_ref(), Some(MarketError::UnknownCode(_))) => {
+                    return Err(e)
+                }
+                Err(e) => {
+                    eprintln!("warning: no profile of {:0>6}: {:#}", fund.code, e);
+                    continue;
+                }
+            };
+            if let Some(inception) = profile.inception.filter(|x| *x > self.start) {
+                eprintln!(
+                    "warning: {:0>6} {} was founded on {}, after the start {}",
+                    fund.code, profile.name, inception, self.start
+                );
+            }
+            fund.setting.fee = fee_schedule(&fund.fee, Some(&profile))
+                .with_context(|| format!("invalid fee in the profile of {:0>6}", fund.code))?;
+            fund.profile = Some(profile);
+        }
+        Ok(())
+    }
+
+    /// 对funds运行名为strategy的策略，设置了业绩基准时再以相同的买卖净额回测业绩基准
+    fn run<P>(&self, provider: &P, strategy: &str, funds: &[FundEntry]) -> Result<Outcome>
+    where
+        P: QueryMarketInfo<MarketInfo = FundData>,
+    {
+        let codes: Vec<u32> = funds.iter().map(|x| x.code).collect();
+        let budget: Vec<Money> = funds.iter().map(|x| x.budget).collect();
+        let plan = funds
+            .iter()
+            .filter_map(|x| x.schedule.clone().map(|schedule| (x.code, schedule)))
+            .fold(
+                FundPlan::new(1, &codes, &budget).with_schedule(self.schedule.clone()),
+                |plan, (code, schedule)| plan.with_fund_schedule(code, schedule),
+            );
+        let params = self.params.get(strategy).cloned().unwrap_or_default();
+        let mut strategy = build_fund_strategy(strategy, plan, &params)?;
+        let settings = funds.iter().map(|x| (x.code, x.setting.clone())).collect();
+        let result = run_fund_strategy_with(
+            provider,
+            self.start,
+            self.end,
+            &codes,
+            strategy.as_mut(),
+            &settings,
+            self.cash.clone(),
+        )?;
+        let risk_free = self.risk_free / 100.0;
+        let comparison = match self.benchmark {
+            Some(code) => {
+                // 基准也是回测的基金时沿用其设置，否则使用命令行和配置文件中默认的费率
+                let setting = match funds.iter().find(|x| x.code == code) {
+                    Some(fund) => fund.setting.clone(),
+                    None => FundSetting {
+                        fee: fee_schedule(&self.benchmark_fee, None)?,
+                        ..Default::default()
+                    },
+                };
+                let benchmark = run_benchmark_with(
+                    provider,
+                    self.start,
+                    self.end,
+                    code,
+                    result.account.trade_flows(),
+                    &setting,
+                    self.cash.clone(),
+                )?;
+                Some((
+                    code,
+                    Comparison::evaluate(&result.equity, &benchmark.equity, risk_free),
+                ))
+            }
+            None => None,
+        };
+        Ok(Outcome {
+            names: funds
+                .iter()
+                .filter_map(|x| x.profile.as_ref().map(|p| (x.code, p.name.clone())))
+                .collect(),
+            strategy: strategy.name().to_string(),
+            performance: Performance::evaluate(&result.equity, risk_free),
+            account: result.account,
+            equity: result.equity,
+            comparison,
+        })
+    }
+}
+
+/// 每月买入日是买入计划的简写，两者只能设置一个
+fn pick_schedule(day: Option<u8>, schedule: Option<Schedule>) -> Result<Option<Schedule>> {
+    match (day, schedule) {
+        (Some(_), Some(_)) => bail!("set either day or schedule, not both"),
+        (Some(day), None) => Ok(Some(Schedule::Monthly(day))),
+        (None, schedule) => Ok(schedule),
+    }
+}
+
+/// 解析yyyymmdd格式的日期
+pub(crate) fn parse_date(value: u32) -> Result<Date> {
+    let format = format_description!("[year][month][day]");
+    Ok(Date::parse(&value.to_string(), &format)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    fn resolve(args: &[&str]) -> Result<Plan> {
+        let cli = Cli::try_parse_from(["trade_helper_rs", "backtest"].iter().chain(args))?;
+        match cli.command {
+            Command::Backtest(opt) => opt.plan.resolve(),
+            _ => panic!("expect backtest"),
+        }
+    }
+
+    #[test]
+    fn test_parse_cli() {
+        let args = [
+            "-b",
+            "20210101",
+            "-e",
+            "20211231",
+            "-f",
+            "1",
+            "2",
+            "-p",
+            "100",
+            "200.5",
+            "--initial-cash",
+            "1000",
+        ];
+        let plan = resolve(&args).unwrap();
+        assert_eq!(
+            (plan.start, plan.end),
+            (date!(2021 - 1 - 1), date!(2021 - 12 - 31))
+        );
+        let funds: Vec<_> = plan.funds.iter().map(|x| (x.code, x.budget)).collect();
+        assert_eq!(
+            funds,
+            vec![
+                (1, Money::from_raw(100_000_000)),
+                (2, Money::from_raw(200_500_000))
+            ]
+        );
+        assert!(plan.cash.is_some());
+
+        // 基金与金额数目不一致，或起止日期颠倒
+        assert!(resolve(&args[..9]).is_err());
+        assert!(resolve(&["-b", "20211231", "-e", "20210101", "-f", "1", "-p", "100"]).is_err());
+        assert!(resolve(&["-b", "20210101", "-f", "1", "-p", "100"]).is_err());
+        assert!(resolve(&["-b", "20210101", "-e", "20211231"]).is_err());
+
+        let plan = resolve(&[&args[..], &["--schedule", "biweekly:fri"]].concat()).unwrap();
+        assert_eq!(plan.schedule, Schedule::BiWeekly(time::Weekday::Friday));
+        assert!(resolve(&[&args[..], &["--schedule", "month-end", "-d", "2"]].concat()).is_err());
+        assert!(resolve(&[&args[..], &["--schedule", "daily"]].concat()).is_err());
+    }
+
+    #[test]
+    fn test_config_overridden_by_cli() {
+        let dir = std::env::temp_dir().join(format!("trade_helper_plan_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("plan.toml");
+        std::fs::write(
+            &path,
+            r#"
+begin = 20210101
+end = 20211231
+day = 5
+data_dir = "data"
+
+[fee]
+subscription_rate = 1.5
+
+[[funds]]
+code = 1
+budget = 100
+day = 15
+dividend = "cash"
+fee = { discount = 0.1 }
+
+[[funds]]
+code = 2
+budget = 200
+"#,
+        )
+        .unwrap();
+        let config = path.to_str().unwrap();
+
+        let plan = resolve(&["--config", config]).unwrap();
+        assert_eq!(plan.end, date!(2021 - 12 - 31));
+        assert_eq!(plan.schedule, Schedule::Monthly(5));
+        assert_eq!(plan.funds.len(), 2);
+        assert_eq!(plan.funds[0].schedule, Some(Schedule::Monthly(15)));
+        assert_eq!(plan.funds[0].setting.dividend_policy, DividendPolicy::Cash);
+        assert_eq!(plan.funds[0].setting.fee.subscription_rate, 15_000);
+        assert_eq!(plan.funds[0].setting.fee.discount, 100_000);
+        assert_eq!(plan.funds[1].setting.fee.discount, 1_000_000);
+        assert!(plan.cash.is_none());
+        assert_eq!(plan.source.data_dir, Some(dir.join("data")));
+
+        // 命令行的结束日、费率、基金和买入日覆盖配置文件
+        let plan = resolve(&[
+            "--config",
+            config,
+            "-e",
+            "20210630",
+            "-d",
+            "10",
+            "-f",
+            "1",
+            "-p",
+            "50",
+            "--subscription-rate",
+            "1.2",
+        ])
+        .unwrap();
+        assert_eq!(plan.end, date!(2021 - 6 - 30));
+        assert_eq!(plan.schedule, Schedule::Monthly(10));
+        assert_eq!(plan.funds.len(), 1);
+        assert_eq!(plan.funds[0].budget, Money::from_raw(50_000_000));
+        assert_eq!(plan.funds[0].schedule, None);
+        assert_eq!(plan.funds[0].setting.dividend_policy, DividendPolicy::Cash);
+        assert_eq!(plan.funds[0].setting.fee.subscription_rate, 12_000);
+        assert_eq!(plan.funds[0].setting.fee.discount, 100_000);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_profiles() {
+        let dir = std::env::temp_dir().join(format!("trade_helper_profile_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("profile_000001.json"),
+            r#"{"code":1,"name":"华夏成长混合","category":"hybrid","inception":"2021-03-01",
+                "subscription_rate":1.5,"management_rate":1.2,"redemption_fee":"7:1.5"}"#,
+        )
+        .unwrap();
+        let args = [
+            "-b",
+            "20210101",
+            "-e",
+            "20211231",
+            "-f",
+            "1",
+            "2",
+            "-p",
+            "100",
+            "100",
+            "--discount",
+            "0.1",
+            "--data-dir",
+            dir.to_str().unwrap(),
+        ];
+        let mut plan = resolve(&args).unwrap();
+        plan.load_profiles(plan.source.profiles().as_ref()).unwrap();
+        // 没有设置的费率使用公布的费率，没有资料的基金不变
+        let fee = &plan.funds[0].setting.fee;
+        assert_eq!(plan.funds[0].profile.as_ref().unwrap().name, "华夏成长混合");
+        assert_eq!(
+            (fee.subscription_rate, fee.discount, fee.management_rate),
+            (15_000, 100_000, 12_000)
+        );
+        assert_eq!(fee.redemption.len(), 1);
+        assert!(plan.funds[1].profile.is_none());
+        assert_eq!(plan.funds[1].setting.fee.subscription_rate, 0);
+
+        // 命令行设置的费率优先
+        let mut plan = resolve(&[&args[..], &["--subscription-rate", "1.2"]].concat()).unwrap();
+        plan.load_profiles(plan.source.profiles().as_ref()).unwrap();
+        assert_eq!(plan.funds[0].setting.fee.subscription_rate, 12_000);
+        assert_eq!(plan.funds[0].setting.fee.management_rate, 12_000);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+//! ## quote子命令
+//! ----
+//!
+//! 显示基金最近一个交易日的净值，日涨跌幅按复权净值的变化计算，不受分红和份额折算影响
+
+use super::SourceOpt;
+use crate::market::fund_market::{adjusted_nav, FundData};
+use crate::market::QueryMarketInfo;
+use anyhow::Result;
+use clap::Args;
+use time::{Duration, OffsetDateTime};
+
+/// 向前查询的天数，覆盖长假
+const LOOKBACK_DAYS: i64 = 30;
+
+#[derive(Args, Debug)]
+pub struct QuoteOpt {
+    /// the list of fund code
+    #[clap(name = "FUND LIST", short = 'f', long, required = true, min_values = 1)]
+    fund: Vec<u32>,
+
+    #[clap(flatten)]
+    source: SourceOpt,
+}
+
+pub fn run(opt: &QuoteOpt) -> Result<()> {
+    let end = OffsetDateTime::now_utc().date();
+    let start = end - Duration::days(LOOKBACK_DAYS);
+    let provider = opt.source.provider();
+    for &code in &opt.fund {
+        let records = provider.query_history_info(code, start, end)?;
+        match latest_change(&records) {
+            Some((latest, change)) => println!(
+                "{:0>6}: {} nav {} accumulated {} change {}",
+                code,
+                latest.date,
+                latest.unit_nav,
+                latest.accumulate_nav,
+                change.map_or_else(|| "n/a".into(), |x| format!("{:+.2}%", x * 100.0))
+            ),
+            None => println!("{:0>6}: no nav since {}", code, start),
+        }
+    }
+    Ok(())
+}
+
+/// 最新的净值及相对前一交易日的涨跌幅，只有一条记录时涨跌幅为None
+fn latest_change(records: &[FundData]) -> Option<(&FundData, Option<f64>)> {
+    let latest = records.iter().max_by_key(|x| x.date)?;
+    let change = match adjusted_nav(records)[..] {
+        [.., (prev_date, prev), (date, nav)] if date == latest.date && prev_date < date => {
+            Some(nav / prev - 1.0)
+        }
+        _ => None,
+    };
+    Some((latest, change))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::fund_market::FundEvent;
+    use time::macros::date;
+
+    #[test]
+    fn test_latest_change() {
+        assert!(latest_change(&[]).is_none());
+        let first = FundData::new(date!(2021 - 9 - 29), 20000, 30000, None);
+        assert_eq!(latest_change(&[first]), Some((&first, None)));
+        // 9月30日每份分红0.1元，单位净值下降但累计净值上涨
+        let second = FundData::new(date!(2021 - 9 - 30), 19500, 30500, Some(1000));
+        let records = [second, first];
+        let (latest, change) = latest_change(&records).unwrap();
+        assert_eq!(latest.date, date!(2021 - 9 - 30));
+        assert!((change.unwrap() - 0.025).abs() < 1e-12);
+        // 10月8日每份折算为2份，单位净值减半，涨跌幅为0
+        let mut third = FundData::new(date!(2021 - 10 - 8), 9750, 30500, None);
+        third.event = Some(FundEvent::Split(2 * FundEvent::SPLIT_SCALE));
+        let (_, change) = latest_change(&[first, second, third]).unwrap();
+        assert!(change.unwrap().abs() < 1e-12);
+    }
+}
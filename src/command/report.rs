@@ -0,0 +1,31 @@
+//! ## report子命令
+//! ----
+//!
+//! 读取`backtest --output json`保存的结果，重新输出为html、csv或json，不需要重新回测
+
+use crate::report::export::{OutputFormat, Report};
+use anyhow::Result;
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct ReportOpt {
+    /// the json file saved by `backtest --output json`
+    #[clap(short, long, parse(from_os_str), required = true)]
+    input: PathBuf,
+
+    /// [optional] the format to render: html, csv or json
+    #[clap(long, default_value = "html")]
+    output: OutputFormat,
+
+    /// where to write the rendered result
+    #[clap(long, parse(from_os_str), required = true)]
+    output_file: PathBuf,
+}
+
+pub fn run(opt: &ReportOpt) -> Result<()> {
+    let report = Report::read_json(&opt.input)?;
+    report.write(opt.output, &opt.output_file)?;
+    println!("result rendered to {}", opt.output_file.display());
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! ## search子命令
+//! ----
+//!
+//! 在全部基金列表(见market::fund_universe)中按代码、名称片段或拼音缩写查找基金，
+//! 可按类型筛选；只给类型时列出该类型的全部基金
+
+use super::SourceOpt;
+use crate::market::fund_profile::FundCategory;
+use crate::market::fund_universe::{FundListing, FundUniverse};
+use anyhow::{bail, Result};
+use clap::Args;
+
+#[derive(Args, Debug)]
+pub struct SearchOpt {
+    /// [optional] a code, name fragment or pinyin initials, e.g. 华夏 or hxcz
+    #[clap(name = "QUERY")]
+    query: Option<String>,
+
+    /// [optional] only list funds of this type: equity, hybrid, bond, qdii, money, index or other
+    #[clap(short, long)]
+    category: Option<FundCategory>,
+
+    /// [optional] the maximum number of funds to print, 0 for all
+    #[clap(short, long, default_value = "20")]
+    limit: usize,
+
+    #[clap(flatten)]
+    source: SourceOpt,
+}
+
+pub fn run(opt: &SearchOpt) -> Result<()> {
+    for line in listing(opt)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// 输出的各行，超过--limit时最后一行说明省略的数量
+fn listing(opt: &SearchOpt) -> Result<Vec<String>> {
+    if opt.query.is_none() && opt.category.is_none() {
+        bail!("give a query or --category");
+    }
+    let universe = FundUniverse::load(opt.source.universe().as_ref())?;
+    let found = select(&universe, opt.query.as_deref(), opt.category);
+    let shown = match opt.limit {
+        0 => found.len(),
+        limit => found.len().min(limit),
+    };
+    let mut lines: Vec<String> = found[..shown]
+        .iter()
+        .map(|fund| {
+            format!(
+                "{:0>6} {} [{}] {}",
+                fund.code, fund.name, fund.fund_type, fund.abbr
+            )
+        })
+        .collect();
+    if shown < found.len() {
+        lines.push(format!(
+            "... {} of {} funds shown, see --limit",
+            shown,
+            found.len()
+        ));
+    } else if found.is_empty() {
+        lines.push(format!("no fund found in {} funds", universe.len()));
+    }
+    Ok(lines)
+}
+
+/// 按查询和类型选出基金，有查询时按匹配程度排序，否则按代码排序
+fn select<'a>(
+    universe: &'a FundUniverse,
+    query: Option<&str>,
+    category: Option<FundCategory>,
+) -> Vec<&'a FundListing> {
+    match (query, category) {
+        (Some(query), Some(category)) => universe
+            .search(query)
+            .into_iter()
+            .filter(|x| x.category() == category)
+            .collect(),
+        (Some(query), None) => universe.search(query),
+        (None, Some(category)) => universe.filter(category).collect(),
+        (None, None) => universe.funds().iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+
+    #[derive(Parser)]
+    struct Wrapper {
+        #[clap(flatten)]
+        opt: SearchOpt,
+    }
+
+    #[test]
+    fn test_search_listing() {
+        let dir = std::env::temp_dir().join(format!("trade_helper_search_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut content = String::from("code,name,abbr,type\n");
+        for code in 1..=25 {
+            content += &format!("{},华夏债券{},HXZQ{},债券型-长债\n", code, code, code);
+        }
+        content += "100,华夏成长混合,HXCZHH,混合型-偏股\n";
+        fs::write(dir.join("funds.csv"), content).unwrap();
+        let parse = |args: &[&str]| {
+            let mut argv = vec!["search", "--data-dir", dir.to_str().unwrap()];
+            argv.extend(args);
+            Wrapper::try_parse_from(argv).unwrap().opt
+        };
+
+        // 查询和类型同时给出时两者都要满足
+        let lines = listing(&parse(&["华夏", "-c", "hybrid"])).unwrap();
+        assert_eq!(lines, ["000100 华夏成长混合 [混合型-偏股] HXCZHH"]);
+        let lines = listing(&parse(&["hxcz", "-c", "bond"])).unwrap();
+        assert_eq!(lines, ["no fund found in 26 funds"]);
+
+        // 默认最多20条，--limit 0输出全部
+        let lines = listing(&parse(&["-c", "bond"])).unwrap();
+        assert_eq!(lines.len(), 21);
+        assert_eq!(lines[20], "... 20 of 25 funds shown, see --limit");
+        let lines = listing(&parse(&["-c", "bond", "--limit", "0"])).unwrap();
+        assert_eq!(lines.len(), 25);
+        assert_eq!(lines[24], "000025 华夏债券25 [债券型-长债] HXZQ25");
+
+        assert!(listing(&parse(&[])).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+//! ## 回测配置文件
+//! ----
+//!
+//! 以TOML(.toml)或YAML(.yaml/.yml)文件描述一次回测，所有项均可省略，命令行参数优先于文件中的值：
+//! ```toml
+//! begin = 20210101
+//! end = 20211231
+//! day = 1                       # 默认每月买入日，也可以用schedule指定买入计划
+//! schedule = "weekly:mon"       # weekly/biweekly:<星期>、monthly:<日>、month-end或dates:<yyyymmdd>,...
+//! strategy = "buy-more"
+//! benchmark = 300
+//! risk_free = 2.0
+//! data_dir = "data"
+//!
+//! [params.buy-more]             # 各策略的参数
+//! max_multiple = 3
+//!
+//! [fee]                         # 所有基金默认的费率
+//! subscription_rate = 1.5
+//! discount = 0.1
+//! redemption_fee = "7:1.5,365:0.5"
+//! management_rate = 1.2
+//!
+//! [cash]
+//! initial = 10000
+//! deposit = "3000.50"
+//! deposit_day = 10
+//! yield = 2.0
+//! partial_fill = true
+//!
+//! [[funds]]
+//! code = 2021
+//! budget = 100
+//! schedule = "month-end"        # 单独指定买入计划(或用day指定每月买入日)
+//! dividend = "cash"             # reinvest或cash
+//! fee = { subscription_rate = 1.2 }
+//!
+//! [output]
+//! format = "html"
+//! file = "report.html"
+//! ```
+//! 金额可以写成数字或字符串，写成字符串时按十进制精确解析
+
+use crate::account::DividendPolicy;
+use crate::decimal::Money;
+use crate::report::export::OutputFormat;
+use crate::strategy::registry::StrategyParams;
+use crate::strategy::schedule::Schedule;
+use anyhow::{bail, Context, Result};
+use serde::{de, Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 回测配置
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BacktestConfig {
+    pub(crate) begin: Option<u32>,
+    pub(crate) end: Option<u32>,
+    pub(crate) day: Option<u8>,
+    pub(crate) schedule: Option<Schedule>,
+    pub(crate) strategy: Option<String>,
+    pub(crate) params: HashMap<String, StrategyParams>, // 策略名到参数
+    pub(crate) benchmark: Option<u32>,
+    pub(crate) risk_free: Option<f64>,
+    pub(crate) data_dir: Option<PathBuf>,
+    pub(crate) fee: FeeConfig,
+    pub(crate) cash: CashConfig,
+    pub(crate) funds: Vec<FundConfig>,
+    pub(crate) output: OutputConfig,
+}
+
+/// 费率，省略的项使用默认费率
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeConfig {
+    pub(crate) subscription_rate: Option<f32>,
+    pub(crate) discount: Option<f32>,
+    pub(crate) redemption_fee: Option<String>,
+    pub(crate) management_rate: Option<f32>,
+}
+
+/// 现金计划
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CashConfig {
+    #[serde(deserialize_with = "decimal")]
+    pub(crate) initial: Option<Money>,
+    #[serde(deserialize_with = "decimal")]
+    pub(crate) deposit: Option<Money>,
+    pub(crate) deposit_day: Option<u8>,
+    #[serde(rename = "yield")]
+    pub(crate) cash_yield: Option<f32>,
+    pub(crate) partial_fill: Option<bool>,
+}
+
+/// 单只基金的定投设置
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FundConfig {
+    pub(crate) code: u32,
+    #[serde(deserialize_with = "required_decimal")]
+    pub(crate) budget: Money,
+    #[serde(default)]
+    pub(crate) day: Option<u8>,
+    #[serde(default)]
+    pub(crate) schedule: Option<Schedule>,
+    #[serde(default)]
+    pub(crate) fee: FeeConfig,
+    #[serde(default)]
+    pub(crate) dividend: Option<DividendPolicy>,
+}
+
+/// 导出设置
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub(crate) format: Option<OutputFormat>,
+    pub(crate) file: Option<PathBuf>,
+}
+
+impl BacktestConfig {
+    /// 按扩展名读取TOML或YAML配置文件，相对路径的data_dir和输出文件以配置文件所在目录为基准
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config {}", path.display()))?;
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(str::to_ascii_lowercase);
+        let mut config: BacktestConfig = match extension.as_deref() {
+            Some("toml") => toml::from_str(&content).map_err(anyhow::Error::from),
+            Some("yaml") | Some("yml") => {
+                serde_yaml::from_str(&content).map_err(anyhow::Error::from)
+            }
+            _ => bail!(
+                "unsupported config {}, expected .toml, .yaml or .yml",
+                path.display()
+            ),
+        }
+        .with_context(|| format!("invalid config {}", path.display()))?;
+        if let Some(base) = path.parent() {
+            config.data_dir = config.data_dir.map(|x| base.join(x));
+            config.output.file = config.output.file.map(|x| base.join(x));
+        }
+        Ok(config)
+    }
+}
+
+/// 写成字符串或数字的值，按FromStr解析
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Text {
+    String(String),
+    Integer(i64),
+    Float(f64),
+}
+
+impl Text {
+    fn parse<T, E>(self) -> Result<T, E>
+    where
+        T: FromStr,
+        T::Err: Display,
+        E: de::Error,
+    {
+        let text = match self {
+            Text::String(x) => x,
+            Text::Integer(x) => x.to_string(),
+            Text::Float(x) => x.to_string(),
+        };
+        text.parse().map_err(E::custom)
+    }
+}
+
+fn decimal<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Option::<Text>::deserialize(deserializer)?
+        .map(Text::parse)
+        .transpose()
+}
+
+fn required_decimal<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Text::deserialize(deserializer)?.parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("trade_helper_config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_toml_and_yaml() {
+        let toml = write_config(
+            "plan.toml",
+            r#"
+begin = 20210101
+strategy = "buy-more"
+
+[params.buy-more]
+max_multiple = 3
+
+[cash]
+initial = 10000
+deposit = "3000.005"
+yield = 2.0
+
+[[funds]]
+code = 2021
+budget = 100.5
+dividend = "cash"
+fee = { subscription_rate = 1.2 }
+
+[[funds]]
+code = 70032
+budget = "200"
+day = 15
+schedule = "weekly:fri"
+
+[output]
+format = "html"
+file = "report.html"
+"#,
+        );
+        let yaml = write_config(
+            "plan.yaml",
+            r#"
+begin: 20210101
+strategy: buy-more
+params:
+  buy-more:
+    max_multiple: 3
+cash:
+  initial: 10000
+  deposit: "3000.005"
+  yield: 2.0
+funds:
+  - code: 2021
+    budget: 100.5
+    dividend: cash
+    fee:
+      subscription_rate: 1.2
+  - code: 70032
+    budget: "200"
+    day: 15
+    schedule: weekly:fri
+output:
+  format: html
+  file: report.html
+"#,
+        );
+        let config = BacktestConfig::load(&toml).unwrap();
+        assert_eq!(config, BacktestConfig::load(&yaml).unwrap());
+        assert_eq!(config.begin, Some(20210101));
+        assert_eq!(config.end, None);
+        assert_eq!(config.params["buy-more"]["max_multiple"], 3.0);
+        assert_eq!(config.cash.initial, Some(Money::from_raw(10_000_000_000)));
+        assert_eq!(config.cash.deposit, Some(Money::from_raw(3_000_005_000)));
+        assert_eq!(config.funds.len(), 2);
+        assert_eq!(config.funds[0].budget, Money::from_raw(100_500_000));
+        assert_eq!(config.funds[0].dividend, Some(DividendPolicy::Cash));
+        assert_eq!(config.funds[0].fee.subscription_rate, Some(1.2));
+        assert_eq!(config.funds[1].day, Some(15));
+        assert_eq!(
+            config.funds[1].schedule,
+            Some(Schedule::Weekly(time::Weekday::Friday))
+        );
+        assert_eq!(config.output.format, Some(OutputFormat::Html));
+        assert_eq!(
+            config.output.file,
+            Some(toml.parent().unwrap().join("report.html"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let unknown = write_config("unknown.toml", "begn = 20210101\n");
+        assert!(BacktestConfig::load(&unknown).is_err());
+        let budget = write_config("budget.toml", "[[funds]]\ncode = 1\nbudget = \"1x\"\n");
+        assert!(BacktestConfig::load(&budget).is_err());
+        let format = write_config("format.toml", "[output]\nformat = 1\n");
+        assert!(BacktestConfig::load(&format).is_err());
+        let json = write_config("plan.json", "{}");
+        assert!(BacktestConfig::load(&json).is_err());
+    }
+}
@@ -0,0 +1,481 @@
+#![cfg_attr(debug_assertions, allow(dead_code))]
+
+//! ## 定点数
+//! ----
+//!
+//! 为避免浮点运算，价格、份额和金额都以放大后的整数保存：
+//! + Price: 价格(单位净值、股价、每份分红)，真实值乘以10000
+//! + Shares: 份额(股数)，真实值乘以100
+//! + Money: 金额，真实值乘以1000000，可以为负
+//!
+//! 价格乘以份额恰好得到金额，不需要舍入。其余情况的舍入规则：
+//! + 从十进制字符串解析时，超出精度的部分四舍五入
+//! + 金额折算份额、按比例拆分份额或金额时向下取整，与基金份额保留两位小数、舍去尾数的规则一致
+//!
+//! checked_*方法在溢出时返回None，运算符在溢出时panic，不会静默回绕
+
+use anyhow::{anyhow, Result};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// 解析十进制字符串，返回放大10^digits倍的整数，超出精度的部分四舍五入
+pub(crate) fn parse_decimal(s: &str, digits: u32) -> Result<i128> {
+    let invalid = || anyhow!("invalid decimal: {:?}", s);
+    let s = s.trim();
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (int_part, frac_part) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let is_digits = |x: &str| x.bytes().all(|b| b.is_ascii_digit());
+    if (int_part.is_empty() && frac_part.is_empty())
+        || !is_digits(int_part)
+        || !is_digits(frac_part)
+    {
+        return Err(invalid());
+    }
+    let mut value: i128 = 0;
+    for b in int_part.bytes() {
+        value = value
+            .checked_mul(10)
+            .and_then(|x| x.checked_add((b - b'0') as i128))
+            .ok_or_else(invalid)?;
+    }
+    let frac = frac_part.as_bytes();
+    for i in 0..digits as usize {
+        let digit = frac.get(i).map_or(0, |b| (b - b'0') as i128);
+        value = value
+            .checked_mul(10)
+            .and_then(|x| x.checked_add(digit))
+            .ok_or_else(invalid)?;
+    }
+    if frac.get(digits as usize).is_some_and(|b| *b >= b'5') {
+        value += 1;
+    }
+    Ok(if negative { -value } else { value })
+}
+
+/// 将放大10^digits倍的整数格式化为保留places位小数的十进制字符串，四舍五入
+fn format_decimal(f: &mut fmt::Formatter, raw: i128, digits: u32, places: u32) -> fmt::Result {
+    let drop = 10_i128.pow(digits - places);
+    let rounded = (raw.abs() + drop / 2) / drop;
+    let unit = 10_i128.pow(places);
+    let sign = if raw < 0 && rounded != 0 { "-" } else { "" };
+    if places == 0 {
+        write!(f, "{}{}", sign, rounded)
+    } else {
+        write!(
+            f,
+            "{}{}.{:0width$}",
+            sign,
+            rounded / unit,
+            rounded % unit,
+            width = places as usize
+        )
+    }
+}
+
+/// 缓存和本地数据中的两种表示：接口返回的字符串，或已放大的整数
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DecimalRepr {
+    Text(String),
+    Scaled(i64),
+}
+
+/// 价格，真实值乘以10000
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Price(u32);
+
+impl Price {
+    pub const SCALE: u32 = 10_000;
+    pub const ZERO: Price = Price(0);
+
+    /// 由放大10000倍的整数构造
+    pub const fn from_raw(raw: u32) -> Self {
+        Price(raw)
+    }
+
+    /// 放大10000倍的整数
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    /// 乘以numerator/denominator，向下取整
+    pub fn mul_ratio(self, numerator: u64, denominator: u64) -> Price {
+        let raw = self.0 as u128 * numerator as u128 / denominator.max(1) as u128;
+        Price(u32::try_from(raw).expect("price overflow"))
+    }
+
+    /// 价格乘以份额得到金额，没有精度损失
+    pub fn checked_mul(self, shares: Shares) -> Option<Money> {
+        i64::try_from(self.0 as u64 * shares.0 as u64)
+            .ok()
+            .map(Money)
+    }
+}
+
+impl Mul<Shares> for Price {
+    type Output = Money;
+
+    fn mul(self, shares: Shares) -> Money {
+        self.checked_mul(shares)
+            .unwrap_or_else(|| panic!("money overflow: {} * {}", self, shares))
+    }
+}
+
+impl FromStr for Price {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let raw = parse_decimal(s, 4)?;
+        u32::try_from(raw)
+            .map(Price)
+            .map_err(|_| anyhow!("price out of range: {:?}", s))
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        format_decimal(f, self.0 as i128, 4, 4)
+    }
+}
+
+// 缓存中直接保存放大后的整数，避免十进制往返
+impl Serialize for Price {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Price {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match DecimalRepr::deserialize(deserializer)? {
+            DecimalRepr::Text(s) => s.parse().map_err(de::Error::custom),
+            DecimalRepr::Scaled(raw) => u32::try_from(raw)
+                .map(Price)
+                .map_err(|_| de::Error::custom(format!("price out of range: {}", raw))),
+        }
+    }
+}
+
+/// 份额(股数)，真实值乘以100
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Shares(u32);
+
+impl Shares {
+    pub const SCALE: u32 = 100;
+    pub const ZERO: Shares = Shares(0);
+
+    /// 由放大100倍的整数构造
+    pub const fn from_raw(raw: u32) -> Self {
+        Shares(raw)
+    }
+
+    /// 放大100倍的整数
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    pub fn checked_add(self, rhs: Shares) -> Option<Shares> {
+        self.0.checked_add(rhs.0).map(Shares)
+    }
+
+    pub fn checked_sub(self, rhs: Shares) -> Option<Shares> {
+        self.0.checked_sub(rhs.0).map(Shares)
+    }
+
+    /// 乘以numerator/denominator，向下取整
+    pub fn mul_ratio(self, numerator: u64, denominator: u64) -> Shares {
+        let raw = self.0 as u128 * numerator as u128 / denominator.max(1) as u128;
+        Shares(u32::try_from(raw).expect("shares overflow"))
+    }
+
+    /// 按比例(0到1之间)拆分，向下取整
+    pub fn proportion(self, proportion: f64) -> Shares {
+        Shares((self.0 as f64 * proportion.clamp(0.0, 1.0)) as u32)
+    }
+}
+
+impl Add for Shares {
+    type Output = Shares;
+
+    fn add(self, rhs: Shares) -> Shares {
+        self.checked_add(rhs).expect("shares overflow")
+    }
+}
+
+impl AddAssign for Shares {
+    fn add_assign(&mut self, rhs: Shares) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Shares {
+    type Output = Shares;
+
+    fn sub(self, rhs: Shares) -> Shares {
+        self.checked_sub(rhs).expect("shares underflow")
+    }
+}
+
+impl SubAssign for Shares {
+    fn sub_assign(&mut self, rhs: Shares) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sum for Shares {
+    fn sum<I: Iterator<Item = Shares>>(iter: I) -> Shares {
+        iter.fold(Shares::ZERO, Add::add)
+    }
+}
+
+impl FromStr for Shares {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let raw = parse_decimal(s, 2)?;
+        u32::try_from(raw)
+            .map(Shares)
+            .map_err(|_| anyhow!("shares out of range: {:?}", s))
+    }
+}
+
+impl fmt::Display for Shares {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        format_decimal(f, self.0 as i128, 2, 2)
+    }
+}
+
+/// 金额，真实值乘以1000000，可以为负
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const SCALE: i64 = 1_000_000;
+    pub const ZERO: Money = Money(0);
+
+    /// 由放大1000000倍的整数构造
+    pub const fn from_raw(raw: i64) -> Self {
+        Money(raw)
+    }
+
+    /// 放大1000000倍的整数
+    pub const fn raw(self) -> i64 {
+        self.0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    pub fn checked_add(self, rhs: Money) -> Option<Money> {
+        self.0.checked_add(rhs.0).map(Money)
+    }
+
+    pub fn checked_sub(self, rhs: Money) -> Option<Money> {
+        self.0.checked_sub(rhs.0).map(Money)
+    }
+
+    /// 乘以numerator/denominator，向零取整
+    pub fn mul_ratio(self, numerator: u64, denominator: u64) -> Money {
+        let raw = self.0 as i128 * numerator as i128 / denominator.max(1) as i128;
+        Money(i64::try_from(raw).expect("money overflow"))
+    }
+
+    /// 按价格折算份额，向下取整，金额为负时返回0份
+    pub fn shares_at(self, price: Price) -> Shares {
+        if price.is_zero() || self.0 <= 0 {
+            return Shares::ZERO;
+        }
+        Shares(u32::try_from(self.0 / price.0 as i64).expect("shares overflow"))
+    }
+
+    /// 每份的价格，向下取整，份额为0时返回None
+    pub fn price_per(self, shares: Shares) -> Option<Price> {
+        if shares.is_zero() || self.0 < 0 {
+            return None;
+        }
+        u32::try_from(self.0 / shares.0 as i64).ok().map(Price)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, rhs: Money) -> Money {
+        self.checked_add(rhs).expect("money overflow")
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, rhs: Money) -> Money {
+        self.checked_sub(rhs).expect("money overflow")
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(self.0.checked_neg().expect("money overflow"))
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl FromStr for Money {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let raw = parse_decimal(s, 6)?;
+        i64::try_from(raw)
+            .map(Money)
+            .map_err(|_| anyhow!("money out of range: {:?}", s))
+    }
+}
+
+/// 保留两位小数
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        format_decimal(f, self.0 as i128, 6, 2)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match DecimalRepr::deserialize(deserializer)? {
+            DecimalRepr::Text(s) => s.parse().map_err(de::Error::custom),
+            DecimalRepr::Scaled(raw) => Ok(Money(raw)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exact() {
+        // 不经过浮点数，按十进制精确解析
+        assert_eq!("1.2880".parse::<Price>().unwrap(), Price::from_raw(12880));
+        assert_eq!("0.03".parse::<Price>().unwrap(), Price::from_raw(300));
+        assert_eq!("2".parse::<Price>().unwrap(), Price::from_raw(20000));
+        // 超出精度的部分四舍五入
+        assert_eq!("1.00005".parse::<Price>().unwrap(), Price::from_raw(10001));
+        assert_eq!("1.00004".parse::<Price>().unwrap(), Price::from_raw(10000));
+        assert_eq!(
+            "100.15".parse::<Money>().unwrap(),
+            Money::from_raw(100150000)
+        );
+        assert_eq!("-0.5".parse::<Money>().unwrap(), Money::from_raw(-500000));
+        assert_eq!("12.345".parse::<Shares>().unwrap(), Shares::from_raw(1235));
+        assert!("".parse::<Price>().is_err());
+        assert!("1.2.3".parse::<Price>().is_err());
+        assert!("-1".parse::<Price>().is_err());
+        assert!("abc".parse::<Money>().is_err());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Price::from_raw(12880).to_string(), "1.2880");
+        assert_eq!(Shares::from_raw(4992).to_string(), "49.92");
+        assert_eq!(Money::from_raw(99_845_000).to_string(), "99.85");
+        assert_eq!(Money::from_raw(-1_500_000).to_string(), "-1.50");
+        assert_eq!(Money::from_raw(-1).to_string(), "0.00");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let price = Price::from_raw(20000);
+        let shares = Shares::from_raw(4992);
+        assert_eq!(price * shares, Money::from_raw(99_840_000));
+        // 金额折算份额向下取整
+        assert_eq!(
+            Money::from_raw(99_850_224).shares_at(price),
+            Shares::from_raw(4992)
+        );
+        assert_eq!(
+            Money::from_raw(200_000_000).price_per(Shares::from_raw(10000)),
+            Some(price)
+        );
+        assert_eq!(Money::from_raw(1).price_per(Shares::ZERO), None);
+        assert_eq!(
+            Shares::from_raw(10000).mul_ratio(3, 7),
+            Shares::from_raw(4285)
+        );
+        assert_eq!(Money::from_raw(-7).mul_ratio(1, 2), Money::from_raw(-3));
+        // 两个u32相乘超过i64的范围
+        assert_eq!(
+            Price::from_raw(u32::MAX).checked_mul(Shares::from_raw(u32::MAX)),
+            None
+        );
+        assert_eq!(Shares::from_raw(1).checked_sub(Shares::from_raw(2)), None);
+        assert_eq!(
+            Money::from_raw(i64::MAX).checked_add(Money::from_raw(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let price: Price = serde_json::from_str("\"1.2880\"").unwrap();
+        assert_eq!(price, Price::from_raw(12880));
+        let price: Price = serde_json::from_str("12880").unwrap();
+        assert_eq!(price, Price::from_raw(12880));
+        assert_eq!(serde_json::to_string(&price).unwrap(), "12880");
+        assert!(serde_json::from_str::<Price>("-1").is_err());
+    }
+}
@@ -0,0 +1,691 @@
+//! ## 本地行情缓存
+//! ----
+//!
+//! 按标的代码在本地保存已下载的历史行情，同时记录已覆盖的日期区间。
+//! 再次查询同一标的时，只需下载尚未覆盖的日期区间，下载结果合并进本地序列。
+//!
+//! + 缓存目录默认为`$HOME/.cache/trade_helper_rs`，可通过环境变量`TRADE_HELPER_CACHE_DIR`指定
+//! + 每个标的一个json文件，文件名为`<类型>_<六位代码>.json`
+//! + 基金资料(见fund_profile模块)保存在`profile_<六位代码>.json`，超过PROFILE_MAX_AGE天重新查询
+//! + 全部基金列表(见fund_universe模块)保存在`universe.json`，超过UNIVERSE_MAX_AGE天重新下载
+//!
+//! CachedProvider为任意数据源加上缓存，InfoMixer::new默认使用带缓存的东方财富数据源
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use time::{Date, Duration, OffsetDateTime};
+
+use super::error::MarketError;
+use super::fund_profile::{profile_file_name, FundProfile, QueryFundProfile};
+use super::fund_universe::{FundListing, QueryFundUniverse};
+use super::{MarketCode, QuantitativeMarket, QueryMarketInfo};
+
+/// 指定缓存目录的环境变量
+pub const CACHE_DIR_ENV: &str = "TRADE_HELPER_CACHE_DIR";
+
+/// 行情发布可能滞后的天数，距今不足该天数的区间只记录到最后一条行情为止
+const SETTLE_DAYS: i64 = 7;
+
+/// 基金资料缓存的有效天数，规模、基金经理等会变化
+const PROFILE_MAX_AGE: i64 = 30;
+
+/// 基金列表缓存的有效天数，期间新发的基金查不到
+const UNIVERSE_MAX_AGE: i64 = 7;
+
+/// 基金列表的缓存文件名
+const UNIVERSE_FILE_NAME: &str = "universe.json";
+
+/// 可以保存到本地缓存的行情
+pub trait Cacheable: QuantitativeMarket + Serialize + DeserializeOwned {
+    /// 缓存文件名前缀，区分不同种类的行情
+    const CACHE_NAME: &'static str;
+    /// 缓存格式的版本，行情增加字段时加一，其他版本的缓存会被丢弃并重新下载
+    const CACHE_VERSION: u32 = 0;
+}
+
+/// 单个标的的缓存内容
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T: Cacheable")]
+struct CacheEntry<T> {
+    // 缓存格式的版本
+    #[serde(default)]
+    version: u32,
+    // 已覆盖的日期区间，按起始日期排序且互不重叠
+    ranges: Vec<(Date, Date)>,
+    // 行情记录，按时间先后排序
+    records: Vec<T>,
+}
+
+impl<T> Default for CacheEntry<T> {
+    fn default() -> Self {
+        CacheEntry {
+            version: 0,
+            ranges: Vec::new(),
+            records: Vec::new(),
+        }
+    }
+}
+
+/// 缓存的基金资料及查询日期
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileEntry {
+    fetched: Date,
+    profile: FundProfile,
+}
+
+/// 缓存的基金列表及下载日期
+#[derive(Debug, Serialize, Deserialize)]
+struct UniverseEntry {
+    fetched: Date,
+    funds: Vec<FundListing>,
+}
+
+/// 本地行情缓存
+#[derive(Debug, Clone)]
+pub struct MarketCache {
+    dir: PathBuf,
+}
+
+/// 缓存目录，交易日历的更新文件(见calendar模块)也放在这里
+pub(crate) fn cache_dir() -> PathBuf {
+    std::env::var_os(CACHE_DIR_ENV)
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".cache").join("trade_helper_rs"))
+        })
+        .unwrap_or_else(|| PathBuf::from(".trade_helper_cache"))
+}
+
+impl Default for MarketCache {
+    fn default() -> Self {
+        MarketCache { dir: cache_dir() }
+    }
+}
+
+impl MarketCache {
+    /// 使用指定目录作为缓存
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        MarketCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn entry_path<T: Cacheable>(&self, code: MarketCode) -> PathBuf {
+        self.dir
+            .join(format!("{}_{:0>6}.json", T::CACHE_NAME, code))
+    }
+
+    fn read_entry<T: Cacheable>(&self, code: MarketCode) -> CacheEntry<T> {
+        // 缓存损坏或版本不同时当作没有缓存，重新下载即可
+        fs::read_to_string(self.entry_path::<T>(code))
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheEntry<T>>(&content).ok())
+            .filter(|entry| entry.version == T::CACHE_VERSION)
+            .unwrap_or_default()
+    }
+
+    fn write_entry<T: Cacheable>(&self, code: MarketCode, entry: &CacheEntry<T>) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.entry_path::<T>(code);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(entry)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// 读取缓存的基金资料及其查询日期
+    fn load_profile(&self, code: MarketCode) -> Option<(Date, FundProfile)> {
+        fs::read_to_string(self.dir.join(profile_file_name(code)))
+            .ok()
+            .and_then(|content| serde_json::from_str::<ProfileEntry>(&content).ok())
+            .map(|entry| (entry.fetched, entry.profile))
+    }
+
+    fn store_profile(&self, profile: &FundProfile) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = ProfileEntry {
+            fetched: OffsetDateTime::now_utc().date(),
+            profile: profile.clone(),
+        };
+        fs::write(
+            self.dir.join(profile_file_name(profile.code)),
+            serde_json::to_string(&entry)?,
+        )?;
+        Ok(())
+    }
+
+    /// 读取缓存的基金列表及其下载日期
+    fn load_universe(&self) -> Option<(Date, Vec<FundListing>)> {
+        fs::read_to_string(self.dir.join(UNIVERSE_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str::<UniverseEntry>(&content).ok())
+            .map(|entry| (entry.fetched, entry.funds))
+    }
+
+    fn store_universe(&self, funds: &[FundListing]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = UniverseEntry {
+            fetched: OffsetDateTime::now_utc().date(),
+            funds: funds.to_vec(),
+        };
+        let path = self.dir.join(UNIVERSE_FILE_NAME);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&entry)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// 读取缓存，返回指定日期范围内已缓存的行情，以及需要重新下载的日期区间
+    pub fn load<T: Cacheable>(
+        &self,
+        code: MarketCode,
+        start_date: Date,
+        end_date: Date,
+    ) -> (Vec<T>, Vec<(Date, Date)>) {
+        let entry = self.read_entry::<T>(code);
+        let missing = missing_ranges(&entry.ranges, start_date, end_date);
+        let records = entry
+            .records
+            .into_iter()
+            .filter(|x| in_range(x, start_date, end_date))
+            .collect();
+        (records, missing)
+    }
+
+    /// 将新下载的行情合并进缓存，`fetched`中每一项为下载的日期区间及其行情
+    pub fn store<T: Cacheable>(
+        &self,
+        code: MarketCode,
+        fetched: Vec<((Date, Date), Vec<T>)>,
+    ) -> Result<()> {
+        let mut entry = self.read_entry::<T>(code);
+        entry.version = T::CACHE_VERSION;
+        let today = OffsetDateTime::now_utc().date();
+        let mut covered = false;
+        for ((start_date, end_date), records) in fetched {
+            // 早已结算的区间即使没有行情(节假日、成立日之前)也记录覆盖，
+            // 近期的区间只记录到最后一条行情为止，没有行情时下次重新下载
+            let last = records.iter().map(|x| x.get_info_datetime().date()).max();
+            let covered_end = match last {
+                _ if end_date + Duration::days(SETTLE_DAYS) < today => end_date,
+                Some(last) => last.min(end_date),
+                None => continue,
+            };
+            entry.ranges.push((start_date, covered_end));
+            entry.records.extend(
+                records
+                    .into_iter()
+                    .filter(|x| in_range(x, start_date, end_date)),
+            );
+            covered = true;
+        }
+        if !covered {
+            return Ok(());
+        }
+        entry.ranges = merge_ranges(entry.ranges);
+        entry.records.sort_by_key(|x| x.get_info_datetime());
+        entry.records.dedup_by_key(|x| x.get_info_datetime());
+        self.write_entry(code, &entry)
+    }
+}
+
+/// 带本地缓存的数据源，缓存未覆盖的日期区间才向内部数据源查询
+#[derive(Debug, Clone)]
+pub struct CachedProvider<P> {
+    inner: P,
+    cache: MarketCache,
+}
+
+impl<P> CachedProvider<P> {
+    pub fn new(inner: P, cache: MarketCache) -> Self {
+        CachedProvider { inner, cache }
+    }
+}
+
+impl<P> QueryMarketInfo for CachedProvider<P>
+where
+    P: QueryMarketInfo,
+    P::MarketInfo: Cacheable,
+{
+    type MarketInfo = P::MarketInfo;
+
+    fn query_history_info(
+        &self,
+        code: MarketCode,
+        start_date: Date,
+        end_date: Date,
+    ) -> Result<Vec<Self::MarketInfo>> {
+        let (mut infos, missing) = self
+            .cache
+            .load::<Self::MarketInfo>(code, start_date, end_date);
+        let mut fetched = Vec::with_capacity(missing.len());
+        for (missing_start, missing_end) in missing {
+            let records = self
+                .inner
+                .query_history_info(code, missing_start, missing_end)?;
+            infos.extend(
+                records
+                    .iter()
+                    .filter(|x| in_range(*x, start_date, end_date)),
+            );
+            fetched.push(((missing_start, missing_end), records));
+        }
+        infos.sort_by_key(|x| x.get_info_datetime());
+        infos.dedup_by_key(|x| x.get_info_datetime());
+        // 缓存写入失败不影响本次查询
+        if let Err(e) = self.cache.store(code, fetched) {
+            eprintln!("failed to update cache for {:0>6}: {}", code, e);
+        }
+        Ok(infos)
+    }
+}
+
+impl<P: QueryFundProfile> QueryFundProfile for CachedProvider<P> {
+    /// 缓存过期时重新查询，查询失败(基金代码不存在除外)时沿用过期的资料
+    fn query_profile(&self, code: MarketCode) -> Result<Option<FundProfile>> {
+        let cached = self.cache.load_profile(code);
+        let today = OffsetDateTime::now_utc().date();
+        match cached {
+            Some((fetched, profile)) if today - fetched < Duration::days(PROFILE_MAX_AGE) => {
+                return Ok(Some(profile))
+            }
+            _ => {}
+        }
+        match self.inner.query_profile(code) {
+            Ok(Some(profile)) => {
+                if let Err(e) = self.cache.store_profile(&profile) {
+                    eprintln!("failed to update cache for {:0>6}: {}", code, e);
+                }
+                Ok(Some(profile))
+            }
+            Ok(None) => Ok(cached.map(|x| x.1)),
+            Err(e) if matches!(e.downcast_ref(), Some(MarketError::UnknownCode(_))) => Err(e),
+            Err(e) => match cached {
+                Some((_, profile)) => Ok(Some(profile)),
+                None => Err(e),
+            },
+        }
+    }
+}
+
+impl<P: QueryFundUniverse> QueryFundUniverse for CachedProvider<P> {
+    /// 缓存过期时重新下载，下载失败时沿用过期的列表
+    fn query_universe(&self) -> Result<Vec<FundListing>> {
+        let cached = self.cache.load_universe();
+        let today = OffsetDateTime::now_utc().date();
+        match cached {
+            Some((fetched, funds)) if today - fetched < Duration::days(UNIVERSE_MAX_AGE) => {
+                return Ok(funds)
+            }
+            _ => {}
+        }
+        match self.inner.query_universe() {
+            Ok(funds) => {
+                if let Err(e) = self.cache.store_universe(&funds) {
+                    eprintln!("failed to update cache for the fund list: {}", e);
+                }
+                Ok(funds)
+            }
+            Err(e) => match cached {
+                Some((_, funds)) => Ok(funds),
+                None => Err(e),
+            },
+        }
+    }
+}
+
+fn in_range<T: QuantitativeMarket>(info: &T, start_date: Date, end_date: Date) -> bool {
+    let date = info.get_info_datetime().date();
+    date >= start_date && date <= end_date
+}
+
+/// 合并重叠或相邻的日期区间
+fn merge_ranges(mut ranges: Vec<(Date, Date)>) -> Vec<(Date, Date)> {
+    ranges.sort();
+    let mut merged: Vec<(Date, Date)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 + Duration::days(1) => {
+                last.1 = last.1.max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// 计算[start_date, end_date]中未被已缓存区间覆盖的部分
+fn missing_ranges(cached: &[(Date, Date)], start_date: Date, end_date: Date) -> Vec<(Date, Date)> {
+    let mut missing = Vec::new();
+    let mut cursor = start_date;
+    for &(start, end) in cached {
+        if cursor > end_date {
+            break;
+        }
+        if end < cursor {
+            continue;
+        }
+        if start > cursor {
+            missing.push((cursor, (start - Duration::days(1)).min(end_date)));
+        }
+        cursor = cursor.max(end + Duration::days(1));
+    }
+    if cursor <= end_date {
+        missing.push((cursor, end_date));
+    }
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::fund_market::FundData;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use time::macros::*;
+
+    // 每个自然日都有一条行情，并记录被查询的次数
+    struct CountingProvider(AtomicUsize);
+
+    impl QueryMarketInfo for CountingProvider {
+        type MarketInfo = FundData;
+
+        fn query_history_info(
+            &self,
+            _code: MarketCode,
+            start_date: Date,
+            end_date: Date,
+        ) -> Result<Vec<FundData>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let mut ret = Vec::new();
+            let mut date = start_date;
+            while date <= end_date {
+                ret.push(FundData::new(date, 10000, 10000, None));
+                date = date.next_day().unwrap();
+            }
+            Ok(ret)
+        }
+    }
+
+    fn temp_cache(name: &str) -> MarketCache {
+        let dir = std::env::temp_dir().join(format!(
+            "trade_helper_cache_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        MarketCache::new(dir)
+    }
+
+    #[test]
+    fn test_missing_ranges_without_cache() {
+        let missing = missing_ranges(&[], date!(2021 - 1 - 1), date!(2021 - 12 - 31));
+        assert_eq!(missing, vec![(date!(2021 - 1 - 1), date!(2021 - 12 - 31))]);
+    }
+
+    #[test]
+    fn test_missing_ranges_with_gaps() {
+        let cached = [
+            (date!(2021 - 3 - 1), date!(2021 - 5 - 31)),
+            (date!(2021 - 8 - 1), date!(2021 - 8 - 31)),
+        ];
+        let missing = missing_ranges(&cached, date!(2021 - 1 - 1), date!(2021 - 12 - 31));
+        assert_eq!(
+            missing,
+            vec![
+                (date!(2021 - 1 - 1), date!(2021 - 2 - 28)),
+                (date!(2021 - 6 - 1), date!(2021 - 7 - 31)),
+                (date!(2021 - 9 - 1), date!(2021 - 12 - 31)),
+            ]
+        );
+        let missing = missing_ranges(&cached, date!(2021 - 4 - 1), date!(2021 - 5 - 1));
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_merge_ranges() {
+        let ranges = vec![
+            (date!(2021 - 6 - 1), date!(2021 - 6 - 30)),
+            (date!(2021 - 1 - 1), date!(2021 - 5 - 31)),
+            (date!(2021 - 8 - 1), date!(2021 - 8 - 31)),
+        ];
+        assert_eq!(
+            merge_ranges(ranges),
+            vec![
+                (date!(2021 - 1 - 1), date!(2021 - 6 - 30)),
+                (date!(2021 - 8 - 1), date!(2021 - 8 - 31)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let cache = temp_cache("store");
+        let first = vec![
+            FundData::new(date!(2021 - 9 - 1), 12880, 38280, None),
+            FundData::new(date!(2021 - 9 - 2), 12900, 38300, Some(100)),
+        ];
+        let second = vec![
+            FundData::new(date!(2021 - 9 - 2), 12900, 38300, Some(100)),
+            FundData::new(date!(2021 - 9 - 3), 13000, 38400, None),
+        ];
+        cache
+            .store(
+                2021,
+                vec![((date!(2021 - 9 - 1), date!(2021 - 9 - 2)), first.clone())],
+            )
+            .unwrap();
+        cache
+            .store(
+                2021,
+                vec![((date!(2021 - 9 - 2), date!(2021 - 9 - 3)), second)],
+            )
+            .unwrap();
+
+        let (records, missing) =
+            cache.load::<FundData>(2021, date!(2021 - 9 - 1), date!(2021 - 9 - 3));
+        assert!(missing.is_empty());
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[..2], first[..]);
+
+        let (records, missing) =
+            cache.load::<FundData>(2021, date!(2021 - 9 - 2), date!(2021 - 9 - 10));
+        assert_eq!(records.len(), 2);
+        assert_eq!(missing, vec![(date!(2021 - 9 - 4), date!(2021 - 9 - 10))]);
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_outdated_version_is_discarded() {
+        let cache = temp_cache("version");
+        let range = (date!(2021 - 9 - 1), date!(2021 - 9 - 1));
+        let records = vec![FundData::new(date!(2021 - 9 - 1), 12880, 38280, None)];
+        cache.store(2021, vec![(range, records)]).unwrap();
+        assert!(cache.load::<FundData>(2021, range.0, range.1).1.is_empty());
+
+        let path = cache.entry_path::<FundData>(2021);
+        let content = fs::read_to_string(&path).unwrap();
+        let old = content.replace(
+            &format!("\"version\":{}", FundData::CACHE_VERSION),
+            "\"version\":0",
+        );
+        assert_ne!(old, content);
+        fs::write(&path, old).unwrap();
+        assert_eq!(
+            cache.load::<FundData>(2021, range.0, range.1).1,
+            vec![range]
+        );
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_cached_profile() {
+        struct Profiles(AtomicUsize);
+
+        impl QueryFundProfile for Profiles {
+            fn query_profile(&self, code: MarketCode) -> Result<Option<FundProfile>> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                match code {
+                    1 => Ok(Some(FundProfile {
+                        code,
+                        name: "test".into(),
+                        category: Default::default(),
+                        company: None,
+                        manager: None,
+                        size: None,
+                        inception: Some(date!(2001 - 12 - 18)),
+                        subscription_rate: Some(1.5),
+                        management_rate: None,
+                        redemption_fee: None,
+                    })),
+                    2 => Err(anyhow::anyhow!("network error")),
+                    _ => Err(MarketError::UnknownCode(code).into()),
+                }
+            }
+        }
+
+        let cache = temp_cache("profile");
+        let provider = CachedProvider::new(Profiles(AtomicUsize::new(0)), cache.clone());
+        let profile = provider.query_profile(1).unwrap().unwrap();
+        assert_eq!(provider.query_profile(1).unwrap(), Some(profile.clone()));
+        assert_eq!(provider.inner.0.load(Ordering::SeqCst), 1);
+        assert!(provider.query_profile(2).is_err());
+        assert!(provider.query_profile(3).is_err());
+
+        // 过期的资料在查询失败时沿用
+        let stale = ProfileEntry {
+            fetched: date!(2020 - 1 - 1),
+            profile: FundProfile { code: 2, ..profile },
+        };
+        fs::write(
+            cache.dir.join(profile_file_name(2)),
+            serde_json::to_string(&stale).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(provider.query_profile(2).unwrap().unwrap().code, 2);
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_cached_universe() {
+        struct Universe(AtomicUsize);
+
+        impl QueryFundUniverse for Universe {
+            fn query_universe(&self) -> Result<Vec<FundListing>> {
+                match self.0.fetch_add(1, Ordering::SeqCst) {
+                    0 => Ok(vec![FundListing {
+                        code: 1,
+                        name: "华夏成长混合".into(),
+                        abbr: "HXCZHH".into(),
+                        fund_type: "混合型-偏股".into(),
+                        pinyin: None,
+                    }]),
+                    _ => Err(anyhow::anyhow!("network error")),
+                }
+            }
+        }
+
+        let cache = temp_cache("universe");
+        let provider = CachedProvider::new(Universe(AtomicUsize::new(0)), cache.clone());
+        let funds = provider.query_universe().unwrap();
+        assert_eq!(provider.query_universe().unwrap(), funds);
+        assert_eq!(provider.inner.0.load(Ordering::SeqCst), 1);
+
+        // 过期的列表在下载失败时沿用
+        let stale = UniverseEntry {
+            fetched: date!(2020 - 1 - 1),
+            funds: funds.clone(),
+        };
+        fs::write(
+            cache.dir.join(UNIVERSE_FILE_NAME),
+            serde_json::to_string(&stale).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(provider.query_universe().unwrap(), funds);
+        assert_eq!(provider.inner.0.load(Ordering::SeqCst), 2);
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_empty_fetch_is_not_covered() {
+        let cache = temp_cache("empty");
+        let today = OffsetDateTime::now_utc().date();
+        let recent = (today - Duration::days(2), today);
+        cache
+            .store::<FundData>(2021, vec![(recent, vec![])])
+            .unwrap();
+        let (records, missing) = cache.load::<FundData>(2021, recent.0, recent.1);
+        assert!(records.is_empty());
+        assert_eq!(missing, vec![recent]);
+
+        // 早已结算的区间没有行情也记录覆盖
+        let settled = (date!(2021 - 10 - 1), date!(2021 - 10 - 7));
+        cache
+            .store::<FundData>(2021, vec![(settled, vec![])])
+            .unwrap();
+        let (records, missing) = cache.load::<FundData>(2021, settled.0, settled.1);
+        assert!(records.is_empty());
+        assert!(missing.is_empty());
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_cached_provider_skips_settled_gaps() {
+        struct Holidays(AtomicUsize);
+
+        impl QueryMarketInfo for Holidays {
+            type MarketInfo = FundData;
+
+            fn query_history_info(
+                &self,
+                _code: MarketCode,
+                _start_date: Date,
+                _end_date: Date,
+            ) -> Result<Vec<FundData>> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Ok(Vec::new())
+            }
+        }
+
+        let cache = temp_cache("holidays");
+        let provider = CachedProvider::new(Holidays(AtomicUsize::new(0)), cache.clone());
+        for _ in 0..2 {
+            let ret = provider
+                .query_history_info(1, date!(2021 - 10 - 1), date!(2021 - 10 - 7))
+                .unwrap();
+            assert!(ret.is_empty());
+        }
+        assert_eq!(provider.inner.0.load(Ordering::SeqCst), 1);
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+
+    #[test]
+    fn test_cached_provider_fetches_missing_only() {
+        let cache = temp_cache("provider");
+        let provider = CachedProvider::new(CountingProvider(AtomicUsize::new(0)), cache.clone());
+        let ret = provider
+            .query_history_info(1, date!(2020 - 1 - 1), date!(2020 - 1 - 10))
+            .unwrap();
+        assert_eq!(ret.len(), 10);
+        assert_eq!(provider.inner.0.load(Ordering::SeqCst), 1);
+
+        // 完全命中缓存，不再查询
+        let ret = provider
+            .query_history_info(1, date!(2020 - 1 - 3), date!(2020 - 1 - 5))
+            .unwrap();
+        assert_eq!(ret.len(), 3);
+        assert_eq!(provider.inner.0.load(Ordering::SeqCst), 1);
+
+        // 只查询两端缺失的区间
+        let ret = provider
+            .query_history_info(1, date!(2019 - 12 - 25), date!(2020 - 1 - 20))
+            .unwrap();
+        assert_eq!(ret.len(), 27);
+        assert_eq!(provider.inner.0.load(Ordering::SeqCst), 3);
+        assert!(ret.windows(2).all(|w| w[0].date < w[1].date));
+        let _ = fs::remove_dir_all(&cache.dir);
+    }
+}
@@ -0,0 +1,180 @@
+//! ## 交易日历
+//! ----
+//!
+//! 上海、深圳证券交易所的交易日为周一至周五中除去休市日的日子
+//! + 休市日随程序发布(data/cn_holidays.txt)，每行一个日期或日期区间，格式为yyyymmdd或yyyymmdd-yyyymmdd
+//! + 交易所每年年底公布下一年的休市安排，可在缓存目录(见cache模块)下放置同样格式的calendar.txt更新，
+//!   文件中出现的年份替换内置数据中这些年份的休市日
+//! + 没有休市安排的年份只排除周末
+
+use super::cache::cache_dir;
+use anyhow::{anyhow, bail, Error, Result};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use time::macros::format_description;
+use time::{Date, Weekday};
+
+/// 内置的休市日
+const BUNDLED: &str = include_str!("../../data/cn_holidays.txt");
+
+/// 缓存目录下用于更新休市日的文件
+pub const CALENDAR_FILE: &str = "calendar.txt";
+
+/// 交易日历
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TradingCalendar {
+    holidays: BTreeSet<Date>, // 周一至周五中的休市日
+    years: BTreeSet<i32>,     // 有休市安排的年份
+}
+
+impl TradingCalendar {
+    /// 沪深交易所的交易日历：内置的休市日加上缓存目录下的更新，更新文件无效时忽略
+    pub fn china() -> &'static TradingCalendar {
+        static CALENDAR: OnceLock<TradingCalendar> = OnceLock::new();
+        CALENDAR.get_or_init(|| {
+            let mut calendar: TradingCalendar = BUNDLED.parse().expect("invalid bundled calendar");
+            let path = cache_dir().join(CALENDAR_FILE);
+            if path.exists() {
+                match std::fs::read_to_string(&path)
+                    .map_err(Error::from)
+                    .and_then(|x| x.parse())
+                {
+                    Ok(update) => calendar.update(update),
+                    Err(e) => eprintln!("ignore invalid calendar {}: {:#}", path.display(), e),
+                }
+            }
+            calendar
+        })
+    }
+
+    /// 以update中出现的年份的休市日替换当前日历中这些年份的休市日
+    pub fn update(&mut self, update: TradingCalendar) {
+        self.holidays.retain(|x| !update.years.contains(&x.year()));
+        self.holidays.extend(update.holidays);
+        self.years.extend(update.years);
+    }
+
+    pub fn is_trading_day(&self, date: Date) -> bool {
+        !is_weekend(date) && !self.holidays.contains(&date)
+    }
+
+    /// date之后的第一个交易日
+    pub fn next_trading_day(&self, date: Date) -> Date {
+        let mut date = date.next_day().unwrap();
+        while !self.is_trading_day(date) {
+            date = date.next_day().unwrap();
+        }
+        date
+    }
+
+    /// date之前的最后一个交易日
+    pub fn previous_trading_day(&self, date: Date) -> Date {
+        let mut date = date.previous_day().unwrap();
+        while !self.is_trading_day(date) {
+            date = date.previous_day().unwrap();
+        }
+        date
+    }
+
+    /// start至end(含)之间的交易日
+    pub fn trading_days(&self, start: Date, end: Date) -> Vec<Date> {
+        let mut days = Vec::new();
+        let mut date = start;
+        while date <= end {
+            if self.is_trading_day(date) {
+                days.push(date);
+            }
+            date = date.next_day().unwrap();
+        }
+        days
+    }
+}
+
+impl FromStr for TradingCalendar {
+    type Err = Error;
+
+    /// 每行一个日期或日期区间，#之后为注释
+    fn from_str(s: &str) -> Result<Self> {
+        let format = format_description!("[year][month][day]");
+        let parse = |x: &str| {
+            Date::parse(x.trim(), &format).map_err(|_| anyhow!("invalid date {:?}", x.trim()))
+        };
+        let mut calendar = TradingCalendar::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (start, end) = match line.split_once('-') {
+                Some((start, end)) => (parse(start), parse(end)),
+                None => (parse(line), parse(line)),
+            };
+            let (start, end) = match (start, end) {
+                (Ok(start), Ok(end)) if start <= end => (start, end),
+                (Ok(_), Ok(_)) => bail!("line {}: the range {} is reversed", i + 1, line),
+                (Err(e), _) | (_, Err(e)) => bail!("line {}: {}", i + 1, e),
+            };
+            calendar.years.extend(start.year()..=end.year());
+            let mut date = start;
+            while date <= end {
+                if !is_weekend(date) {
+                    calendar.holidays.insert(date);
+                }
+                date = date.next_day().unwrap();
+            }
+        }
+        Ok(calendar)
+    }
+}
+
+fn is_weekend(date: Date) -> bool {
+    matches!(date.weekday(), Weekday::Saturday | Weekday::Sunday)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_bundled_calendar() {
+        let calendar: TradingCalendar = BUNDLED.parse().unwrap();
+        assert!(!calendar.is_trading_day(date!(2021 - 1 - 1)));
+        assert!(!calendar.is_trading_day(date!(2021 - 1 - 2)));
+        assert!(calendar.is_trading_day(date!(2021 - 1 - 4)));
+        // 调休上班的周六仍然休市
+        assert!(!calendar.is_trading_day(date!(2021 - 9 - 18)));
+        assert_eq!(
+            calendar.next_trading_day(date!(2021 - 9 - 30)),
+            date!(2021 - 10 - 8)
+        );
+        assert_eq!(
+            calendar.previous_trading_day(date!(2021 - 10 - 8)),
+            date!(2021 - 9 - 30)
+        );
+        assert_eq!(
+            calendar
+                .trading_days(date!(2021 - 1 - 1), date!(2021 - 1 - 31))
+                .len(),
+            20
+        );
+        // 没有休市安排的年份只排除周末
+        assert!(calendar.is_trading_day(date!(2030 - 1 - 1)));
+    }
+
+    #[test]
+    fn test_update_calendar() {
+        let mut calendar: TradingCalendar = "20210101-20210103\n20220103 # 元旦\n".parse().unwrap();
+        assert_eq!(calendar.holidays.len(), 2);
+        calendar.update("\n20220104\n20300101\n".parse().unwrap());
+        assert!(!calendar.is_trading_day(date!(2021 - 1 - 1)));
+        assert!(calendar.is_trading_day(date!(2022 - 1 - 3)));
+        assert!(!calendar.is_trading_day(date!(2022 - 1 - 4)));
+        assert!(!calendar.is_trading_day(date!(2030 - 1 - 1)));
+
+        for invalid in ["2021010", "20210103-20210101", "20210101-"] {
+            assert!(invalid.parse::<TradingCalendar>().is_err(), "{}", invalid);
+        }
+    }
+}
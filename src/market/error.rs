@@ -0,0 +1,70 @@
+//! ## 行情查询错误
+//! ----
+//!
+//! 查询行情失败的具体原因，可通过anyhow::Error::downcast_ref取回
+
+use reqwest::StatusCode;
+use std::fmt;
+use time::Date;
+
+use super::MarketCode;
+
+/// 行情查询错误
+#[derive(Debug)]
+pub enum MarketError {
+    /// 请求地址不合法
+    InvalidUrl(String),
+    /// 网络请求失败
+    Network(reqwest::Error),
+    /// 服务器返回非2xx状态码
+    HttpStatus(StatusCode),
+    /// 返回内容不是合法的JSONP
+    MalformedResponse(String),
+    /// 指定日期范围内没有行情
+    EmptyRange {
+        code: MarketCode,
+        start_date: Date,
+        end_date: Date,
+    },
+    /// 标的代码不存在
+    UnknownCode(MarketCode),
+}
+
+impl fmt::Display for MarketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarketError::InvalidUrl(e) => write!(f, "invalid request url: {}", e),
+            MarketError::Network(e) => write!(f, "network error: {}", e),
+            MarketError::HttpStatus(status) => write!(f, "server responded with {}", status),
+            MarketError::MalformedResponse(e) => write!(f, "malformed response: {}", e),
+            MarketError::EmptyRange {
+                code,
+                start_date,
+                end_date,
+            } => write!(
+                f,
+                "no market data for {:0>6} between {} and {}",
+                code, start_date, end_date
+            ),
+            MarketError::UnknownCode(code) => write!(f, "unknown code {:0>6}", code),
+        }
+    }
+}
+
+impl std::error::Error for MarketError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MarketError::Network(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for MarketError {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => MarketError::HttpStatus(status),
+            None => MarketError::Network(e),
+        }
+    }
+}
@@ -5,60 +5,279 @@
 use anyhow::{anyhow, Result};
 // use itertools::Itertools;
 use reqwest::{Client, Url};
-use serde::{de, Deserialize, Deserializer};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 // use std::collections::HashMap;
-use async_trait::async_trait;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 use time::{format_description, macros::*, Date, PrimitiveDateTime};
+use tokio::runtime::Builder;
 
-use super::QuantitativeMarket;
+use super::cache::Cacheable;
+use super::error::MarketError;
+use super::provider::{DataFileFormat, EastMoneyProvider, FileProvider};
+use super::{QuantitativeMarket, QueryMarketInfo};
+use crate::decimal::{parse_decimal, Money, Price};
 
-/// fund trade status
+/// 基金的申购、赎回状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
 pub enum FundStatus {
-    BuyAvailable,
-    SellAvailable,
-    TransForbidden,
+    /// 开放申购
+    BuyOpen,
+    /// 限制大额申购，单笔申购上限，数据源没有给出上限时为None
+    BuyLimited(Option<Money>),
+    /// 暂停申购
+    BuySuspended,
+    /// 封闭期，不能申购也不能赎回
+    Closed,
+    /// 开放赎回
+    SellOpen,
+    /// 暂停赎回
+    SellSuspended,
+}
+
+impl FromStr for FundStatus {
+    type Err = anyhow::Error;
+
+    /// 东方财富接口中的状态文字，限制大额申购可以在括号中给出上限，如`限制大额申购(1000)`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, limit) = match s.find(['(', '（']) {
+            Some(begin) => {
+                let limit = s[begin..]
+                    .trim_start_matches(['(', '（'])
+                    .trim_end_matches([')', '）'])
+                    .trim_end_matches('元')
+                    .trim();
+                (s[..begin].trim(), Some(limit.parse::<Money>()?))
+            }
+            None => (s, None),
+        };
+        match (name, limit) {
+            ("开放申购", None) => Ok(FundStatus::BuyOpen),
+            ("限制大额申购" | "限大额", limit) => Ok(FundStatus::BuyLimited(limit)),
+            ("暂停申购", None) => Ok(FundStatus::BuySuspended),
+            ("封闭期", None) => Ok(FundStatus::Closed),
+            ("开放赎回", None) => Ok(FundStatus::SellOpen),
+            ("暂停赎回", None) => Ok(FundStatus::SellSuspended),
+            _ => Err(anyhow!("unknown fund status {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for FundStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FundStatus::BuyOpen => write!(f, "开放申购"),
+            FundStatus::BuyLimited(None) => write!(f, "限制大额申购"),
+            FundStatus::BuyLimited(Some(limit)) => write!(f, "限制大额申购({})", limit),
+            FundStatus::BuySuspended => write!(f, "暂停申购"),
+            FundStatus::Closed => write!(f, "封闭期"),
+            FundStatus::SellOpen => write!(f, "开放赎回"),
+            FundStatus::SellSuspended => write!(f, "暂停赎回"),
+        }
+    }
+}
+
+/// 基金的分红、拆分事件，发生在净值日期(除息日或折算日)
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Copy, Clone)]
+pub enum FundEvent {
+    /// 每份派发的现金
+    Dividend(Price),
+    /// 份额折算或拆分，每份变为若干份，乘以SPLIT_SCALE
+    Split(u64),
+}
+
+impl FundEvent {
+    /// 拆分比例的放大倍数，折算比例通常有8位小数
+    pub const SPLIT_SCALE: u64 = 100_000_000;
+
+    /// 每base份折算为ratio份
+    fn split(ratio: &str, base: u64) -> Result<FundEvent> {
+        match parse_decimal(ratio, 8)? {
+            raw if raw > 0 => {
+                let raw = u64::try_from(raw)
+                    .map_err(|_| anyhow!("split ratio out of range: {:?}", ratio))?;
+                Ok(FundEvent::Split(raw / base.max(1)))
+            }
+            _ => Err(anyhow!("invalid split ratio {:?}", ratio)),
+        }
+    }
+}
+
+/// 分红送配文字中表示拆分的关键字
+const SPLIT_WORDS: [&str; 3] = ["折算", "拆分", "分拆"];
+
+/// 按分红送配文字判断事件类型：拆分为Some(true)，派现为Some(false)，无法识别为None
+fn is_split_text(s: &str) -> Option<bool> {
+    if SPLIT_WORDS.iter().any(|x| s.contains(x)) {
+        Some(true)
+    } else if s.contains("现金") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// 关键字之后的第一个数字
+fn number_after<'a>(s: &'a str, word: &str) -> Option<&'a str> {
+    let rest = &s[s.find(word)? + word.len()..];
+    let begin = rest.find(|c: char| c.is_ascii_digit())?;
+    let rest = &rest[begin..];
+    let end = rest
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
+impl FromStr for FundEvent {
+    type Err = anyhow::Error;
+
+    /// 东方财富接口中的分红送配文字，如`每份派现金0.0300元`、`每10份派现金0.3元`、
+    /// `每份基金份额折算1.02345678份`。取关键字之后的数字，有`每N份`时除以N
+    fn from_str(s: &str) -> Result<Self> {
+        let unknown = || anyhow!("unknown fund event {:?}", s);
+        let split = is_split_text(s).ok_or_else(unknown)?;
+        let number = match split {
+            true => SPLIT_WORDS.iter().find_map(|x| number_after(s, x)),
+            false => number_after(s, "现金"),
+        }
+        .ok_or_else(unknown)?;
+        // 每N份，省略N时为每份
+        let base: u64 = match number_after(s, "每") {
+            Some(n) if s.contains(&format!("每{}份", n)) => n
+                .parse()
+                .ok()
+                .filter(|x| *x > 0)
+                .ok_or_else(|| anyhow!("invalid share base in {:?}", s))?,
+            _ => 1,
+        };
+        if split {
+            FundEvent::split(number, base)
+        } else {
+            Ok(FundEvent::Dividend(
+                number.parse::<Price>()?.mul_ratio(1, base),
+            ))
+        }
+    }
+}
+
+/// 由东方财富接口的FHFCZ(分红或折算值)、FHFCBZ(分红拆分标志，1为拆分)、DTYPE(类型)、
+/// FHSP(分红送配文字)得到分红拆分事件。数值取FHFCZ，文字能够识别时以文字判断事件类型，
+/// 没有FHFCZ时才从文字中读取数值
+fn fund_event(
+    value: Option<&str>,
+    flag: Option<&str>,
+    kind: Option<&str>,
+    text: Option<&str>,
+) -> Result<Option<FundEvent>> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(text.and_then(|x| x.parse().ok())),
+    };
+    let is_split = text.and_then(is_split_text).unwrap_or_else(|| {
+        flag == Some("1") || kind.is_some_and(|x| x.contains("拆分") || x.contains("折算"))
+    });
+    if is_split {
+        FundEvent::split(value, 1).map(Some)
+    } else {
+        let dividend = value
+            .parse()
+            .map_err(|_| anyhow!("invalid dividend: {:?}", value))?;
+        Ok(Some(FundEvent::Dividend(dividend)))
+    }
 }
 
 /// fund information
 #[allow(non_snake_case)]
-#[derive(Debug, Deserialize, PartialEq, PartialOrd, Copy, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, PartialOrd, Copy, Clone)]
+#[serde(try_from = "FundRecord")]
 pub struct FundData {
     #[serde(alias = "FSRQ")]
     #[serde(deserialize_with = "deserialize_with_date")]
+    #[serde(serialize_with = "serialize_with_date")]
     pub(crate) date: Date, // 净值日期
     #[serde(alias = "DWJZ")]
-    #[serde(deserialize_with = "deserialize_with_price")]
-    pub(crate) unit_nav: u32, // 单位净值
+    pub(crate) unit_nav: Price, // 单位净值
     #[serde(alias = "LJJZ")]
-    #[serde(deserialize_with = "deserialize_with_price")]
-    pub(crate) accumulate_nav: u32, //累计净值
-    #[serde(skip_deserializing)]
+    pub(crate) accumulate_nav: Price, //累计净值
+    #[serde(skip)]
     SDATE: Option<()>,
-    #[serde(skip_deserializing)]
+    #[serde(skip)]
     ACTUALSYI: (),
-    #[serde(skip_deserializing)]
+    #[serde(skip)]
     NAVTYPE: (),
-    #[serde(skip_deserializing)]
+    #[serde(skip)]
     JZZZL: (),
-    //Todo: deserialize it
-    #[serde(skip_deserializing)]
-    #[serde(alias = "SGZT")]
-    buy_status: (),
-    #[serde(skip_deserializing)]
-    #[serde(alias = "SHZT")]
-    sell_status: (),
-    #[serde(deserialize_with = "deserialize_with_dividend")]
-    #[serde(alias = "FHFCZ")]
-    pub(crate) dividend: Option<u32>, //分红
-    #[serde(skip_deserializing)]
-    FHFCBZ: (),
-    #[serde(skip_deserializing)]
-    DTYPE: (),
-    #[serde(skip_deserializing)]
-    FHSP: (),
+    #[serde(default, alias = "SGZT")]
+    #[serde(deserialize_with = "deserialize_with_status")]
+    #[serde(serialize_with = "serialize_with_status")]
+    pub(crate) buy_status: Option<FundStatus>, // 申购状态
+    #[serde(default, alias = "SHZT")]
+    #[serde(deserialize_with = "deserialize_with_status")]
+    #[serde(serialize_with = "serialize_with_status")]
+    pub(crate) sell_status: Option<FundStatus>, // 赎回状态
+    pub(crate) event: Option<FundEvent>, // 分红或拆分
+}
+
+/// 接口或缓存中的一条净值记录，分红拆分由多个字段共同决定
+#[derive(Deserialize)]
+struct FundRecord {
+    #[serde(alias = "FSRQ")]
+    #[serde(deserialize_with = "deserialize_with_date")]
+    date: Date,
+    #[serde(alias = "DWJZ")]
+    unit_nav: Price,
+    #[serde(alias = "LJJZ")]
+    accumulate_nav: Price,
+    #[serde(default, alias = "SGZT")]
+    #[serde(deserialize_with = "deserialize_with_status")]
+    buy_status: Option<FundStatus>,
+    #[serde(default, alias = "SHZT")]
+    #[serde(deserialize_with = "deserialize_with_status")]
+    sell_status: Option<FundStatus>,
+    #[serde(default)]
+    event: Option<FundEvent>,
+    #[serde(default, rename = "FHFCZ")]
+    #[serde(deserialize_with = "deserialize_with_text")]
+    value: Option<String>,
+    #[serde(default, rename = "FHFCBZ")]
+    #[serde(deserialize_with = "deserialize_with_text")]
+    flag: Option<String>,
+    #[serde(default, rename = "DTYPE")]
+    #[serde(deserialize_with = "deserialize_with_text")]
+    kind: Option<String>,
+    #[serde(default, rename = "FHSP")]
+    #[serde(deserialize_with = "deserialize_with_text")]
+    text: Option<String>,
+}
+
+impl TryFrom<FundRecord> for FundData {
+    type Error = anyhow::Error;
+
+    fn try_from(record: FundRecord) -> Result<Self> {
+        let event = match record.event {
+            Some(event) => Some(event),
+            None => fund_event(
+                record.value.as_deref(),
+                record.flag.as_deref(),
+                record.kind.as_deref(),
+                record.text.as_deref(),
+            )?,
+        };
+        let mut data = FundData::new(record.date, 0, 0, None);
+        data.unit_nav = record.unit_nav;
+        data.accumulate_nav = record.accumulate_nav;
+        data.buy_status = record.buy_status;
+        data.sell_status = record.sell_status;
+        data.event = event;
+        Ok(data)
+    }
 }
 
 impl FundData {
+    /// 净值和分红为放大10000倍的整数
     pub(crate) fn new(
         date: Date,
         unit_nav: u32,
@@ -67,22 +286,64 @@ impl FundData {
     ) -> Self {
         FundData {
             date,
-            unit_nav,
-            accumulate_nav,
+            unit_nav: Price::from_raw(unit_nav),
+            accumulate_nav: Price::from_raw(accumulate_nav),
             SDATE: None,
             ACTUALSYI: (),
             NAVTYPE: (),
             JZZZL: (),
-            buy_status: (),
-            sell_status: (),
-            dividend,
-            FHFCBZ: (),
-            DTYPE: (),
-            FHSP: (),
+            buy_status: None,
+            sell_status: None,
+            event: dividend.map(|x| FundEvent::Dividend(Price::from_raw(x))),
+        }
+    }
+
+    /// 除息日的每份分红
+    pub(crate) fn dividend(&self) -> Option<Price> {
+        match self.event {
+            Some(FundEvent::Dividend(dividend)) => Some(dividend),
+            _ => None,
+        }
+    }
+
+    /// 折算日每份变为的份数，乘以FundEvent::SPLIT_SCALE
+    pub(crate) fn split_ratio(&self) -> Option<u64> {
+        match self.event {
+            Some(FundEvent::Split(ratio)) => Some(ratio),
+            _ => None,
         }
     }
 }
 
+/// 复权净值：假设分红按除息日净值再投资、折算后的份额继续持有，以第一条记录的单位净值为起点，
+/// 返回按日期排序的(净值日期, 复权净值)。单位净值在除息日和折算日会跳水，复权净值则是连续的，
+/// 适合计算涨跌幅、回撤和波动率等指标；实际交易仍按单位净值成交。单位净值为0的记录被忽略
+pub fn adjusted_nav(records: &[FundData]) -> Vec<(Date, f64)> {
+    let mut records: Vec<&FundData> = records.iter().filter(|x| !x.unit_nav.is_zero()).collect();
+    records.sort_by_key(|x| x.date);
+    let mut factor = 1.0;
+    records
+        .iter()
+        .enumerate()
+        .map(|(i, data)| {
+            let nav = data.unit_nav.to_f64();
+            // 第一条记录之前的事件不影响复权净值
+            if i > 0 {
+                match data.event {
+                    Some(FundEvent::Dividend(dividend)) => {
+                        factor *= (nav + dividend.to_f64()) / nav;
+                    }
+                    Some(FundEvent::Split(ratio)) => {
+                        factor *= ratio as f64 / FundEvent::SPLIT_SCALE as f64;
+                    }
+                    None => {}
+                }
+            }
+            (data.date, nav * factor)
+        })
+        .collect()
+}
+
 unsafe impl Send for FundData {}
 
 fn deserialize_with_date<'de, D>(deserializer: D) -> Result<Date, D::Error>
@@ -94,29 +355,122 @@ where
     Date::parse(&s, &format).map_err(de::Error::custom)
 }
 
-fn deserialize_with_price<'de, D>(deserializer: D) -> Result<u32, D::Error>
+// 接口在没有分红时返回空字符串或null，本地文件中的值也可以写成数字
+fn deserialize_with_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: String = Deserialize::deserialize(deserializer)?;
-    let true_price = s.parse::<f32>();
-    match true_price {
-        Ok(val) => Ok((val * 10000.0) as u32),
-        Err(_) => Err(de::Error::custom(format!("can't parse f32{}", s))),
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TextRepr {
+        Text(String),
+        Number(serde_json::Number),
     }
+    Ok(match Option::<TextRepr>::deserialize(deserializer)? {
+        Some(TextRepr::Text(s)) => Some(s.trim().to_string()),
+        Some(TextRepr::Number(x)) => Some(x.to_string()),
+        None => None,
+    }
+    .filter(|x| !x.is_empty()))
 }
-fn deserialize_with_dividend<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+
+// 空字符串或不认识的状态(如场内买入)当作没有状态信息，不限制交易
+fn deserialize_with_status<'de, D>(deserializer: D) -> Result<Option<FundStatus>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let s: String = Deserialize::deserialize(deserializer)?;
-    if s.is_empty() {
-        return Ok(None);
+    Ok(Option::<String>::deserialize(deserializer)?.and_then(|s| s.parse().ok()))
+}
+
+fn serialize_with_status<S>(status: &Option<FundStatus>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match status {
+        Some(status) => serializer.serialize_str(&status.to_string()),
+        None => serializer.serialize_none(),
     }
-    let true_price = s.parse::<f32>();
-    match true_price {
-        Ok(val) => Ok(Some((val * 10000.0) as u32)),
-        Err(_) => Err(de::Error::custom(format!("can't parse f32{}", s))),
+}
+
+fn serialize_with_date<S>(date: &Date, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let format = format_description!("[year]-[month]-[day]");
+    let s = date.format(&format).map_err(serde::ser::Error::custom)?;
+    serializer.serialize_str(&s)
+}
+
+const FUND_HISTORY_URL: &str =
+    "http://api.fund.eastmoney.com/f10/lsjz?callback=jQuery18304038998523093684_1586160530315";
+
+// http://api.fund.eastmoney.com/f10/lsjz?callback=jQuery18308885693300284889_1643120391690&fundCode=002021&pageIndex=4&pageSize=20&startDate=&endDate=&_=1643120473795
+fn fund_history_url(code: u32, start_date: Date, end_date: Date) -> Result<Url, MarketError> {
+    let format = format_description!("[year]-[month]-[day]");
+    let format_date = |date: Date| {
+        date.format(&format)
+            .map_err(|e| MarketError::InvalidUrl(e.to_string()))
+    };
+    let params = [
+        ("fundCode", format!("{:0>6}", code)),
+        ("pageIndex", "1".to_string()),
+        ("pageSize", "65535".to_string()),
+        ("startDate", format_date(start_date)?),
+        ("endDate", format_date(end_date)?),
+    ];
+    Url::parse_with_params(FUND_HISTORY_URL, &params)
+        .map_err(|e| MarketError::InvalidUrl(e.to_string()))
+}
+
+/// 历史净值接口的返回内容
+#[derive(Debug, Deserialize)]
+struct FundHistoryResponse {
+    #[serde(rename = "Data")]
+    data: Option<FundHistoryData>,
+    #[serde(rename = "ErrCode", default)]
+    err_code: i64,
+    #[serde(rename = "ErrMsg", default)]
+    err_msg: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FundHistoryData {
+    #[serde(rename = "LSJZList", default)]
+    list: Option<Vec<FundData>>,
+    #[serde(rename = "FundType", default)]
+    fund_type: Option<String>,
+}
+
+// 解析JSONP格式的历史净值，按时间先后排序
+fn parse_fund_history(code: u32, content: &str) -> Result<Vec<FundData>, MarketError> {
+    let (begin, end) = match (content.find('('), content.rfind(')')) {
+        (Some(begin), Some(end)) if begin < end => (begin, end),
+        _ => {
+            return Err(MarketError::MalformedResponse(format!(
+                "not a jsonp response: {:.64}",
+                content
+            )))
+        }
+    };
+    let response: FundHistoryResponse = serde_json::from_str(&content[begin + 1..end])
+        .map_err(|e| MarketError::MalformedResponse(e.to_string()))?;
+    if response.err_code != 0 {
+        return Err(MarketError::MalformedResponse(format!(
+            "error code {}: {}",
+            response.err_code,
+            response.err_msg.unwrap_or_default()
+        )));
+    }
+    match response.data {
+        Some(FundHistoryData {
+            list: Some(list), ..
+        }) if !list.is_empty() => Ok(list.into_iter().rev().collect()),
+        // 没有净值且没有基金类型，说明基金代码不存在
+        Some(FundHistoryData {
+            fund_type: Some(fund_type),
+            ..
+        }) if !fund_type.is_empty() => Ok(vec![]),
+        _ => Err(MarketError::UnknownCode(code)),
     }
 }
 
@@ -127,99 +481,153 @@ pub(crate) fn get_fund_history(
     end_date: Date,
 ) -> Result<Vec<FundData>> {
     let client = reqwest::blocking::Client::new();
-    let format = format_description::parse("[year]-[month]-[day]")?;
-    let start_date_str = start_date.format(&format)?;
-    let end_date_str = end_date.format(&format)?;
-    let params = [
-        ("fundCode", format!("{:0>6}", code)),
-        ("pageIndex", "1".to_string()),
-        ("pageSize", "65535".to_string()),
-        ("startDate", start_date_str),
-        ("endDate", end_date_str),
-    ];
-    // http://api.fund.eastmoney.com/f10/lsjz?callback=jQuery18308885693300284889_1643120391690&fundCode=002021&pageIndex=4&pageSize=20&startDate=&endDate=&_=1643120473795
-    let url = Url::parse_with_params(
-        "http://api.fund.eastmoney.com/f10/lsjz?callback=jQuery18304038998523093684_1586160530315",
-        &params,
-    )?;
+    let url = fund_history_url(code, start_date, end_date)?;
     println!("{}", url);
     let res = client
         .get(url)
         .header("Referer", "http://fundf10.eastmoney.com/".to_string())
-        .send()?;
-    let content = res.text()?;
-    let begin = content.find('[').unwrap();
-    let end = content.find(']').unwrap();
-    let all_fund_data: Vec<FundData> = serde_json::from_str(&content[begin..=end])?;
-    let ret: Vec<FundData> = all_fund_data
+        .send()
+        .and_then(|res| res.error_for_status())
+        .map_err(MarketError::from)?;
+    let content = res.text().map_err(MarketError::from)?;
+    let ret: Vec<FundData> = parse_fund_history(code, &content)?
         .into_iter()
         .filter(|x| x.date >= start_date && x.date <= end_date)
-        // .rev()
         .collect();
     if ret.is_empty() {
-        // Err(anyhow::Error::new(error).context("empty"))
-        Err(anyhow!(
-            "can't fetch fund data between {} and {}",
+        Err(MarketError::EmptyRange {
+            code,
             start_date,
-            end_date
-        ))
+            end_date,
+        }
+        .into())
     } else {
         Ok(ret)
     }
 }
 
-#[async_trait]
+impl Cacheable for FundData {
+    const CACHE_NAME: &'static str = "fund";
+    // 1: 增加申购、赎回状态; 2: 分红改为分红拆分事件; 3: 修正分红送配文字的解析
+    const CACHE_VERSION: u32 = 3;
+}
+
 impl QuantitativeMarket for FundData {
     fn get_info_datetime(&self) -> PrimitiveDateTime {
         self.date.with_hms(19, 0, 0).unwrap()
     }
 
-    async fn query_history_info(
+    fn buy_limit(&self) -> Option<Money> {
+        match self.buy_status {
+            Some(FundStatus::BuySuspended | FundStatus::Closed) => Some(Money::ZERO),
+            Some(FundStatus::BuyLimited(limit)) => limit,
+            _ => None,
+        }
+    }
+
+    fn can_sell(&self) -> bool {
+        !matches!(
+            self.sell_status,
+            Some(FundStatus::SellSuspended | FundStatus::Closed)
+        )
+    }
+}
+
+// 从东方财富异步查询指定日期范围内的基金数据，按时间先后排序
+async fn query_fund_history(
+    code: u32,
+    start_date: Date,
+    end_date: Date,
+    cli: &Client,
+) -> Result<Vec<FundData>, MarketError> {
+    let url = fund_history_url(code, start_date, end_date)?;
+    #[cfg(test)]
+    println!("{}", url);
+    let content = cli
+        .get(url)
+        .header("Referer", "http://fundf10.eastmoney.com".to_string())
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_fund_history(code, &content)
+}
+
+impl QueryMarketInfo for EastMoneyProvider<FundData> {
+    type MarketInfo = FundData;
+
+    fn query_history_info(
+        &self,
         code: u32,
         start_date: Date,
         end_date: Date,
-        cli: Client,
-    ) -> Vec<FundData> {
-        let format = format_description::parse("[year]-[month]-[day]").unwrap();
-        let start_date_str = start_date
-            .format(&format)
-            .unwrap_or_else(|_| "2000-01-02".to_string());
-        let end_date_str = end_date
-            .format(&format)
-            .unwrap_or_else(|_| "2000-01-01".to_string());
-        let params = [
-            ("fundCode", format!("{:0>6}", code)),
-            ("pageIndex", "1".to_string()),
-            ("pageSize", "65535".to_string()),
-            ("startDate", start_date_str),
-            ("endDate", end_date_str),
-        ];
-        if let Ok(url) = Url::parse_with_params(
-        "http://api.fund.eastmoney.com/f10/lsjz?callback=jQuery18304038998523093684_1586160530315",
-        &params ) {
-            #[cfg(test)]
-            println!("{}", url);
-            if let Ok(res) = cli
-                .get(url)
-                .header(
-                    "Referer",
-                    "http://fundf10.eastmoney.com".to_string()
-                )
-                .send().await {
-                if let Ok(content) = res.text().await{
-                    let begin = content.find('[').unwrap();
-                    let end = content.find(']').unwrap();
-                    if let Ok(all_fund_data)= serde_json::from_str::<Vec<FundData>>(&content[begin..=end]) {
-                        return all_fund_data
-                            .into_iter()
-                            .rev()
-                            .collect::<Vec<FundData>>()
-                    }
-                }
+    ) -> Result<Vec<FundData>> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(runtime.block_on(query_fund_history(code, start_date, end_date, &self.client))?)
+    }
+}
+
+impl QueryMarketInfo for FileProvider<FundData> {
+    type MarketInfo = FundData;
+
+    /// 读取`<六位代码>.json`或`<六位代码>.csv`，字段与东方财富接口一致：
+    /// FSRQ(净值日期)、DWJZ(单位净值)、LJJZ(累计净值)、FHFCZ(分红，可为空)、
+    /// SGZT(申购状态，可选)、SHZT(赎回状态，可选)、FHFCBZ/DTYPE/FHSP(分红拆分标志、类型和文字，可选)
+    fn query_history_info(
+        &self,
+        code: u32,
+        start_date: Date,
+        end_date: Date,
+    ) -> Result<Vec<FundData>> {
+        let all_fund_data = match self.find_file(code)? {
+            (path, DataFileFormat::Json) => {
+                serde_json::from_str::<Vec<FundData>>(&fs::read_to_string(path)?)?
             }
-        }
-        vec![]
+            (path, DataFileFormat::Csv) => read_fund_csv(&path)?,
+        };
+        Ok(all_fund_data
+            .into_iter()
+            .filter(|x| x.date >= start_date && x.date <= end_date)
+            .collect())
+    }
+}
+
+// 按表头读取csv中的基金数据，列的顺序不限
+fn read_fund_csv(path: &Path) -> Result<Vec<FundData>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| anyhow!("column {} not found in {}", name, path.display()))
+    };
+    let (date_idx, unit_idx, acc_idx) = (column("FSRQ")?, column("DWJZ")?, column("LJJZ")?);
+    let (value_idx, flag_idx) = (column("FHFCZ").ok(), column("FHFCBZ").ok());
+    let (kind_idx, text_idx) = (column("DTYPE").ok(), column("FHSP").ok());
+    let (buy_idx, sell_idx) = (column("SGZT").ok(), column("SHZT").ok());
+    let format = format_description!("[year]-[month]-[day]");
+    let mut ret = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+        let date = Date::parse(field(date_idx), &format)?;
+        let mut data = FundData::new(date, 0, 0, None);
+        data.unit_nav = field(unit_idx).parse()?;
+        data.accumulate_nav = field(acc_idx).parse()?;
+        let optional = |idx: Option<usize>| idx.map(field).filter(|x| !x.is_empty());
+        data.event = fund_event(
+            optional(value_idx),
+            optional(flag_idx),
+            optional(kind_idx),
+            optional(text_idx),
+        )?;
+        data.buy_status = buy_idx.and_then(|idx| field(idx).parse().ok());
+        data.sell_status = sell_idx.and_then(|idx| field(idx).parse().ok());
+        ret.push(data);
     }
+    Ok(ret)
 }
 
 #[cfg(test)]
@@ -231,30 +639,143 @@ mod tests {
         let code = 002021;
         let start_date = date!(2021 - 9 - 1);
         let end_date = date!(2021 - 9 - 1);
-        let ret = get_fund_history(code, start_date, end_date);
+        let ret = get_fund_history(code, start_date, end_date).unwrap();
+        // 申购、赎回状态为查询时的状态
         let expect = vec![FundData {
-            date: date!(2021 - 9 - 1),
-            unit_nav: 12880,
-            accumulate_nav: 38280,
-            SDATE: None,
-            ACTUALSYI: (),
-            NAVTYPE: (),
-            JZZZL: (),
-            buy_status: (),
-            sell_status: (),
-            dividend: None,
-            FHFCBZ: (),
-            DTYPE: (),
-            FHSP: (),
+            buy_status: ret[0].buy_status,
+            sell_status: ret[0].sell_status,
+            ..FundData::new(date!(2021 - 9 - 1), 12880, 38280, None)
         }];
-        assert_eq!(expect, ret.unwrap())
+        assert_eq!(expect, ret)
     }
 
     #[test]
     fn test_deserialize_fund_data() {
         let input = "{\"FSRQ\":\"2021-09-15\",\"DWJZ\":\"1.4640\",\"LJJZ\":\"5.0330\",\"SDATE\":null,\"ACTUALSYI\":\"\",\"NAVTYPE\":\"1\",\"JZZZL\":\"-1.45\",\"SGZT\":\"限制大额申购\",\"SHZT\":\"开放赎回\",\"FHFCZ\":\"0.03\",\"FHFCBZ\":\"0\",\"DTYPE\":null,\"FHSP\":\"每份派现金0.0300元\"}";
-        let res = serde_json::from_str::<FundData>(input);
-        println!("{:#?}", res);
+        let res = serde_json::from_str::<FundData>(input).unwrap();
+        assert_eq!(res.buy_status, Some(FundStatus::BuyLimited(None)));
+        assert_eq!(res.sell_status, Some(FundStatus::SellOpen));
+        assert_eq!(res.buy_limit(), None);
+        assert!(res.can_sell());
+        assert_eq!(res.event, Some(FundEvent::Dividend(Price::from_raw(300))));
+        // 缓存中保存状态文字
+        let json = serde_json::to_string(&res).unwrap();
+        assert_eq!(serde_json::from_str::<FundData>(&json).unwrap(), res);
+    }
+
+    #[test]
+    fn test_fund_event() {
+        let split = "{\"FSRQ\":\"2021-09-15\",\"DWJZ\":\"1.0000\",\"LJJZ\":\"5.0330\",\"FHFCZ\":\"1.02345678\",\"FHFCBZ\":\"1\",\"DTYPE\":null,\"FHSP\":\"每份基金份额折算1.02345678份\"}";
+        let res = serde_json::from_str::<FundData>(split).unwrap();
+        assert_eq!(res.split_ratio(), Some(102345678));
+        assert_eq!(res.dividend(), None);
+        let json = serde_json::to_string(&res).unwrap();
+        assert_eq!(serde_json::from_str::<FundData>(&json).unwrap(), res);
+
+        assert_eq!(
+            "每份基金份额分拆2份".parse::<FundEvent>().unwrap(),
+            FundEvent::Split(2 * FundEvent::SPLIT_SCALE)
+        );
+        assert!("每份基金份额折算0份".parse::<FundEvent>().is_err());
+        // 取关键字之后的数字，每N份时除以N
+        assert_eq!(
+            "每10份派现金0.3元".parse::<FundEvent>().unwrap(),
+            FundEvent::Dividend(Price::from_raw(300))
+        );
+        assert_eq!(
+            "2021年第3次分红，每份派现金0.0300元"
+                .parse::<FundEvent>()
+                .unwrap(),
+            FundEvent::Dividend(Price::from_raw(300))
+        );
+        assert_eq!(
+            "每10份基金份额折算为12份".parse::<FundEvent>().unwrap(),
+            FundEvent::Split(120_000_000)
+        );
+        assert!("每份派发红利".parse::<FundEvent>().is_err());
+        // 数值以FHFCZ为准，文字只用于判断类型
+        assert_eq!(
+            fund_event(Some("0.03"), Some("0"), None, Some("每10份派现金0.3元")).unwrap(),
+            Some(FundEvent::Dividend(Price::from_raw(300)))
+        );
+        assert_eq!(
+            fund_event(
+                Some("1.2"),
+                Some("0"),
+                None,
+                Some("每10份基金份额折算为12份")
+            )
+            .unwrap(),
+            Some(FundEvent::Split(120_000_000))
+        );
+        assert_eq!(
+            fund_event(None, None, None, Some("每10份派现金0.3元")).unwrap(),
+            Some(FundEvent::Dividend(Price::from_raw(300)))
+        );
+        // 文字无法识别时按标志解析FHFCZ
+        assert_eq!(
+            fund_event(Some("1.5"), Some("1"), None, Some("其他")).unwrap(),
+            Some(FundEvent::Split(150_000_000))
+        );
+        assert_eq!(
+            fund_event(Some("0.03"), Some("0"), None, None).unwrap(),
+            Some(FundEvent::Dividend(Price::from_raw(300)))
+        );
+        assert_eq!(fund_event(None, Some("0"), None, None).unwrap(), None);
+        assert!(fund_event(Some("n/a"), Some("0"), None, None).is_err());
+    }
+
+    #[test]
+    fn test_adjusted_nav() {
+        let mut split = FundData::new(date!(2021 - 9 - 3), 10000, 32000, None);
+        split.event = Some(FundEvent::Split(2 * FundEvent::SPLIT_SCALE));
+        let records = [
+            split,
+            FundData::new(date!(2021 - 9 - 1), 20000, 30000, Some(1000)),
+            // 每份分红0.1元，单位净值由2.0下跌到1.95，复权净值上涨2.5%
+            FundData::new(date!(2021 - 9 - 2), 19500, 30500, Some(1000)),
+            FundData::new(date!(2021 - 9 - 6), 0, 0, None),
+        ];
+        let nav = adjusted_nav(&records);
+        let dates: Vec<Date> = nav.iter().map(|x| x.0).collect();
+        assert_eq!(
+            dates,
+            [
+                date!(2021 - 9 - 1),
+                date!(2021 - 9 - 2),
+                date!(2021 - 9 - 3)
+            ]
+        );
+        let expect = [2.0, 2.05, 1.0 * 2.0 * 2.05 / 1.95];
+        for ((_, value), expect) in nav.iter().zip(expect) {
+            assert!((value - expect).abs() < 1e-12, "{} != {}", value, expect);
+        }
+        assert!(adjusted_nav(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_fund_status() {
+        assert_eq!(
+            "限制大额申购（1000元）".parse::<FundStatus>().unwrap(),
+            FundStatus::BuyLimited(Some("1000".parse().unwrap()))
+        );
+        assert_eq!(
+            "限制大额申购(1000.5)"
+                .parse::<FundStatus>()
+                .unwrap()
+                .to_string(),
+            "限制大额申购(1000.50)"
+        );
+        assert!("场内买入".parse::<FundStatus>().is_err());
+        assert!("暂停申购(100)".parse::<FundStatus>().is_err());
+
+        let mut data = FundData::new(date!(2021 - 9 - 1), 10000, 10000, None);
+        data.buy_status = Some(FundStatus::BuySuspended);
+        data.sell_status = Some(FundStatus::SellSuspended);
+        assert_eq!(data.buy_limit(), Some(Money::ZERO));
+        assert!(!data.can_sell());
+        data.buy_status = Some(FundStatus::BuyLimited(Some("1000".parse().unwrap())));
+        assert_eq!(data.buy_limit(), Some("1000".parse().unwrap()));
     }
 
     #[test]
@@ -266,4 +787,94 @@ mod tests {
 
         assert!(ret.is_ok());
     }
+
+    #[test]
+    fn test_parse_fund_history() {
+        let content = "jQuery18304038998523093684_1586160530315({\"Data\":{\"LSJZList\":[\
+            {\"FSRQ\":\"2021-09-02\",\"DWJZ\":\"1.3000\",\"LJJZ\":\"3.8400\",\"FHFCZ\":\"\",\"FHSP\":\"\"},\
+            {\"FSRQ\":\"2021-09-01\",\"DWJZ\":\"1.2880\",\"LJJZ\":\"3.8280\",\"FHFCZ\":\"\",\"FHSP\":\"\"}],\
+            \"FundType\":\"002\"},\"ErrCode\":0,\"ErrMsg\":null,\"TotalCount\":2})";
+        let ret = parse_fund_history(2021, content).unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0].date, date!(2021 - 9 - 1));
+
+        let empty =
+            "cb({\"Data\":{\"LSJZList\":[],\"FundType\":\"002\"},\"ErrCode\":0,\"ErrMsg\":null})";
+        assert!(parse_fund_history(2021, empty).unwrap().is_empty());
+
+        let unknown =
+            "cb({\"Data\":{\"LSJZList\":[],\"FundType\":null},\"ErrCode\":0,\"ErrMsg\":null})";
+        assert!(matches!(
+            parse_fund_history(999999, unknown),
+            Err(MarketError::UnknownCode(999999))
+        ));
+
+        let failed = "cb({\"Data\":null,\"ErrCode\":-999,\"ErrMsg\":\"busy\"})";
+        assert!(matches!(
+            parse_fund_history(2021, failed),
+            Err(MarketError::MalformedResponse(_))
+        ));
+        assert!(matches!(
+            parse_fund_history(2021, "<html>404</html>"),
+            Err(MarketError::MalformedResponse(_))
+        ));
+    }
+
+    fn temp_data_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("trade_helper_data_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_provider_json() {
+        let dir = temp_data_dir("json");
+        fs::write(
+            dir.join("002021.json"),
+            "[{\"FSRQ\":\"2021-09-02\",\"DWJZ\":\"1.3000\",\"LJJZ\":\"3.8400\",\"FHFCZ\":\"0.03\"},\
+              {\"FSRQ\":\"2021-09-01\",\"DWJZ\":\"1.2880\",\"LJJZ\":\"3.8280\"}]",
+        )
+        .unwrap();
+        let provider = FileProvider::<FundData>::new(&dir);
+        let ret = provider
+            .query_history_info(2021, date!(2021 - 9 - 1), date!(2021 - 9 - 1))
+            .unwrap();
+        assert_eq!(
+            ret,
+            vec![FundData::new(date!(2021 - 9 - 1), 12880, 38280, None)]
+        );
+        let ret = provider
+            .query_history_info(2021, date!(2021 - 9 - 1), date!(2021 - 9 - 2))
+            .unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0].dividend(), Some(Price::from_raw(300)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_file_provider_csv() {
+        let dir = temp_data_dir("csv");
+        fs::write(
+            dir.join("070032.csv"),
+            "LJJZ,FSRQ,DWJZ,FHFCZ\n3.8280,2021-09-01,1.2880,\n3.8400,2021-09-02,1.3000,0.03\n",
+        )
+        .unwrap();
+        let provider = FileProvider::<FundData>::new(&dir);
+        let ret = provider
+            .query_history_info(70032, date!(2021 - 9 - 1), date!(2021 - 9 - 30))
+            .unwrap();
+        assert_eq!(
+            ret,
+            vec![
+                FundData::new(date!(2021 - 9 - 1), 12880, 38280, None),
+                FundData::new(date!(2021 - 9 - 2), 13000, 38400, Some(300)),
+            ]
+        );
+        assert!(provider
+            .query_history_info(2021, date!(2021 - 9 - 1), date!(2021 - 9 - 30))
+            .is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
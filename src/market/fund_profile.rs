@@ -0,0 +1,316 @@
+//! ## 基金资料
+//! ----
+//!
+//! 基金名称、类型、基金公司、基金经理、规模、成立日期和公布的费率，用于标注报告、
+//! 检查回测区间是否早于成立日以及预填费率。数据源(QueryFundProfile)目前提供：
+//! + EastMoneyProvider: 从东方财富接口查询，不含赎回费分档
+//! + FileProvider: 读取目录下的`profile_<六位代码>.json`，格式与本地缓存相同，可以手工编写或从缓存目录复制
+//! + CachedProvider: 在缓存目录下保存查询结果，超过30天重新查询，查询失败时沿用旧的资料
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use time::macros::format_description;
+use time::Date;
+use tokio::runtime::Builder;
+
+use super::error::MarketError;
+use super::provider::{EastMoneyProvider, FileProvider};
+use super::MarketCode;
+use crate::decimal::Money;
+
+/// 基金类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FundCategory {
+    Equity,
+    Hybrid,
+    Bond,
+    Qdii,
+    Money,
+    Index,
+    #[default]
+    Other,
+}
+
+impl FromStr for FundCategory {
+    type Err = anyhow::Error;
+
+    /// 英文类型名，或东方财富的类型文字，如`混合型-偏股`、`指数型-股票`、`QDII-指数`。
+    /// 先按`-`之前的主类型归类，`混合型-偏债`是混合型而不是债券型，无法识别时再按包含的文字归类
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let primary = s.split('-').next().unwrap_or_default().trim();
+        let category = match s.to_ascii_lowercase().as_str() {
+            "equity" => FundCategory::Equity,
+            "hybrid" => FundCategory::Hybrid,
+            "bond" => FundCategory::Bond,
+            "qdii" => FundCategory::Qdii,
+            "money" => FundCategory::Money,
+            "index" => FundCategory::Index,
+            "other" => FundCategory::Other,
+            _ if primary.eq_ignore_ascii_case("qdii") => FundCategory::Qdii,
+            _ if primary.starts_with("混合") => FundCategory::Hybrid,
+            _ if primary.starts_with("债券") => FundCategory::Bond,
+            _ if primary.starts_with("指数") => FundCategory::Index,
+            _ if primary.starts_with("股票") => FundCategory::Equity,
+            _ if primary.starts_with("货币") => FundCategory::Money,
+            x if x.contains("qdii") => FundCategory::Qdii,
+            _ if s.contains("指数") => FundCategory::Index,
+            _ if s.contains("货币") => FundCategory::Money,
+            _ if s.contains("混合") => FundCategory::Hybrid,
+            _ if s.contains("债") => FundCategory::Bond,
+            _ if s.contains("股票") => FundCategory::Equity,
+            _ if s.is_empty() => return Err(anyhow!("empty fund category")),
+            _ => FundCategory::Other,
+        };
+        Ok(category)
+    }
+}
+
+impl fmt::Display for FundCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FundCategory::Equity => "equity",
+            FundCategory::Hybrid => "hybrid",
+            FundCategory::Bond => "bond",
+            FundCategory::Qdii => "qdii",
+            FundCategory::Money => "money",
+            FundCategory::Index => "index",
+            FundCategory::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 基金资料，费率均为百分比
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundProfile {
+    pub(crate) code: u32,
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) category: FundCategory,
+    #[serde(default)]
+    pub(crate) company: Option<String>, // 基金公司
+    #[serde(default)]
+    pub(crate) manager: Option<String>, // 基金经理
+    #[serde(default)]
+    pub(crate) size: Option<Money>, // 基金规模(元)
+    #[serde(default)]
+    pub(crate) inception: Option<Date>, // 成立日期
+    #[serde(default)]
+    pub(crate) subscription_rate: Option<f32>, // 公布的申购费率(原费率)
+    #[serde(default)]
+    pub(crate) management_rate: Option<f32>, // 管理费年费率
+    #[serde(default)]
+    pub(crate) redemption_fee: Option<String>, // 赎回费分档，格式同--redemption-fee
+}
+
+impl fmt::Display for FundProfile {
+    /// 一行摘要，如`000001 华夏成长混合: hybrid, 华夏基金, manager 王泽实, size 30.12亿, since 2001-12-18`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:0>6} {}: {}", self.code, self.name, self.category)?;
+        if let Some(company) = &self.company {
+            write!(f, ", {}", company)?;
+        }
+        if let Some(manager) = &self.manager {
+            write!(f, ", manager {}", manager)?;
+        }
+        if let Some(size) = self.size {
+            write!(f, ", size {:.2}亿", size.to_f64() / 1e8)?;
+        }
+        if let Some(inception) = self.inception {
+            write!(f, ", since {}", inception)?;
+        }
+        Ok(())
+    }
+}
+
+/// 基金资料数据源，没有该基金的资料时返回None，基金代码不存在时返回MarketError::UnknownCode
+pub trait QueryFundProfile: Sync {
+    fn query_profile(&self, code: MarketCode) -> Result<Option<FundProfile>>;
+}
+
+/// 运行时才确定的数据源，如命令行中可选的本地目录或网络
+impl QueryFundProfile for Box<dyn QueryFundProfile> {
+    fn query_profile(&self, code: MarketCode) -> Result<Option<FundProfile>> {
+        self.as_ref().query_profile(code)
+    }
+}
+
+/// 资料文件名，本地目录和缓存目录相同
+pub(crate) fn profile_file_name(code: MarketCode) -> String {
+    format!("profile_{:0>6}.json", code)
+}
+
+impl QueryFundProfile for FileProvider<FundProfile> {
+    fn query_profile(&self, code: MarketCode) -> Result<Option<FundProfile>> {
+        let path = self.dir.join(profile_file_name(code));
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let profile: FundProfile = serde_json::from_str(&fs::read_to_string(&path)?)
+            .map_err(|e| anyhow!("invalid profile {}: {}", path.display(), e))?;
+        Ok(Some(profile))
+    }
+}
+
+const FUND_PROFILE_URL: &str =
+    "https://fundmobapi.eastmoney.com/FundMApi/FundBaseTypeInformation.ashx?deviceid=Wap&plat=Wap&product=EFund&version=2.0.0";
+
+/// 基金资料接口的返回内容
+#[derive(Debug, Deserialize)]
+struct FundProfileResponse {
+    #[serde(rename = "Datas")]
+    data: Option<FundProfileData>,
+    #[serde(rename = "ErrCode", default)]
+    err_code: i64,
+    #[serde(rename = "ErrMsg", default)]
+    err_msg: Option<String>,
+}
+
+// 接口中的数值有时为字符串，有时为数字
+#[derive(Debug, Deserialize)]
+struct FundProfileData {
+    #[serde(rename = "SHORTNAME", default)]
+    name: Option<Value>,
+    #[serde(rename = "FTYPE", default)]
+    category: Option<Value>,
+    #[serde(rename = "JJGS", default)]
+    company: Option<Value>,
+    #[serde(rename = "JJJL", default)]
+    manager: Option<Value>,
+    #[serde(rename = "ENDNAV", default)]
+    size: Option<Value>,
+    #[serde(rename = "ESTABDATE", default)]
+    inception: Option<Value>,
+    #[serde(rename = "SOURCERATE", default)]
+    subscription_rate: Option<Value>,
+    #[serde(rename = "MGREXP", default)]
+    management_rate: Option<Value>,
+}
+
+/// 去掉空白，数字转为文字，空字符串和`--`当作没有
+fn text(value: Option<Value>) -> Option<String> {
+    match value? {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(x) => Some(x.to_string()),
+        _ => None,
+    }
+    .filter(|x| !x.is_empty() && x != "--")
+}
+
+// 解析基金资料，无法识别的字段当作没有
+fn parse_fund_profile(code: MarketCode, content: &str) -> Result<FundProfile, MarketError> {
+    let response: FundProfileResponse =
+        serde_json::from_str(content).map_err(|e| MarketError::MalformedResponse(e.to_string()))?;
+    if response.err_code != 0 {
+        return Err(MarketError::MalformedResponse(format!(
+            "error code {}: {}",
+            response.err_code,
+            response.err_msg.unwrap_or_default()
+        )));
+    }
+    let data = match response.data {
+        Some(data) => data,
+        None => return Err(MarketError::UnknownCode(code)),
+    };
+    let name = match text(data.name) {
+        Some(name) => name,
+        None => return Err(MarketError::UnknownCode(code)),
+    };
+    let rate = |x: Option<Value>| text(x).and_then(|x| x.trim_end_matches('%').parse().ok());
+    Ok(FundProfile {
+        code,
+        name,
+        category: text(data.category)
+            .and_then(|x| x.parse().ok())
+            .unwrap_or_default(),
+        company: text(data.company),
+        manager: text(data.manager),
+        size: text(data.size).and_then(|x| x.parse().ok()),
+        inception: text(data.inception)
+            .and_then(|x| Date::parse(&x, format_description!("[year]-[month]-[day]")).ok()),
+        subscription_rate: rate(data.subscription_rate),
+        management_rate: rate(data.management_rate),
+        redemption_fee: None,
+    })
+}
+
+async fn query_fund_profile(code: MarketCode, cli: &Client) -> Result<FundProfile, MarketError> {
+    let url = Url::parse_with_params(FUND_PROFILE_URL, &[("FCODE", format!("{:0>6}", code))])
+        .map_err(|e| MarketError::InvalidUrl(e.to_string()))?;
+    let content = cli
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_fund_profile(code, &content)
+}
+
+impl QueryFundProfile for EastMoneyProvider<FundProfile> {
+    fn query_profile(&self, code: MarketCode) -> Result<Option<FundProfile>> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(Some(
+            runtime.block_on(query_fund_profile(code, &self.client))?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_parse_fund_profile() {
+        let content = r#"{"Datas":{"FCODE":"000001","SHORTNAME":"华夏成长混合","FTYPE":"混合型-偏股","JJGS":"华夏基金","JJJL":"王泽实","ENDNAV":"3012345678.90","ESTABDATE":"2001-12-18","SOURCERATE":"1.50","RATE":"0.15","MGREXP":1.2},"ErrCode":0,"ErrMsg":null,"Success":true}"#;
+        let profile = parse_fund_profile(1, content).unwrap();
+        assert_eq!(profile.name, "华夏成长混合");
+        assert_eq!(profile.category, FundCategory::Hybrid);
+        assert_eq!(profile.size, Some("3012345678.9".parse().unwrap()));
+        assert_eq!(profile.inception, Some(date!(2001 - 12 - 18)));
+        assert_eq!(profile.subscription_rate, Some(1.5));
+        assert_eq!(profile.management_rate, Some(1.2));
+        assert_eq!(
+            profile.to_string(),
+            "000001 华夏成长混合: hybrid, 华夏基金, manager 王泽实, size 30.12亿, since 2001-12-18"
+        );
+        // 缓存和本地文件使用同样的格式
+        let json = serde_json::to_string(&profile).unwrap();
+        assert_eq!(serde_json::from_str::<FundProfile>(&json).unwrap(), profile);
+
+        let unknown = r#"{"Datas":null,"ErrCode":0,"ErrMsg":null,"Success":true}"#;
+        assert!(matches!(
+            parse_fund_profile(999999, unknown),
+            Err(MarketError::UnknownCode(999999))
+        ));
+        assert!(matches!(
+            parse_fund_profile(1, "<html>404</html>"),
+            Err(MarketError::MalformedResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_fund_category() {
+        let parse = |x: &str| x.parse::<FundCategory>().unwrap();
+        assert_eq!(parse("QDII-指数"), FundCategory::Qdii);
+        assert_eq!(parse("指数型-股票"), FundCategory::Index);
+        assert_eq!(parse("债券型-混合二级"), FundCategory::Bond);
+        assert_eq!(parse("混合型-偏债"), FundCategory::Hybrid);
+        assert_eq!(parse("混合型-偏股"), FundCategory::Hybrid);
+        assert_eq!(parse("指数型-固收"), FundCategory::Index);
+        assert_eq!(parse("偏债混合"), FundCategory::Hybrid);
+        assert_eq!(parse("货币型-普通货币"), FundCategory::Money);
+        assert_eq!(parse("股票型"), FundCategory::Equity);
+        assert_eq!(parse("FOF-稳健型"), FundCategory::Other);
+        assert_eq!(parse("Bond"), FundCategory::Bond);
+        assert!("".parse::<FundCategory>().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! ## 基金列表
+//! ----
+//!
+//! 全部基金的代码、名称、拼音缩写和类型，用于按名称片段、拼音首字母或代码查找基金，
+//! 以及按类型筛选。数据源(QueryFundUniverse)目前提供：
+//! + EastMoneyProvider: 从东方财富下载完整列表
+//! + FileProvider: 读取目录下的`funds.csv`，列为code、name、abbr(拼音缩写)、type(类型)，可选pinyin(全拼)
+//! + CachedProvider: 在缓存目录下保存列表(universe.json)，超过7天重新下载，下载失败时沿用旧的列表
+
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Builder;
+
+use super::error::MarketError;
+use super::fund_profile::FundCategory;
+use super::provider::{EastMoneyProvider, FileProvider};
+
+/// 基金列表中的一项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FundListing {
+    pub(crate) code: u32,
+    pub(crate) name: String,
+    pub(crate) abbr: String, // 拼音首字母缩写，如HXCZHH
+    #[serde(rename = "type")]
+    pub(crate) fund_type: String, // 数据源中的类型文字，如混合型-偏股
+    #[serde(default)]
+    pub(crate) pinyin: Option<String>, // 全拼
+}
+
+impl FundListing {
+    /// 按类型文字归类，无法识别时为Other
+    pub fn category(&self) -> FundCategory {
+        self.fund_type.parse().unwrap_or_default()
+    }
+}
+
+/// 基金列表数据源
+pub trait QueryFundUniverse: Sync {
+    fn query_universe(&self) -> Result<Vec<FundListing>>;
+}
+
+/// 运行时才确定的数据源，如命令行中可选的本地目录或网络
+impl QueryFundUniverse for Box<dyn QueryFundUniverse> {
+    fn query_universe(&self) -> Result<Vec<FundListing>> {
+        self.as_ref().query_universe()
+    }
+}
+
+/// 全部基金，按代码排序
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FundUniverse {
+    funds: Vec<FundListing>,
+}
+
+impl FundUniverse {
+    pub fn new(mut funds: Vec<FundListing>) -> Self {
+        funds.sort_by_key(|x| x.code);
+        funds.dedup_by_key(|x| x.code);
+        FundUniverse { funds }
+    }
+
+    /// 从数据源读取全部基金
+    pub fn load<P: QueryFundUniverse + ?Sized>(provider: &P) -> Result<Self> {
+        Ok(Self::new(provider.query_universe()?))
+    }
+
+    pub fn funds(&self) -> &[FundListing] {
+        &self.funds
+    }
+
+    pub fn len(&self) -> usize {
+        self.funds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.funds.is_empty()
+    }
+
+    /// 按代码查找
+    pub fn get(&self, code: u32) -> Option<&FundListing> {
+        self.funds
+            .binary_search_by_key(&code, |x| x.code)
+            .ok()
+            .map(|i| &self.funds[i])
+    }
+
+    /// 按代码、名称片段、拼音缩写或全拼查找，不区分大小写。
+    /// 结果按匹配程度排序：代码或缩写完全相同、代码或缩写以查询开头、其他包含查询的，同等程度按代码排序
+    pub fn search(&self, query: &str) -> Vec<&FundListing> {
+        let query = query.trim().to_uppercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut found: Vec<(u8, &FundListing)> = self
+            .funds
+            .iter()
+            .filter_map(|fund| {
+                let code = format!("{:0>6}", fund.code);
+                let abbr = fund.abbr.to_uppercase();
+                let pinyin = fund.pinyin.as_deref().unwrap_or_default().to_uppercase();
+                if code == query || abbr == query {
+                    Some((0, fund))
+                } else if code.starts_with(&query) || abbr.starts_with(&query) {
+                    Some((1, fund))
+                } else if code.contains(&query)
+                    || abbr.contains(&query)
+                    || pinyin.contains(&query)
+                    || fund.name.to_uppercase().contains(&query)
+                {
+                    Some((2, fund))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        found.sort_by_key(|(rank, fund)| (*rank, fund.code));
+        found.into_iter().map(|(_, fund)| fund).collect()
+    }
+
+    /// 指定类型的全部基金
+    pub fn filter(&self, category: FundCategory) -> impl Iterator<Item = &FundListing> {
+        self.funds.iter().filter(move |x| x.category() == category)
+    }
+}
+
+/// 读取`funds.csv`
+impl QueryFundUniverse for FileProvider<FundListing> {
+    fn query_universe(&self) -> Result<Vec<FundListing>> {
+        let path = self.dir.join("funds.csv");
+        let mut reader = csv::Reader::from_path(&path)
+            .map_err(|e| anyhow!("failed to read {}: {}", path.display(), e))?;
+        reader
+            .deserialize()
+            .map(|x| x.map_err(|e| anyhow!("invalid fund list {}: {}", path.display(), e)))
+            .collect()
+    }
+}
+
+const FUND_LIST_URL: &str = "http://fund.eastmoney.com/js/fundcode_search.js";
+
+// 解析`var r = [["000001","HXCZHH","华夏成长混合","混合型-灵活","HUAXIACHENGZHANGHUNHE"],...];`
+fn parse_fund_list(content: &str) -> Result<Vec<FundListing>, MarketError> {
+    let (begin, end) = match (content.find('['), content.rfind(']')) {
+        (Some(begin), Some(end)) if begin < end => (begin, end),
+        _ => {
+            return Err(MarketError::MalformedResponse(format!(
+                "not a fund list: {:.64}",
+                content
+            )))
+        }
+    };
+    let rows: Vec<Vec<String>> = serde_json::from_str(&content[begin..=end])
+        .map_err(|e| MarketError::MalformedResponse(e.to_string()))?;
+    rows.into_iter()
+        .map(|row| match &row[..] {
+            [code, abbr, name, fund_type, rest @ ..] => Ok(FundListing {
+                code: code.parse().map_err(|_| {
+                    MarketError::MalformedResponse(format!("invalid fund code {:?}", code))
+                })?,
+                name: name.clone(),
+                abbr: abbr.clone(),
+                fund_type: fund_type.clone(),
+                pinyin: rest.first().filter(|x| !x.is_empty()).cloned(),
+            }),
+            _ => Err(MarketError::MalformedResponse(format!(
+                "invalid fund list row {:?}",
+                row
+            ))),
+        })
+        .collect()
+}
+
+async fn query_fund_list(cli: &Client) -> Result<Vec<FundListing>, MarketError> {
+    let content = cli
+        .get(FUND_LIST_URL)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_fund_list(&content)
+}
+
+impl QueryFundUniverse for EastMoneyProvider<FundListing> {
+    fn query_universe(&self) -> Result<Vec<FundListing>> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(runtime.block_on(query_fund_list(&self.client))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn universe() -> FundUniverse {
+        let content = r#"var r = [["110022","YFDXFHYGP","易方达消费行业股票","股票型","YIFANGDAXIAOFEIHANGYEGUPIAO"],["000001","HXCZHH","华夏成长混合","混合型-灵活","HUAXIACHENGZHANGHUNHE"],["000300","HXHS300ETFLJA","华夏沪深300ETF联接A","指数型-股票","HUAXIAHUSHEN300ETFLIANJIEA"],["001001","HXZZ","华夏债券","债券型-混合二级",""]];"#;
+        FundUniverse::new(parse_fund_list(content).unwrap())
+    }
+
+    #[test]
+    fn test_search() {
+        let universe = universe();
+        assert_eq!(universe.len(), 4);
+        assert_eq!(universe.get(1).unwrap().name, "华夏成长混合");
+        assert_eq!(universe.get(1001).unwrap().pinyin, None);
+        let codes =
+            |query: &str| -> Vec<u32> { universe.search(query).iter().map(|x| x.code).collect() };
+        assert_eq!(codes("hxcz"), [1]);
+        assert_eq!(codes("00"), [1, 300, 1001, 110022]);
+        assert_eq!(codes("000300"), [300]);
+        assert_eq!(codes("华夏"), [1, 300, 1001]);
+        assert_eq!(codes("xiaofei"), [110022]);
+        assert_eq!(codes("300"), [300]);
+        assert!(codes(" ").is_empty());
+
+        let bonds: Vec<_> = universe
+            .filter(FundCategory::Bond)
+            .map(|x| x.code)
+            .collect();
+        assert_eq!(bonds, [1001]);
+        assert!(matches!(
+            parse_fund_list("<html>404</html>"),
+            Err(MarketError::MalformedResponse(_))
+        ));
+    }
+
+    #[test]
+    fn test_file_provider() {
+        let dir =
+            std::env::temp_dir().join(format!("trade_helper_universe_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let provider = FileProvider::<FundListing>::new(&dir);
+        assert!(provider.query_universe().is_err());
+        fs::write(
+            dir.join("funds.csv"),
+            "code,name,abbr,type\n1,华夏成长混合,HXCZHH,hybrid\n2021,华夏回报二号混合,HXHBEHHH,混合型-偏股\n",
+        )
+        .unwrap();
+        let universe = FundUniverse::load(&provider).unwrap();
+        let hybrid: Vec<_> = universe
+            .filter(FundCategory::Hybrid)
+            .map(|x| x.code)
+            .collect();
+        assert_eq!(hybrid, [1, 2021]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -10,95 +10,182 @@
 //! ----
 //! 定义市场行情数据的获取方法
 //! + get_info_datetime: 获取当前市场行情的时间信息
+//! + buy_limit/can_sell: 行情附带的交易限制(如基金的申购、赎回状态)，账户买卖时遵守
+//! + on_trading_day: 行情是否属于交易日(见calendar模块)，节假日公布的估值只能用于计算市值，不能据此交易
+//!
+//! ### Trait QueryMarketInfo
+//! ----
+//! 行情数据源(见provider模块)
 //! + query_history_info: 获取指定时间范围内某具体投资标的(由code指定)的行情信息
 //!
 //! ### Struct InfoMixer
 //! ----
-//! 用于同时关注多个标的的行情信息，实现Iterator接口，将按时间先后顺序返回行情信息
+//! 用于同时关注多个标的的行情信息，实现Iterator接口，按时间先后顺序归并各标的的行情，
+//! 每次返回同一时刻所有标的的行情(截面)，截面内按code中的顺序排列
 //! + code: 关注标的的代码
-//! + info：各个关注标的的行情信息，每个具体标的的行情信息是一个Vec<T: QuantitativeMarket>
+//! + info：各个关注标的尚未返回的行情信息，每个具体标的的行情信息是一个VecDeque<T: QuantitativeMarket>
+//!
+//! 可由任意数据源构造，默认使用带本地缓存(见cache模块)的东方财富数据源
 
+use crate::decimal::Money;
 use anyhow::Result;
-use async_trait::async_trait;
-use reqwest::Client;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
 use std::fmt::Debug;
 use std::iter::Iterator;
-use std::sync::Arc;
 use time::{macros::*, Date, PrimitiveDateTime};
-use tokio::runtime::Builder;
-use tokio::sync::Mutex;
 
+pub mod cache;
+pub mod calendar;
+pub mod error;
 pub mod fund_market;
+pub mod fund_profile;
+pub mod fund_universe;
+pub mod provider;
+pub mod stock_market;
+
+use cache::{Cacheable, CachedProvider, MarketCache};
+use calendar::TradingCalendar;
+use error::MarketError;
+use provider::EastMoneyProvider;
 
 /// 市场行情
-#[async_trait]
 pub trait QuantitativeMarket: Send + Copy + 'static {
     /// 行情的日期时间
     fn get_info_datetime(&self) -> PrimitiveDateTime;
 
-    async fn query_history_info(
-        code: u32,
-        start_date: Date,
-        end_date: Date,
-        cli: Client,
-    ) -> Vec<Self>
-    where
-        Self: std::marker::Sized;
+    /// 当日单笔买入金额的上限，不能买入时为零，没有限制时为None
+    fn buy_limit(&self) -> Option<Money> {
+        None
+    }
+
+    /// 当日能否卖出
+    fn can_sell(&self) -> bool {
+        true
+    }
+
+    /// 行情的日期是否为沪深交易所的交易日
+    fn on_trading_day(&self) -> bool {
+        TradingCalendar::china().is_trading_day(self.get_info_datetime().date())
+    }
 }
 
 type MarketCode = u32;
 
-pub trait QueryMarketInfo {
+/// 行情数据源，获取指定时间范围内某具体投资标的(由code指定)的行情信息
+pub trait QueryMarketInfo: Sync {
     type MarketInfo: QuantitativeMarket;
 
-    fn query_history_info(&self, start_date: Date, end_date: Date)
-        -> Result<Vec<Self::MarketInfo>>;
+    fn query_history_info(
+        &self,
+        code: MarketCode,
+        start_date: Date,
+        end_date: Date,
+    ) -> Result<Vec<Self::MarketInfo>>;
+}
+
+/// 默认数据源：带本地缓存的东方财富数据源
+pub(crate) fn default_provider<T>() -> CachedProvider<EastMoneyProvider<T>> {
+    CachedProvider::new(EastMoneyProvider::default(), MarketCache::default())
+}
+
+/// 运行时才确定的数据源，如命令行中可选的本地目录或网络
+impl<T: QuantitativeMarket> QueryMarketInfo for Box<dyn QueryMarketInfo<MarketInfo = T>> {
+    type MarketInfo = T;
+
+    fn query_history_info(
+        &self,
+        code: MarketCode,
+        start_date: Date,
+        end_date: Date,
+    ) -> Result<Vec<T>> {
+        self.as_ref().query_history_info(code, start_date, end_date)
+    }
 }
 
 #[derive(Debug)]
 pub struct InfoMixer<T: QuantitativeMarket> {
     pub(crate) code: Vec<u32>,
-    pub(crate) info: Vec<Vec<T>>,
+    pub(crate) info: Vec<VecDeque<T>>,
+    heads: BinaryHeap<Reverse<(PrimitiveDateTime, usize)>>, // 各标的下一条行情的时间及下标
+}
+
+impl<T> InfoMixer<T>
+where
+    T: Cacheable + Debug,
+    EastMoneyProvider<T>: QueryMarketInfo<MarketInfo = T>,
+{
+    /// 从带本地缓存的东方财富数据源构造
+    pub(crate) fn new(codes: &[u32], start_date: Date, end_date: Date) -> Result<Self> {
+        Self::from_provider(&default_provider::<T>(), codes, start_date, end_date)
+    }
 }
 
 impl<T> InfoMixer<T>
 where
     T: QuantitativeMarket + Debug,
 {
-    pub(crate) fn new(codes: &[u32], start_date: Date, end_date: Date) -> Self {
-        let runtime = Builder::new_multi_thread()
-            .worker_threads(1)
-            .enable_all()
-            .build()
-            .unwrap();
-        let mutex_infos = Arc::new(Mutex::new(Vec::<(u32, Vec<T>)>::new()));
-        let mut handles = Vec::with_capacity(codes.len());
-        let owned_codes = codes.to_owned();
-        // for i in 0..codes.len() {
-        for code in owned_codes.into_iter().take(codes.len()) {
-            let info_copy = mutex_infos.clone();
-            handles.push(runtime.spawn(async move {
-                let client = reqwest::Client::new();
-                let ret = T::query_history_info(code, start_date, end_date, client).await;
-                let mut copy = info_copy.lock().await;
-                copy.push((code, ret));
-            }));
-        }
-        for handle in handles {
-            runtime.block_on(handle).unwrap();
-        }
-        let infos: Vec<_> = Arc::try_unwrap(mutex_infos).unwrap().into_inner();
-        // let mut code_infos: Vec<_> = Vec::with_capacity(infos.len());
-        let mut code_infos: Vec<Vec<_>> = vec![Vec::new(); codes.len()];
-        for (code, infos) in infos {
-            let idx = codes.iter().position(|&c| c == code).unwrap();
-            code_infos[idx] = infos;
-        }
+    /// 从指定数据源构造，各个标的并发查询，任一标的查询失败或没有行情则返回错误
+    pub(crate) fn from_provider<P>(
+        provider: &P,
+        codes: &[u32],
+        start_date: Date,
+        end_date: Date,
+    ) -> Result<Self>
+    where
+        P: QueryMarketInfo<MarketInfo = T>,
+    {
+        let code_infos = crossbeam::scope(|s| {
+            let handles: Vec<_> = codes
+                .iter()
+                .map(|&code| {
+                    s.spawn(move |_| provider.query_history_info(code, start_date, end_date))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Result<Vec<_>>>()
+        })
+        .unwrap()?;
+        let code_infos = codes
+            .iter()
+            .zip(code_infos)
+            .map(|(&code, mut infos)| {
+                if infos.is_empty() {
+                    return Err(MarketError::EmptyRange {
+                        code,
+                        start_date,
+                        end_date,
+                    }
+                    .into());
+                }
+                infos.sort_by_key(|x| x.get_info_datetime());
+                Ok(VecDeque::from(infos))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-        InfoMixer {
+        let heads = code_infos
+            .iter()
+            .enumerate()
+            .filter_map(|(i, infos)| Some(Reverse((infos.front()?.get_info_datetime(), i))))
+            .collect();
+        Ok(InfoMixer {
             code: codes.into(),
             info: code_infos,
+            heads,
+        })
+    }
+}
+
+impl<T: QuantitativeMarket> InfoMixer<T> {
+    /// 取出第i个标的的下一条行情，并将其后一条行情的时间放入堆中
+    fn pop(&mut self, i: usize) -> (u32, T) {
+        let info = self.info[i].pop_front().unwrap();
+        if let Some(next) = self.info[i].front() {
+            self.heads.push(Reverse((next.get_info_datetime(), i)));
         }
+        (self.code[i], info)
     }
 }
 
@@ -106,30 +193,20 @@ impl<T> Iterator for InfoMixer<T>
 where
     T: QuantitativeMarket,
 {
-    type Item = (u32, T);
-    fn next(&mut self) -> Option<Self::Item> {
-        let future_time = date!(2099 - 1 - 1).with_hms(0, 0, 0).unwrap();
-        let first_ele_time: Vec<_> = self
-            .info
-            .iter()
-            .map(|x| {
-                let t = x.get(0);
+    type Item = Vec<(u32, T)>;
 
-                if let Some(s) = t {
-                    s.get_info_datetime()
-                } else {
-                    future_time
-                }
-            })
-            .collect();
-        if !first_ele_time.iter().any(|x| *x != future_time) {
-            return None;
+    /// 同一时刻所有标的的行情，各标的的行情已按时间排序，每次只需比较各标的的第一条行情
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((time, i)) = self.heads.pop()?;
+        let mut bars = vec![self.pop(i)];
+        while let Some(Reverse((next, i))) = self.heads.peek().copied() {
+            if next != time {
+                break;
+            }
+            self.heads.pop();
+            bars.push(self.pop(i));
         }
-
-        let earliest = first_ele_time.iter().min().unwrap();
-        let position = first_ele_time.iter().position(|&x| x == *earliest).unwrap();
-        let market_data = self.info[position].remove(0);
-        Some((self.code[position], market_data))
+        Some(bars)
     }
 }
 
@@ -138,12 +215,108 @@ mod tests {
     use super::*;
     use crate::market::fund_market::FundData;
 
+    /// 东方财富数据源，缓存放在临时目录，不读写用户的缓存目录
+    fn temp_provider(name: &str) -> CachedProvider<EastMoneyProvider<FundData>> {
+        let dir = std::env::temp_dir().join(format!(
+            "trade_helper_mixer_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        CachedProvider::new(EastMoneyProvider::default(), MarketCache::new(dir))
+    }
+
+    struct MemoryProvider(Vec<(u32, Vec<FundData>)>);
+
+    impl QueryMarketInfo for MemoryProvider {
+        type MarketInfo = FundData;
+
+        fn query_history_info(
+            &self,
+            code: u32,
+            start_date: Date,
+            end_date: Date,
+        ) -> Result<Vec<FundData>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|(c, _)| *c == code)
+                .flat_map(|(_, infos)| infos.iter().copied())
+                .filter(|x| x.date >= start_date && x.date <= end_date)
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_from_provider_empty_range() {
+        let provider = MemoryProvider(vec![(
+            1,
+            vec![FundData::new(date!(2021 - 9 - 1), 10000, 10000, None)],
+        )]);
+        let err = InfoMixer::from_provider(
+            &provider,
+            &[1, 2],
+            date!(2021 - 9 - 1),
+            date!(2021 - 9 - 30),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<MarketError>(),
+            Some(MarketError::EmptyRange { code: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_provider() {
+        let provider = MemoryProvider(vec![
+            (
+                1,
+                vec![
+                    FundData::new(date!(2021 - 9 - 2), 10100, 10100, None),
+                    FundData::new(date!(2021 - 9 - 1), 10000, 10000, None),
+                ],
+            ),
+            (
+                2,
+                vec![FundData::new(date!(2021 - 9 - 1), 20000, 20000, None)],
+            ),
+        ]);
+        let fund_mixer = InfoMixer::from_provider(
+            &provider,
+            &[2, 1],
+            date!(2021 - 9 - 1),
+            date!(2021 - 9 - 30),
+        )
+        .unwrap();
+        assert_eq!(fund_mixer.code, [2, 1]);
+        assert_eq!(fund_mixer.info[0].len(), 1);
+        // 按时间先后排序
+        assert_eq!(fund_mixer.info[1][0].date, date!(2021 - 9 - 1));
+        // 同一天的行情在一个截面中返回
+        let order: Vec<Vec<_>> = fund_mixer
+            .map(|bars| bars.iter().map(|(code, info)| (*code, info.date)).collect())
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                vec![(2, date!(2021 - 9 - 1)), (1, date!(2021 - 9 - 1))],
+                vec![(1, date!(2021 - 9 - 2))],
+            ]
+        );
+    }
+
     #[test]
     fn test_new_two_funds() {
         let start_date = date!(2021 - 9 - 1);
         let end_date = date!(2021 - 9 - 7);
         let codes = [002190_u32, 481010];
-        let fund_mixer = InfoMixer::<FundData>::new(&codes, start_date, end_date);
+        let fund_mixer = InfoMixer::from_provider(
+            &temp_provider("new_two_funds"),
+            &codes,
+            start_date,
+            end_date,
+        )
+        .unwrap();
         assert_eq!(fund_mixer.code, codes);
         assert_eq!(fund_mixer.info.len(), 2);
         assert_eq!(fund_mixer.info[0].len(), 5);
@@ -155,8 +328,16 @@ mod tests {
         let start_date = date!(2021 - 9 - 1);
         let end_date = date!(2021 - 9 - 7);
         let codes = [002190, 481010];
-        let fund_mixer = InfoMixer::<FundData>::new(&codes, start_date, end_date);
-        fund_mixer.for_each(|(code, info)| println!("{:?}: {}", info.date, code));
+        let fund_mixer = InfoMixer::from_provider(
+            &temp_provider("two_funds_iter"),
+            &codes,
+            start_date,
+            end_date,
+        )
+        .unwrap();
+        fund_mixer
+            .flatten()
+            .for_each(|(code, info)| println!("{:?}: {}", info.date, code));
     }
 
     #[test]
@@ -164,8 +345,16 @@ mod tests {
         let start_date = date!(2021 - 10 - 1);
         let end_date = date!(2021 - 10 - 25);
         let codes = [013606_u32, 481010];
-        let fund_mixer = InfoMixer::<FundData>::new(&codes, start_date, end_date);
-        fund_mixer.for_each(|(code, info)| println!("{:?}: {}", info.date, code));
+        let fund_mixer = InfoMixer::from_provider(
+            &temp_provider("unbalanced_two_funds_iter"),
+            &codes,
+            start_date,
+            end_date,
+        )
+        .unwrap();
+        fund_mixer
+            .flatten()
+            .for_each(|(code, info)| println!("{:?}: {}", info.date, code));
     }
 
     #[test]
@@ -173,7 +362,9 @@ mod tests {
         let start_date = date!(2021 - 10 - 1);
         let end_date = date!(2021 - 10 - 25);
         let codes = [013606_u32, 481010];
-        let fund_mixer = InfoMixer::<FundData>::new(&codes, start_date, end_date);
+        let fund_mixer =
+            InfoMixer::from_provider(&temp_provider("call_iter"), &codes, start_date, end_date)
+                .unwrap();
         // let fund_iter = fund_mixer.iter();
     }
 }
@@ -0,0 +1,64 @@
+//! ## 行情数据源
+//! ----
+//!
+//! InfoMixer通过实现了QueryMarketInfo的数据源获取历史行情，目前提供：
+//! + EastMoneyProvider: 从东方财富接口下载
+//! + FileProvider: 从本地目录读取csv或json文件，可用于存档数据、第三方数据以及离线回测
+//! + CachedProvider(见cache模块): 为任意数据源增加本地缓存
+//!
+//! 基金资料(见fund_profile模块)同样由这三种数据源提供
+
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use super::MarketCode;
+
+/// 东方财富行情数据源
+#[derive(Debug, Clone)]
+pub struct EastMoneyProvider<T> {
+    pub(crate) client: Client,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for EastMoneyProvider<T> {
+    fn default() -> Self {
+        EastMoneyProvider {
+            client: Client::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// 本地数据文件格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFileFormat {
+    Json,
+    Csv,
+}
+
+/// 本地文件数据源，目录下每个标的一个文件，文件名为`<六位代码>.json`或`<六位代码>.csv`
+#[derive(Debug, Clone)]
+pub struct FileProvider<T> {
+    pub(crate) dir: PathBuf,
+    _marker: PhantomData<T>,
+}
+
+impl<T> FileProvider<T> {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        FileProvider {
+            dir: dir.as_ref().to_path_buf(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// 查找标的对应的数据文件，json优先
+    pub(crate) fn find_file(&self, code: MarketCode) -> Result<(PathBuf, DataFileFormat)> {
+        [("json", DataFileFormat::Json), ("csv", DataFileFormat::Csv)]
+            .into_iter()
+            .map(|(ext, format)| (self.dir.join(format!("{:0>6}.{}", code, ext)), format))
+            .find(|(path, _)| path.is_file())
+            .ok_or_else(|| anyhow!("no data file for {:0>6} in {}", code, self.dir.display()))
+    }
+}
@@ -0,0 +1,308 @@
+#![cfg_attr(
+    debug_assertions,
+    allow(dead_code, unused_imports, unused_variables, unused_mut)
+)]
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use time::{macros::*, Date, PrimitiveDateTime};
+use tokio::runtime::Builder;
+
+use super::cache::Cacheable;
+use super::error::MarketError;
+use super::provider::{DataFileFormat, EastMoneyProvider, FileProvider};
+use super::{QuantitativeMarket, QueryMarketInfo};
+use crate::decimal::Price;
+
+/// 除权除息信息
+#[derive(Debug, Deserialize, Serialize, PartialEq, PartialOrd, Copy, Clone, Default)]
+pub struct ExRights {
+    pub(crate) cash_dividend: Price, // 每股派现
+    pub(crate) bonus_ratio: u32,     // 每股送转股数，乘以10000
+}
+
+/// 股票日线行情
+#[derive(Debug, Deserialize, Serialize, PartialEq, PartialOrd, Copy, Clone)]
+pub struct StockData {
+    pub(crate) date: Date,   // 交易日期
+    pub(crate) open: Price,  // 开盘价
+    pub(crate) high: Price,  // 最高价
+    pub(crate) low: Price,   // 最低价
+    pub(crate) close: Price, // 收盘价
+    pub(crate) volume: u64,  // 成交量(股)
+    #[serde(default)]
+    pub(crate) ex_rights: Option<ExRights>, // 当日除权除息
+}
+
+impl StockData {
+    /// 价格为放大10000倍的整数
+    pub(crate) fn new(date: Date, open: u32, high: u32, low: u32, close: u32, volume: u64) -> Self {
+        StockData {
+            date,
+            open: Price::from_raw(open),
+            high: Price::from_raw(high),
+            low: Price::from_raw(low),
+            close: Price::from_raw(close),
+            volume,
+            ex_rights: None,
+        }
+    }
+}
+
+impl Cacheable for StockData {
+    const CACHE_NAME: &'static str = "stock";
+}
+
+impl QuantitativeMarket for StockData {
+    fn get_info_datetime(&self) -> PrimitiveDateTime {
+        self.date.with_hms(15, 0, 0).unwrap()
+    }
+}
+
+const STOCK_KLINE_URL: &str = "http://push2his.eastmoney.com/api/qt/stock/kline/get";
+
+// 沪市代码以5、6、9开头，其余为深市
+fn stock_secid(code: u32) -> String {
+    let code = format!("{:0>6}", code);
+    match code.as_bytes()[0] {
+        b'5' | b'6' | b'9' => format!("1.{}", code),
+        _ => format!("0.{}", code),
+    }
+}
+
+// http://push2his.eastmoney.com/api/qt/stock/kline/get?secid=1.600000&fields1=f1,f2,f3&fields2=f51,f52,f53,f54,f55,f56&klt=101&fqt=0&beg=20210101&end=20211231
+fn stock_kline_url(code: u32, start_date: Date, end_date: Date) -> Result<Url, MarketError> {
+    let format = format_description!("[year][month][day]");
+    let format_date = |date: Date| {
+        date.format(&format)
+            .map_err(|e| MarketError::InvalidUrl(e.to_string()))
+    };
+    let params = [
+        ("secid", stock_secid(code)),
+        ("fields1", "f1,f2,f3".to_string()),
+        ("fields2", "f51,f52,f53,f54,f55,f56".to_string()),
+        // 日线，不复权
+        ("klt", "101".to_string()),
+        ("fqt", "0".to_string()),
+        ("beg", format_date(start_date)?),
+        ("end", format_date(end_date)?),
+    ];
+    Url::parse_with_params(STOCK_KLINE_URL, &params)
+        .map_err(|e| MarketError::InvalidUrl(e.to_string()))
+}
+
+/// 日线接口的返回内容
+#[derive(Debug, Deserialize)]
+struct KlineResponse {
+    data: Option<KlineData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KlineData {
+    #[serde(default)]
+    klines: Vec<String>,
+}
+
+// 解析一条日线：日期,开盘,收盘,最高,最低,成交量(手)
+fn parse_kline(line: &str) -> Result<StockData, MarketError> {
+    let malformed = || MarketError::MalformedResponse(format!("bad kline: {}", line));
+    let fields: Vec<_> = line.split(',').collect();
+    if fields.len() < 6 {
+        return Err(malformed());
+    }
+    let date = Date::parse(fields[0], &format_description!("[year]-[month]-[day]"))
+        .map_err(|_| malformed())?;
+    let price = |s: &str| s.parse::<Price>().map(Price::raw).map_err(|_| malformed());
+    let lots: u64 = fields[5].parse().map_err(|_| malformed())?;
+    Ok(StockData::new(
+        date,
+        price(fields[1])?,
+        price(fields[3])?,
+        price(fields[4])?,
+        price(fields[2])?,
+        lots * 100,
+    ))
+}
+
+fn parse_stock_history(code: u32, content: &str) -> Result<Vec<StockData>, MarketError> {
+    let response: KlineResponse =
+        serde_json::from_str(content).map_err(|e| MarketError::MalformedResponse(e.to_string()))?;
+    match response.data {
+        Some(data) => data.klines.iter().map(|x| parse_kline(x)).collect(),
+        None => Err(MarketError::UnknownCode(code)),
+    }
+}
+
+// 从东方财富异步查询指定日期范围内的股票日线，按时间先后排序
+async fn query_stock_history(
+    code: u32,
+    start_date: Date,
+    end_date: Date,
+    cli: &Client,
+) -> Result<Vec<StockData>, MarketError> {
+    let url = stock_kline_url(code, start_date, end_date)?;
+    let content = cli
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_stock_history(code, &content)
+}
+
+impl QueryMarketInfo for EastMoneyProvider<StockData> {
+    type MarketInfo = StockData;
+
+    fn query_history_info(
+        &self,
+        code: u32,
+        start_date: Date,
+        end_date: Date,
+    ) -> Result<Vec<StockData>> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(runtime.block_on(query_stock_history(
+            code,
+            start_date,
+            end_date,
+            &self.client,
+        ))?)
+    }
+}
+
+impl QueryMarketInfo for FileProvider<StockData> {
+    type MarketInfo = StockData;
+
+    /// 读取`<六位代码>.json`或`<六位代码>.csv`，
+    /// csv包含date、open、high、low、close、volume列，可选dividend(每股派现)、bonus(每股送转)列
+    fn query_history_info(
+        &self,
+        code: u32,
+        start_date: Date,
+        end_date: Date,
+    ) -> Result<Vec<StockData>> {
+        let all_stock_data = match self.find_file(code)? {
+            (path, DataFileFormat::Json) => {
+                serde_json::from_str::<Vec<StockData>>(&fs::read_to_string(path)?)?
+            }
+            (path, DataFileFormat::Csv) => read_stock_csv(&path)?,
+        };
+        Ok(all_stock_data
+            .into_iter()
+            .filter(|x| x.date >= start_date && x.date <= end_date)
+            .collect())
+    }
+}
+
+// 按表头读取csv中的股票日线，列的顺序不限
+fn read_stock_csv(path: &Path) -> Result<Vec<StockData>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| anyhow!("column {} not found in {}", name, path.display()))
+    };
+    let idx = [
+        column("date")?,
+        column("open")?,
+        column("high")?,
+        column("low")?,
+        column("close")?,
+        column("volume")?,
+    ];
+    let (dividend_idx, bonus_idx) = (column("dividend").ok(), column("bonus").ok());
+    let format = format_description!("[year]-[month]-[day]");
+    let mut ret = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+        let optional_price = |idx: Option<usize>| match idx.map(field) {
+            Some(s) if !s.is_empty() => s.parse::<Price>(),
+            _ => Ok(Price::ZERO),
+        };
+        let mut data = StockData::new(Date::parse(field(idx[0]), &format)?, 0, 0, 0, 0, 0);
+        data.open = field(idx[1]).parse()?;
+        data.high = field(idx[2]).parse()?;
+        data.low = field(idx[3]).parse()?;
+        data.close = field(idx[4]).parse()?;
+        data.volume = field(idx[5]).parse()?;
+        // 送转比例与价格的精度相同
+        let ex_rights = ExRights {
+            cash_dividend: optional_price(dividend_idx)?,
+            bonus_ratio: optional_price(bonus_idx)?.raw(),
+        };
+        if ex_rights != ExRights::default() {
+            data.ex_rights = Some(ex_rights);
+        }
+        ret.push(data);
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stock_secid() {
+        assert_eq!(stock_secid(600000), "1.600000");
+        assert_eq!(stock_secid(510300), "1.510300");
+        assert_eq!(stock_secid(1), "0.000001");
+        assert_eq!(stock_secid(300750), "0.300750");
+    }
+
+    #[test]
+    fn test_parse_stock_history() {
+        let content = "{\"rc\":0,\"data\":{\"code\":\"600000\",\"market\":1,\"klines\":[\
+            \"2021-01-04,10.60,10.50,10.62,10.43,641234\",\
+            \"2021-01-05,10.48,10.31,10.49,10.21,763109\"]}}";
+        let ret = parse_stock_history(600000, content).unwrap();
+        assert_eq!(
+            ret[0],
+            StockData::new(
+                date!(2021 - 1 - 4),
+                106000,
+                106200,
+                104300,
+                105000,
+                64123400
+            )
+        );
+        assert_eq!(ret[1].close, Price::from_raw(103100));
+        assert!(matches!(
+            parse_stock_history(999999, "{\"rc\":100,\"data\":null}"),
+            Err(MarketError::UnknownCode(999999))
+        ));
+    }
+
+    #[test]
+    fn test_file_provider_csv() {
+        let dir = std::env::temp_dir().join(format!("trade_helper_stock_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("600000.csv"),
+            "date,open,high,low,close,volume,dividend,bonus\n\
+             2021-07-14,9.10,9.20,9.00,9.15,500000,,\n\
+             2021-07-15,8.70,8.80,8.60,8.75,600000,0.493,0.3\n",
+        )
+        .unwrap();
+        let provider = FileProvider::<StockData>::new(&dir);
+        let ret = provider
+            .query_history_info(600000, date!(2021 - 7 - 1), date!(2021 - 7 - 31))
+            .unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret[0].ex_rights, None);
+        assert_eq!(
+            ret[1].ex_rights,
+            Some(ExRights {
+                cash_dividend: Price::from_raw(4930),
+                bonus_ratio: 3000
+            })
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
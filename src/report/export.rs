@@ -0,0 +1,465 @@
+//! ## 导出回测结果
+//! ----
+//!
+//! + json: 一个JSON文件，包含summary、holdings、trades、equity四部分
+//! + csv: 指定目录下的summary.csv、holdings.csv、trades.csv、equity.csv四个文件，
+//!   equity.csv在固定列之后为各标的的持仓市值，列名为六位代码
+//! + html: 单个静态网页，见html模块
+//!
+//! 金额、价格、份额均输出为十进制数，收益率等指标输出为小数(0.05表示5%)，无法计算的指标为空
+
+use crate::account::equity::EquityCurve;
+use crate::account::fund_account::FundAccount;
+use crate::account::{Account, DividendPolicy, TradeDetail, TradeItem, UpdateAccountItem};
+use crate::analysis::benchmark::Comparison;
+use crate::analysis::performance::{drawdowns, Performance};
+use anyhow::{anyhow, Context, Result};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::path::Path;
+use std::str::FromStr;
+use time::Date;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Csv,
+    Html,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(anyhow!(
+                "unknown output format {}, expected json, csv or html",
+                s
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// 汇总指标
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub(crate) strategy: String,
+    pub(crate) start: Date,
+    pub(crate) end: Date,
+    pub(crate) account_value: f64,
+    pub(crate) cash: f64,
+    pub(crate) invested: f64,
+    pub(crate) total_fee: f64,
+    pub(crate) management_fee: f64, // 估算的管理费，已体现在净值中
+    pub(crate) total_deposit: f64,
+    pub(crate) total_interest: f64,
+    pub(crate) rejected_buys: u32,
+    pub(crate) blocked_trades: u32, // 因申购、赎回状态未能成交的次数
+    pub(crate) xirr: Option<f64>,
+    pub(crate) twr: f64,
+    pub(crate) annualized_return: Option<f64>,
+    pub(crate) max_drawdown: Option<f64>,
+    pub(crate) drawdown_peak: Option<Date>,
+    pub(crate) drawdown_trough: Option<Date>,
+    pub(crate) volatility: Option<f64>,
+    pub(crate) sharpe: Option<f64>,
+    pub(crate) sortino: Option<f64>,
+    pub(crate) benchmark: Option<u32>,
+    pub(crate) benchmark_xirr: Option<f64>,
+    pub(crate) benchmark_twr: Option<f64>,
+    pub(crate) excess_return: Option<f64>,
+    pub(crate) excess_xirr: Option<f64>,
+    pub(crate) tracking_error: Option<f64>,
+    pub(crate) information_ratio: Option<f64>,
+    pub(crate) alpha: Option<f64>,
+    pub(crate) beta: Option<f64>,
+}
+
+/// 当前持仓
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldingRecord {
+    pub(crate) code: u32,
+    #[serde(default)]
+    pub(crate) name: Option<String>, // 基金名称，没有基金资料时为空
+    pub(crate) volume: f64,
+    pub(crate) price: f64,
+    pub(crate) value: f64,
+    pub(crate) average_price: Option<f64>,
+    pub(crate) realized_pnl: f64,
+    pub(crate) unrealized_pnl: f64,
+}
+
+impl HoldingRecord {
+    /// 六位代码，有名称时加上名称
+    pub(crate) fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{:0>6} {}", self.code, name),
+            None => format!("{:0>6}", self.code),
+        }
+    }
+}
+
+/// 交易记录，分红的price为每份分红，volume为红利再投增加的份额
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeRecord {
+    pub(crate) date: Date,
+    pub(crate) code: u32,
+    pub(crate) side: String,
+    pub(crate) price: f64,
+    pub(crate) volume: f64,
+    pub(crate) amount: f64,
+    pub(crate) fee: f64,
+    pub(crate) cash_flow: f64, // 对账户余额的影响，支出为负
+}
+
+/// 资产曲线上的一点
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquityRecord {
+    pub(crate) date: Date,
+    pub(crate) total_value: f64,
+    pub(crate) market_value: f64,
+    pub(crate) invested: f64,
+    pub(crate) cash: f64,
+    pub(crate) contribution: f64,
+    pub(crate) drawdown: f64, // 相对此前峰值的回撤，见performance::drawdowns
+    pub(crate) holdings: BTreeMap<u32, f64>,
+    pub(crate) prices: BTreeMap<u32, f64>,
+}
+
+/// 完整的回测报告
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub(crate) summary: Summary,
+    pub(crate) holdings: Vec<HoldingRecord>,
+    pub(crate) trades: Vec<TradeRecord>,
+    pub(crate) equity: Vec<EquityRecord>,
+}
+
+impl Report {
+    /// 由回测结果生成报告，benchmark为业绩基准的代码及比较结果
+    pub fn new(
+        strategy: &str,
+        account: &Account<FundAccount>,
+        equity: &EquityCurve,
+        performance: &Performance,
+        benchmark: Option<(u32, &Comparison)>,
+    ) -> Self {
+        let points = equity.points();
+        let (start, end) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first.date, last.date),
+            _ => (Date::MIN, Date::MIN),
+        };
+        let comparison = benchmark.map(|x| x.1);
+        let summary = Summary {
+            strategy: strategy.into(),
+            start,
+            end,
+            account_value: account.account_value.to_f64(),
+            cash: account.balance_price.to_f64(),
+            invested: account.invested.to_f64(),
+            total_fee: account.get_total_fee().to_f64(),
+            management_fee: account.get_management_fee().to_f64(),
+            total_deposit: account.total_deposit.to_f64(),
+            total_interest: account.total_interest.to_f64(),
+            rejected_buys: account.rejected_buys,
+            blocked_trades: account.blocked_trades,
+            xirr: performance.xirr,
+            twr: performance.twr,
+            annualized_return: performance.annualized_return,
+            max_drawdown: performance.max_drawdown.map(|x| x.ratio),
+            drawdown_peak: performance.max_drawdown.map(|x| x.peak),
+            drawdown_trough: performance.max_drawdown.map(|x| x.trough),
+            volatility: performance.volatility,
+            sharpe: performance.sharpe,
+            sortino: performance.sortino,
+            benchmark: benchmark.map(|x| x.0),
+            benchmark_xirr: comparison.and_then(|x| x.benchmark.xirr),
+            benchmark_twr: comparison.map(|x| x.benchmark.twr),
+            excess_return: comparison.map(|x| x.excess_return),
+            excess_xirr: comparison.and_then(|x| x.excess_xirr),
+            tracking_error: comparison.and_then(|x| x.tracking_error),
+            information_ratio: comparison.and_then(|x| x.information_ratio),
+            alpha: comparison.and_then(|x| x.alpha),
+            beta: comparison.and_then(|x| x.beta),
+        };
+        let mut holdings: Vec<_> = account
+            .hold_detail
+            .iter()
+            .map(|(code, item)| HoldingRecord {
+                code: *code,
+                name: None,
+                volume: item.get_current_volume().to_f64(),
+                price: item.get_current_value().to_f64(),
+                value: item.get_current_asset().to_f64(),
+                average_price: item.get_average_price().map(|x| x.to_f64()),
+                realized_pnl: account
+                    .get_object_realized_pnl(*code)
+                    .unwrap_or_default()
+                    .to_f64(),
+                unrealized_pnl: item.get_unrealized_pnl().to_f64(),
+            })
+            .collect();
+        holdings.sort_by_key(|x| x.code);
+        let trades = account
+            .trade_history()
+            .into_iter()
+            .map(|history| {
+                let detail = &history.trade_detail;
+                let (side, price, volume, amount) = match detail {
+                    TradeDetail::Buy(item) => deal("buy", item),
+                    TradeDetail::Sell(item) => deal("sell", item),
+                    TradeDetail::Dividend(item) => (
+                        match item.policy {
+                            DividendPolicy::Reinvest => "dividend_reinvest",
+                            DividendPolicy::Cash => "dividend_cash",
+                        },
+                        item.per_share.to_f64(),
+                        item.reinvest_volume.to_f64(),
+                        item.amount.to_f64(),
+                    ),
+                };
+                TradeRecord {
+                    date: history.trade_time.date(),
+                    code: history.trade_obj,
+                    side: side.into(),
+                    price,
+                    volume,
+                    amount,
+                    fee: detail.get_fee().to_f64(),
+                    cash_flow: detail.calc_cost_or_earning().to_f64(),
+                }
+            })
+            .collect();
+        let equity = points
+            .iter()
+            .zip(drawdowns(equity))
+            .map(|(x, (_, drawdown))| EquityRecord {
+                date: x.date,
+                total_value: x.total_value.to_f64(),
+                market_value: x.market_value.to_f64(),
+                invested: x.invested.to_f64(),
+                cash: x.cash.to_f64(),
+                contribution: x.contribution.to_f64(),
+                drawdown,
+                holdings: x.holdings.iter().map(|(k, v)| (*k, v.to_f64())).collect(),
+                prices: x.prices.iter().map(|(k, v)| (*k, v.to_f64())).collect(),
+            })
+            .collect();
+        Report {
+            summary,
+            holdings,
+            trades,
+            equity,
+        }
+    }
+
+    /// 以基金名称标注持仓
+    pub fn with_names(mut self, names: &HashMap<u32, String>) -> Self {
+        for holding in &mut self.holdings {
+            holding.name = names.get(&holding.code).cloned();
+        }
+        self
+    }
+
+    /// 按指定格式写入path，csv格式时path为目录
+    pub fn write(&self, format: OutputFormat, path: &Path) -> Result<()> {
+        match format {
+            OutputFormat::Json => self.write_json(path),
+            OutputFormat::Csv => self.write_csv(path),
+            OutputFormat::Html => self.write_html(path),
+        }
+        .with_context(|| format!("failed to write report to {}", path.display()))
+    }
+
+    /// 读取write_json保存的报告
+    pub fn read_json(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("invalid report {}", path.display()))
+    }
+
+    fn write_json(&self, path: &Path) -> Result<()> {
+        serde_json::to_writer_pretty(create_file(path)?, self)?;
+        Ok(())
+    }
+
+    fn write_csv(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir)?;
+        write_rows(&dir.join("summary.csv"), [&self.summary])?;
+        write_rows(&dir.join("holdings.csv"), &self.holdings)?;
+        write_rows(&dir.join("trades.csv"), &self.trades)?;
+
+        // 各标的的持仓市值展开为单独的列，价格只在json中输出
+        let codes: Vec<u32> = self
+            .equity
+            .iter()
+            .flat_map(|x| x.holdings.keys().copied())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let mut writer = csv::Writer::from_path(dir.join("equity.csv"))?;
+        let mut header: Vec<String> = [
+            "date",
+            "total_value",
+            "market_value",
+            "invested",
+            "cash",
+            "contribution",
+            "drawdown",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+        header.extend(codes.iter().map(|x| format!("{:0>6}", x)));
+        writer.write_record(&header)?;
+        for point in &self.equity {
+            let mut row = vec![
+                point.date.to_string(),
+                point.total_value.to_string(),
+                point.market_value.to_string(),
+                point.invested.to_string(),
+                point.cash.to_string(),
+                point.contribution.to_string(),
+                point.drawdown.to_string(),
+            ];
+            row.extend(codes.iter().map(|code| {
+                point
+                    .holdings
+                    .get(code)
+                    .map_or_else(String::new, |x| x.to_string())
+            }));
+            writer.write_record(&row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// 买卖记录的方向、成交价、成交份额和成交金额
+fn deal(side: &'static str, item: &TradeItem) -> (&'static str, f64, f64, f64) {
+    (
+        side,
+        item.deal_price.to_f64(),
+        item.deal_volume.to_f64(),
+        (item.deal_price * item.deal_volume).to_f64(),
+    )
+}
+
+/// 创建文件，上级目录不存在时一并创建
+pub(crate) fn create_file(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    Ok(File::create(path)?)
+}
+
+fn write_rows<I, R>(path: &Path, rows: I) -> Result<()>
+where
+    I: IntoIterator<Item = R>,
+    R: Serialize,
+{
+    let mut writer = csv::Writer::from_path(path)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decimal::Money;
+    use crate::market::fund_market::FundData;
+    use time::macros::date;
+
+    fn report() -> Report {
+        let mut account = Account::<FundAccount>::new();
+        let mut equity = EquityCurve::new();
+        let data1 = FundData::new(date!(2021 - 9 - 1), 10000, 10000, None);
+        account.buy_with_cost(1, &data1, Money::from_raw(100_000_000));
+        equity.record(account.snapshot(data1.date));
+        let data2 = FundData::new(date!(2021 - 9 - 2), 10000, 10500, Some(500));
+        account.update_account(1, data2);
+        account.update_account_value();
+        equity.record(account.snapshot(data2.date));
+        let performance = Performance::evaluate(&equity, 0.0);
+        Report::new("aip", &account, &equity, &performance, None)
+    }
+
+    #[test]
+    fn test_report() {
+        let report = report().with_names(&HashMap::from([(1, "测试基金".to_string())]));
+        assert_eq!(report.holdings[0].label(), "000001 测试基金");
+        assert_eq!(report.summary.start, date!(2021 - 9 - 1));
+        assert_eq!(report.summary.account_value, 105.0);
+        assert_eq!(report.summary.benchmark, None);
+        assert_eq!(report.holdings.len(), 1);
+        assert_eq!(report.holdings[0].volume, 105.0);
+        let sides: Vec<_> = report.trades.iter().map(|x| x.side.as_str()).collect();
+        assert_eq!(sides, vec!["buy", "dividend_reinvest"]);
+        assert_eq!(report.trades[0].cash_flow, -100.0);
+        assert_eq!(report.equity.len(), 2);
+        assert_eq!(report.equity[1].holdings[&1], 105.0);
+    }
+
+    #[test]
+    fn test_write_json_and_csv() {
+        let report = report();
+        let dir = std::env::temp_dir().join(format!("trade_helper_export_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let json = dir.join("report.json");
+        report.write(OutputFormat::Json, &json).unwrap();
+        let value: serde_json::Value = serde_json::from_reader(File::open(&json).unwrap()).unwrap();
+        assert_eq!(value["summary"]["strategy"], "aip");
+        assert_eq!(value["trades"][0]["amount"], 100.0);
+        assert_eq!(value["summary"]["sharpe"], serde_json::Value::Null);
+        let saved = Report::read_json(&json).unwrap();
+        assert_eq!(saved.trades, report.trades);
+        assert_eq!(saved.equity.len(), report.equity.len());
+        assert_eq!(saved.equity[1].holdings, report.equity[1].holdings);
+
+        let csv_dir = dir.join("csv");
+        report.write(OutputFormat::Csv, &csv_dir).unwrap();
+        let equity = fs::read_to_string(csv_dir.join("equity.csv")).unwrap();
+        let mut lines = equity.lines();
+        assert_eq!(
+            lines.next(),
+            Some("date,total_value,market_value,invested,cash,contribution,drawdown,000001")
+        );
+        assert_eq!(lines.next(), Some("2021-09-01,100,100,100,-100,100,0,100"));
+        let trades = fs::read_to_string(csv_dir.join("trades.csv")).unwrap();
+        assert!(trades.starts_with("date,code,side,price,volume,amount,fee,cash_flow\n"));
+        assert_eq!(
+            fs::read_to_string(csv_dir.join("summary.csv"))
+                .unwrap()
+                .lines()
+                .count(),
+            2
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_output_format() {
+        assert_eq!("JSON".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+        assert_eq!("csv".parse::<OutputFormat>().unwrap(), OutputFormat::Csv);
+        assert_eq!("html".parse::<OutputFormat>().unwrap(), OutputFormat::Html);
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,461 @@
+//! ## 网页报告
+//! ----
+//!
+//! 将回测报告渲染为单个HTML文件，图表为内嵌SVG，不引用外部脚本和样式：
+//! + 总资产与外部净投入(成本)曲线
+//! + 按时间加权收益率计算的回撤曲线
+//! + 期末持仓分布饼图
+//! + 各标的的净值曲线，买入日期以圆点标出
+//! + 汇总指标、持仓和交易记录表格
+
+use super::export::{create_file, Report};
+use crate::analysis::performance::{number, percent};
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+use time::Date;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 260.0;
+const LEFT: f64 = 70.0; // 左侧留给纵轴刻度
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 30.0; // 上方留给图例
+const BOTTOM: f64 = 30.0; // 下方留给日期
+const COLORS: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:840px;margin:20px auto;color:#222}\
+table{border-collapse:collapse;margin-bottom:16px}\
+td,th{border:1px solid #ccc;padding:3px 8px;text-align:right}\
+th{background:#f3f3f3}td:first-child,th:first-child{text-align:left}\
+svg{display:block;margin-bottom:16px}";
+
+/// 折线图中的一条折线
+struct Series<'a> {
+    name: &'a str,
+    color: &'a str,
+    points: Vec<(Date, f64)>,
+}
+
+impl Report {
+    pub(crate) fn write_html(&self, path: &Path) -> Result<()> {
+        create_file(path)?.write_all(self.to_html().as_bytes())?;
+        Ok(())
+    }
+
+    /// 渲染为完整的HTML文档
+    pub(crate) fn to_html(&self) -> String {
+        let summary = &self.summary;
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>backtest report: {name}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+             <h1>backtest report: {name}</h1>\n<p>{} ~ {}</p>\n",
+            summary.start,
+            summary.end,
+            name = escape(&summary.strategy),
+        );
+
+        html.push_str("<h2>metrics</h2>\n");
+        html.push_str(&table(&["metric", "value"], &self.metrics()));
+
+        html.push_str("<h2>equity curve</h2>\n");
+        let equity = [
+            Series {
+                name: "total value",
+                color: COLORS[0],
+                points: self
+                    .equity
+                    .iter()
+                    .map(|x| (x.date, x.total_value))
+                    .collect(),
+            },
+            Series {
+                name: "contribution",
+                color: COLORS[1],
+                points: self
+                    .equity
+                    .iter()
+                    .map(|x| (x.date, x.contribution))
+                    .collect(),
+            },
+        ];
+        html.push_str(&line_chart(&equity, &[], money));
+
+        html.push_str("<h2>drawdown</h2>\n");
+        let drawdown = [Series {
+            name: "drawdown",
+            color: COLORS[3],
+            points: self.equity.iter().map(|x| (x.date, -x.drawdown)).collect(),
+        }];
+        html.push_str(&line_chart(&drawdown, &[], |x| percent(Some(x))));
+
+        html.push_str("<h2>allocation</h2>\n");
+        let slices: Vec<_> = self
+            .holdings
+            .iter()
+            .filter(|x| x.value > 0.0)
+            .map(|x| (x.label(), x.value))
+            .collect();
+        html.push_str(&pie_chart(&slices));
+
+        for (i, holding) in self.holdings.iter().enumerate() {
+            let label = holding.label();
+            let nav = [Series {
+                name: &label,
+                color: COLORS[i % COLORS.len()],
+                points: self
+                    .equity
+                    .iter()
+                    .filter_map(|x| x.prices.get(&holding.code).map(|p| (x.date, *p)))
+                    .collect(),
+            }];
+            let buys: Vec<_> = self
+                .trades
+                .iter()
+                .filter(|x| x.code == holding.code && x.side == "buy")
+                .map(|x| (x.date, x.price))
+                .collect();
+            html.push_str(&format!("<h2>{} nav and buys</h2>\n", escape(&label)));
+            html.push_str(&line_chart(&nav, &buys, |x| format!("{:.4}", x)));
+        }
+
+        html.push_str("<h2>holdings</h2>\n");
+        let rows: Vec<Vec<String>> = self
+            .holdings
+            .iter()
+            .map(|x| {
+                vec![
+                    x.label(),
+                    format!("{:.2}", x.volume),
+                    format!("{:.4}", x.price),
+                    money(x.value),
+                    x.average_price
+                        .map_or_else(|| "n/a".into(), |x| format!("{:.4}", x)),
+                    money(x.realized_pnl),
+                    money(x.unrealized_pnl),
+                ]
+            })
+            .collect();
+        html.push_str(&table(
+            &[
+                "fund",
+                "volume",
+                "price",
+                "value",
+                "average price",
+                "realized pnl",
+                "unrealized pnl",
+            ],
+            &rows,
+        ));
+
+        html.push_str("<h2>trades</h2>\n");
+        let rows: Vec<Vec<String>> = self
+            .trades
+            .iter()
+            .map(|x| {
+                vec![
+                    x.date.to_string(),
+                    format!("{:0>6}", x.code),
+                    x.side.clone(),
+                    format!("{:.4}", x.price),
+                    format!("{:.2}", x.volume),
+                    money(x.amount),
+                    money(x.fee),
+                ]
+            })
+            .collect();
+        html.push_str(&table(
+            &["date", "code", "side", "price", "volume", "amount", "fee"],
+            &rows,
+        ));
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// 汇总指标表格的各行
+    fn metrics(&self) -> Vec<Vec<String>> {
+        let s = &self.summary;
+        let mut rows = vec![
+            ("account value", money(s.account_value)),
+            ("cash", money(s.cash)),
+            ("invested", money(s.invested)),
+            ("fee", money(s.total_fee)),
+            ("estimated management fee", money(s.management_fee)),
+        ];
+        if s.total_deposit > 0.0 {
+            rows.push(("deposit", money(s.total_deposit)));
+            rows.push(("interest", money(s.total_interest)));
+            rows.push(("rejected buys", s.rejected_buys.to_string()));
+        }
+        if s.blocked_trades > 0 {
+            rows.push(("blocked trades", s.blocked_trades.to_string()));
+        }
+        let max_drawdown = match (s.max_drawdown, s.drawdown_peak, s.drawdown_trough) {
+            (Some(ratio), Some(peak), Some(trough)) => {
+                format!("{} ({} -> {})", percent(Some(ratio)), peak, trough)
+            }
+            _ => "n/a".into(),
+        };
+        rows.extend([
+            ("xirr", percent(s.xirr)),
+            ("time-weighted return", percent(Some(s.twr))),
+            ("annualized return", percent(s.annualized_return)),
+            ("max drawdown", max_drawdown),
+            ("volatility", percent(s.volatility)),
+            ("sharpe", number(s.sharpe)),
+            ("sortino", number(s.sortino)),
+        ]);
+        if let Some(code) = s.benchmark {
+            rows.extend([
+                ("benchmark", format!("{:0>6}", code)),
+                ("benchmark xirr", percent(s.benchmark_xirr)),
+                ("benchmark time-weighted return", percent(s.benchmark_twr)),
+                ("excess return", percent(s.excess_return)),
+                ("excess xirr", percent(s.excess_xirr)),
+                ("tracking error", percent(s.tracking_error)),
+                ("information ratio", number(s.information_ratio)),
+                ("alpha", percent(s.alpha)),
+                ("beta", number(s.beta)),
+            ]);
+        }
+        rows.into_iter()
+            .map(|(name, value)| vec![name.to_string(), value])
+            .collect()
+    }
+}
+
+fn money(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+/// 转义HTML特殊字符
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut html = String::from("<table>\n<tr>");
+    for name in header {
+        html.push_str(&format!("<th>{}</th>", escape(name)));
+    }
+    html.push_str("</tr>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<td>{}</td>", escape(cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// 按日期绘制折线图，markers为额外标出的点，label格式化纵轴刻度
+fn line_chart(series: &[Series], markers: &[(Date, f64)], label: fn(f64) -> String) -> String {
+    let all = || {
+        series
+            .iter()
+            .flat_map(|x| x.points.iter())
+            .chain(markers)
+            .copied()
+    };
+    let (first, last) = match (all().map(|x| x.0).min(), all().map(|x| x.0).max()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return "<p>no data</p>\n".into(),
+    };
+    let (low, high) = all().fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), x| {
+        (low.min(x.1), high.max(x.1))
+    });
+    // 上下各留5%的空白，所有值相同时以该值为中心
+    let (low, high) = if high > low {
+        let pad = (high - low) * 0.05;
+        (low - pad, high + pad)
+    } else {
+        (low - 1.0, high + 1.0)
+    };
+    let span = (last - first).whole_days().max(1) as f64;
+    let x = |date: Date| LEFT + (date - first).whole_days() as f64 / span * (WIDTH - LEFT - RIGHT);
+    let y = |value: f64| TOP + (high - value) / (high - low) * (HEIGHT - TOP - BOTTOM);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-size=\"11\">\n",
+        w = WIDTH,
+        h = HEIGHT
+    );
+    for i in 0..=4 {
+        let value = low + (high - low) * i as f64 / 4.0;
+        svg.push_str(&format!(
+            "<line x1=\"{:.1}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"#e5e5e5\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+            LEFT,
+            WIDTH - RIGHT,
+            LEFT - 4.0,
+            y(value) + 4.0,
+            escape(&label(value)),
+            y = y(value),
+        ));
+    }
+    let middle = first + (last - first) / 2;
+    for (date, anchor) in [(first, "start"), (middle, "middle"), (last, "end")] {
+        svg.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{}\">{}</text>\n",
+            x(date),
+            HEIGHT - 10.0,
+            anchor,
+            date
+        ));
+    }
+    for (i, line) in series.iter().enumerate() {
+        let points: Vec<String> = line
+            .points
+            .iter()
+            .map(|(date, value)| format!("{:.1},{:.1}", x(*date), y(*value)))
+            .collect();
+        svg.push_str(&format!(
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\"/>\n",
+            line.color,
+            points.join(" ")
+        ));
+        let legend = LEFT + 150.0 * i as f64;
+        svg.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"8\" width=\"12\" height=\"12\" fill=\"{}\"/>\
+             <text x=\"{:.1}\" y=\"18\">{}</text>\n",
+            legend,
+            line.color,
+            legend + 16.0,
+            escape(line.name)
+        ));
+    }
+    for (date, value) in markers {
+        svg.push_str(&format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"#d62728\"><title>{} {}</title></circle>\n",
+            x(*date),
+            y(*value),
+            date,
+            escape(&label(*value))
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// 饼图，slices为各部分的名称和数值
+fn pie_chart(slices: &[(String, f64)]) -> String {
+    let total: f64 = slices.iter().map(|x| x.1).sum();
+    if total <= 0.0 {
+        return "<p>no holdings</p>\n".into();
+    }
+    let (cx, cy, r) = (HEIGHT / 2.0, HEIGHT / 2.0, HEIGHT / 2.0 - 10.0);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-size=\"11\">\n",
+        w = WIDTH,
+        h = HEIGHT
+    );
+    // 从12点方向开始顺时针绘制
+    let point = |angle: f64| {
+        let radian = (angle - 0.25) * std::f64::consts::TAU;
+        (cx + r * radian.cos(), cy + r * radian.sin())
+    };
+    let mut start = 0.0;
+    for (i, (name, value)) in slices.iter().enumerate() {
+        let ratio = value / total;
+        let color = COLORS[i % COLORS.len()];
+        if ratio >= 1.0 - 1e-9 {
+            svg.push_str(&format!(
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\"/>\n",
+                cx, cy, r, color
+            ));
+        } else {
+            let (x0, y0) = point(start);
+            let (x1, y1) = point(start + ratio);
+            svg.push_str(&format!(
+                "<path d=\"M{:.1},{:.1} L{:.1},{:.1} A{:.1},{:.1} 0 {} 1 {:.1},{:.1} Z\" fill=\"{}\"/>\n",
+                cx,
+                cy,
+                x0,
+                y0,
+                r,
+                r,
+                u8::from(ratio > 0.5),
+                x1,
+                y1,
+                color
+            ));
+        }
+        start += ratio;
+        let legend = 20.0 + 18.0 * i as f64;
+        svg.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{}\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{} {} ({})</text>\n",
+            HEIGHT + 20.0,
+            legend,
+            color,
+            HEIGHT + 36.0,
+            legend + 10.0,
+            escape(name),
+            money(*value),
+            percent(Some(ratio))
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::equity::EquityCurve;
+    use crate::account::fund_account::FundAccount;
+    use crate::account::Account;
+    use crate::analysis::performance::Performance;
+    use crate::decimal::Money;
+    use crate::market::fund_market::FundData;
+    use time::macros::date;
+
+    #[test]
+    fn test_to_html() {
+        let mut account = Account::<FundAccount>::new();
+        let mut equity = EquityCurve::new();
+        for (date, nav) in [
+            (date!(2021 - 9 - 1), 10000),
+            (date!(2021 - 9 - 2), 9000),
+            (date!(2021 - 9 - 3), 12000),
+        ] {
+            let data = FundData::new(date, nav, nav, None);
+            account.update_account(1, data);
+            account.buy_with_cost(1, &data, Money::from_raw(100_000_000));
+            account.update_account_value();
+            equity.record(account.snapshot(date));
+        }
+        let performance = Performance::evaluate(&equity, 0.0);
+        let report = Report::new("<aip>", &account, &equity, &performance, None);
+        let html = report.to_html();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("backtest report: &lt;aip&gt;"));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("src=") && !html.contains("<link"));
+        // 资产曲线、回撤、净值三张折线图和一张饼图
+        assert_eq!(html.matches("<svg").count(), 4);
+        assert_eq!(html.matches("<circle").count(), 4); // 3个买入点和只有一只基金的饼图
+        assert!(html.contains("<h2>000001 nav and buys</h2>"));
+        assert!(html.contains("<td>max drawdown</td><td>10.00% (2021-09-01 -&gt; 2021-09-02)</td>"));
+    }
+
+    #[test]
+    fn test_empty_chart() {
+        assert_eq!(line_chart(&[], &[], money), "<p>no data</p>\n");
+        assert_eq!(pie_chart(&[]), "<p>no holdings</p>\n");
+        let flat = [Series {
+            name: "flat",
+            color: COLORS[0],
+            points: vec![(date!(2021 - 9 - 1), 1.0)],
+        }];
+        assert!(line_chart(&flat, &[], money).contains("<polyline"));
+    }
+}
@@ -0,0 +1,9 @@
+//! ## 回测报告
+//! ----
+//!
+//! 将回测结果输出为机器可读的格式
+//! + export: 持仓、交易记录、资产曲线及汇总指标，输出为JSON文件或一组CSV文件
+//! + html: 以内嵌SVG图表展示回测结果的单个静态网页，不依赖外部脚本，可离线打开
+
+pub mod export;
+pub mod html;
@@ -0,0 +1,183 @@
+//! ## 回测引擎
+//! ----
+//!
+//! 按时间先后遍历各标的同一时刻的行情截面(见InfoMixer)，先以整个截面更新账户持仓，再交给策略决定是否交易，
+//! 策略看到的是所有标的都已更新到当日的账户。
+//! 非交易日的行情(如节假日公布的估值)只更新持仓市值，不交给策略，也不单独记录快照。
+//! 每个交易日的行情处理完后记录账户快照，得到资产曲线(见account::equity)。
+//! 回测结束时结算现金账户，按最新行情计算账面价值，并记录结束日的快照
+
+use super::Strategy;
+use crate::account::equity::EquityCurve;
+use crate::account::{Account, UpdateAccountItem};
+use crate::market::QuantitativeMarket;
+use crate::market::{InfoMixer, QueryMarketInfo};
+use anyhow::Result;
+use std::fmt::Debug;
+use time::Date;
+
+/// 回测设置：标的及起止日期
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backtest {
+    codes: Vec<u32>,
+    start: Date,
+    end: Date,
+}
+
+/// 回测结果：结束时的账户及逐日的资产曲线
+#[derive(Debug)]
+pub struct BacktestResult<T: UpdateAccountItem> {
+    pub(crate) account: Account<T>,
+    pub(crate) equity: EquityCurve,
+}
+
+impl Backtest {
+    pub fn new(codes: &[u32], start: Date, end: Date) -> Self {
+        Backtest {
+            codes: codes.into(),
+            start,
+            end,
+        }
+    }
+
+    /// 以指定数据源和初始账户运行策略，返回回测结束时的账户及资产曲线
+    pub fn run<T, P, S>(
+        &self,
+        provider: &P,
+        mut account: Account<T>,
+        strategy: &mut S,
+    ) -> Result<BacktestResult<T>>
+    where
+        T: UpdateAccountItem + Default + Clone,
+        T::MarketData: Debug,
+        P: QueryMarketInfo<MarketInfo = T::MarketData>,
+        S: Strategy<T> + ?Sized,
+    {
+        let mixer = InfoMixer::from_provider(provider, &self.codes, self.start, self.end)?;
+        let mut equity = EquityCurve::new();
+        let mut trading_day: Option<Date> = None;
+        strategy.on_start(self.start, &mut account);
+        for bars in mixer {
+            let date = bars[0].1.get_info_datetime().date();
+            let on_trading_day = bars[0].1.on_trading_day();
+            match trading_day {
+                Some(day) if day != date => {
+                    equity.record(account.snapshot(day));
+                    trading_day = on_trading_day.then_some(date);
+                }
+                None => trading_day = on_trading_day.then_some(date),
+                _ => {}
+            }
+            for (code, data) in &bars {
+                account.update_account(*code, *data);
+            }
+            if on_trading_day {
+                strategy.on_bars(&bars, &mut account);
+            }
+        }
+        if let Some(day) = trading_day {
+            equity.record(account.snapshot(day));
+        }
+        account.settle_cash(self.end);
+        account.update_account_value();
+        strategy.on_finish(self.end, &mut account);
+        equity.record(account.snapshot(self.end));
+        Ok(BacktestResult { account, equity })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::fund_account::FundAccount;
+    use crate::decimal::Money;
+    use crate::market::fund_market::FundData;
+    use time::macros::date;
+
+    // 每个自然日净值都是1.0000，包括周末和节假日
+    struct FlatProvider;
+
+    impl QueryMarketInfo for FlatProvider {
+        type MarketInfo = FundData;
+
+        fn query_history_info(
+            &self,
+            _code: u32,
+            start_date: Date,
+            end_date: Date,
+        ) -> Result<Vec<FundData>> {
+            let mut ret = Vec::new();
+            let mut date = start_date;
+            while date <= end_date {
+                ret.push(FundData::new(date, 10000, 10000, None));
+                date = date.next_day().unwrap();
+            }
+            Ok(ret)
+        }
+    }
+
+    // 每条行情都买入1元，并记录各个回调的调用次数
+    #[derive(Default)]
+    struct EveryBar {
+        started: u32,
+        slices: u32,
+        bars: u32,
+        finished: u32,
+    }
+
+    impl Strategy<FundAccount> for EveryBar {
+        fn name(&self) -> &str {
+            "every-bar"
+        }
+
+        fn on_start(&mut self, _start: Date, _account: &mut Account<FundAccount>) {
+            self.started += 1;
+        }
+
+        fn on_bars(&mut self, bars: &[(u32, FundData)], account: &mut Account<FundAccount>) {
+            self.slices += 1;
+            for (code, data) in bars {
+                self.on_bar(*code, data, account);
+            }
+        }
+
+        fn on_bar(&mut self, code: u32, data: &FundData, account: &mut Account<FundAccount>) {
+            self.bars += 1;
+            account.buy_with_cost(code, data, Money::from_raw(1_000_000));
+        }
+
+        fn on_finish(&mut self, _end: Date, _account: &mut Account<FundAccount>) {
+            self.finished += 1;
+        }
+    }
+
+    #[test]
+    fn test_run_strategy() {
+        let mut strategy = EveryBar::default();
+        let BacktestResult { account, equity } =
+            Backtest::new(&[1, 2], date!(2021 - 1 - 1), date!(2021 - 1 - 10))
+                .run(&FlatProvider, Account::new(), &mut strategy)
+                .unwrap();
+        // 1月1日元旦和周末不交易，只有4日至8日的行情交给策略
+        assert_eq!(
+            (
+                strategy.started,
+                strategy.slices,
+                strategy.bars,
+                strategy.finished
+            ),
+            (1, 5, 10, 1)
+        );
+        assert_eq!(account.balance_price, Money::from_raw(-10_000_000));
+        assert_eq!(account.account_value, Money::from_raw(10_000_000));
+        // 每个交易日一个快照，加上结束日的快照
+        assert_eq!(equity.len(), 6);
+        let first = &equity.points()[0];
+        assert_eq!(first.date, date!(2021 - 1 - 4));
+        assert_eq!(first.invested, Money::from_raw(2_000_000));
+        assert_eq!(first.holdings[&2], Money::from_raw(1_000_000));
+        let last = equity.last().unwrap();
+        assert_eq!(last.date, date!(2021 - 1 - 10));
+        assert_eq!(last.market_value, Money::from_raw(10_000_000));
+    }
+}
@@ -2,12 +2,221 @@
     debug_assertions,
     allow(dead_code, unused_imports, unused_variables, unused_mut)
 )]
-use crate::account::fund_account::FundAccount;
+//! ## 基金策略
+//! ----
+//!
+//! + AipStrategy: 定期定额投资，按买入计划(默认每月第day日，非交易日顺延)买入固定金额
+//! + BuyMoreStrategy: 越跌越买，按买入计划买入，净值低于持仓均价时按均价与净值之比加倍买入，可设置倍数上限
+//! + ReplayStrategy: 按另一次回测的买卖净额买卖指定基金，用于与业绩基准比较
+
+use super::engine::{Backtest, BacktestResult};
+use super::schedule::{Schedule, Scheduler};
+use super::Strategy;
+use crate::account::cash::CashPlan;
+use crate::account::fund_account::{FundAccount, FundSetting};
 use crate::account::Account;
+use crate::decimal::Money;
 use crate::market::fund_market::FundData;
-use crate::market::InfoMixer;
-use std::collections::HashMap;
-use time::{macros::*, Date, Month};
+use crate::market::{default_provider, QueryMarketInfo};
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use time::{macros::*, Date};
+
+/// 定投计划：买入计划(见schedule模块)，以及各基金每期的买入金额
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundPlan {
+    pub(crate) schedule: Schedule,
+    pub(crate) schedules: HashMap<u32, Schedule>, // 单独指定买入计划的基金
+    pub(crate) budget: HashMap<u32, Money>,
+}
+
+impl FundPlan {
+    /// 每月第day日买入
+    pub fn new(day: u8, fund: &[u32], budget: &[Money]) -> Self {
+        FundPlan {
+            schedule: Schedule::Monthly(day),
+            schedules: HashMap::new(),
+            budget: fund.iter().copied().zip(budget.iter().copied()).collect(),
+        }
+    }
+
+    /// 所有基金默认的买入计划
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// 单独指定某只基金的买入计划
+    pub fn with_fund_schedule(mut self, code: u32, schedule: Schedule) -> Self {
+        self.schedules.insert(code, schedule);
+        self
+    }
+
+    fn scheduler(&self) -> Scheduler {
+        Scheduler::new(self.schedule.clone(), self.schedules.clone())
+    }
+}
+
+///  Automatic Investment Plan
+#[derive(Debug, Clone)]
+pub struct AipStrategy {
+    budget: HashMap<u32, Money>,
+    schedule: Scheduler,
+}
+
+impl AipStrategy {
+    pub fn new(plan: FundPlan) -> Self {
+        AipStrategy {
+            schedule: plan.scheduler(),
+            budget: plan.budget,
+        }
+    }
+}
+
+impl Strategy<FundAccount> for AipStrategy {
+    fn name(&self) -> &str {
+        "aip"
+    }
+
+    fn on_start(&mut self, start: Date, _account: &mut Account<FundAccount>) {
+        self.schedule.reset(self.budget.keys().copied(), start);
+    }
+
+    fn on_bar(&mut self, code: u32, data: &FundData, account: &mut Account<FundAccount>) {
+        if self.schedule.is_due(code, data.date) {
+            account.buy_with_cost(code, data, self.budget[&code]);
+        }
+    }
+}
+
+/// buy more at lower price
+#[derive(Debug, Clone)]
+pub struct BuyMoreStrategy {
+    budget: HashMap<u32, Money>,
+    schedule: Scheduler,
+    max_multiple: Option<u64>, // 加倍买入的上限
+}
+
+impl BuyMoreStrategy {
+    pub fn new(plan: FundPlan) -> Self {
+        BuyMoreStrategy {
+            schedule: plan.scheduler(),
+            budget: plan.budget,
+            max_multiple: None,
+        }
+    }
+
+    /// 每期最多买入定投金额的几倍，至少为1倍
+    pub fn with_max_multiple(mut self, multiple: u64) -> Self {
+        self.max_multiple = Some(multiple.max(1));
+        self
+    }
+}
+
+impl Strategy<FundAccount> for BuyMoreStrategy {
+    fn name(&self) -> &str {
+        "buy-more"
+    }
+
+    fn on_start(&mut self, start: Date, _account: &mut Account<FundAccount>) {
+        self.schedule.reset(self.budget.keys().copied(), start);
+    }
+
+    fn on_bar(&mut self, code: u32, data: &FundData, account: &mut Account<FundAccount>) {
+        if !self.schedule.is_due(code, data.date) {
+            return;
+        }
+        let mut budget = self.budget[&code];
+        match account.get_object_average_price(code) {
+            Some(avg) if avg > data.unit_nav => {
+                let multiple = (avg.raw() / data.unit_nav.raw()) as u64;
+                let multiple = self.max_multiple.map_or(multiple, |x| multiple.min(x));
+                budget = budget.mul_ratio(multiple, 1);
+            }
+            _ => {}
+        }
+        account.buy_with_cost(code, data, budget);
+    }
+}
+
+/// 重放买卖净额：净买入时买入相同金额，净卖出时卖出相同金额。
+/// 基准没有当日行情时(如停牌、交易日历不同)，顺延到下一个有行情的交易日
+#[derive(Debug, Clone)]
+pub struct ReplayStrategy {
+    flows: BTreeMap<Date, Money>,
+}
+
+impl ReplayStrategy {
+    pub fn new(flows: BTreeMap<Date, Money>) -> Self {
+        ReplayStrategy { flows }
+    }
+}
+
+impl Strategy<FundAccount> for ReplayStrategy {
+    fn name(&self) -> &str {
+        "replay"
+    }
+
+    fn on_bar(&mut self, code: u32, data: &FundData, account: &mut Account<FundAccount>) {
+        let due: Vec<Date> = self.flows.range(..=data.date).map(|x| *x.0).collect();
+        let amount: Money = due.iter().filter_map(|x| self.flows.remove(x)).sum();
+        if amount > Money::ZERO {
+            account.buy_with_cost(code, data, amount);
+        } else if amount < Money::ZERO {
+            let asset = account.get_object_assets(code).unwrap_or_default();
+            if asset > Money::ZERO {
+                let proportion = ((-amount).to_f64() / asset.to_f64()).min(1.0);
+                account.sell_with_proportion(code, data, proportion as f32);
+            }
+        }
+    }
+}
+
+/// 以相同的买卖净额投资业绩基准，返回基准的回测结果。
+/// 基准账户使用与策略相同的现金计划，费率和分红方式由setting指定
+pub fn run_benchmark_with<P>(
+    provider: &P,
+    start: Date,
+    end: Date,
+    benchmark: u32,
+    flows: BTreeMap<Date, Money>,
+    setting: &FundSetting,
+    cash: Option<CashPlan>,
+) -> Result<BacktestResult<FundAccount>>
+where
+    P: QueryMarketInfo<MarketInfo = FundData>,
+{
+    let mut account = match cash {
+        Some(plan) => Account::<FundAccount>::with_cash(plan),
+        None => Account::<FundAccount>::new(),
+    };
+    account.apply_setting(benchmark, setting);
+    let mut strategy = ReplayStrategy::new(flows);
+    Backtest::new(&[benchmark], start, end).run(provider, account, &mut strategy)
+}
+
+/// 使用指定的行情数据源、各基金的费率和分红方式及现金计划(None表示资金无限)运行基金策略
+pub fn run_fund_strategy_with<P>(
+    provider: &P,
+    start: Date,
+    end: Date,
+    fund: &[u32],
+    strategy: &mut dyn Strategy<FundAccount>,
+    settings: &HashMap<u32, FundSetting>,
+    cash: Option<CashPlan>,
+) -> Result<BacktestResult<FundAccount>>
+where
+    P: QueryMarketInfo<MarketInfo = FundData>,
+{
+    let mut fund_accounts = match cash {
+        Some(plan) => Account::<FundAccount>::with_cash(plan),
+        None => Account::<FundAccount>::new(),
+    };
+    for (code, setting) in settings {
+        fund_accounts.apply_setting(*code, setting);
+    }
+    Backtest::new(fund, start, end).run(provider, fund_accounts, strategy)
+}
 
 ///  Automatic Investment Plan
 pub fn run_fund_aip_strategy(
@@ -15,36 +224,38 @@ pub fn run_fund_aip_strategy(
     end: Date,
     day: u8,
     fund: &[u32],
-    budget: &[f32],
-) -> Account<FundAccount> {
-    let fund_mixer = InfoMixer::<FundData>::new(fund, start, end);
-    let mut fund_accounts = Account::<FundAccount>::new();
-    // let mut prev_fund_month = HashMap::<u32, Month>::new();
-    let fund_budget: HashMap<_, _> = fund.iter().zip(budget.iter()).collect();
-
-    let mut prev_fund_month: HashMap<u32, Month> = fund
-        .iter()
-        .map(|x| (*x, start.month().previous()))
-        .collect();
-    // Keep the same with real world, won't use statistical way
-    fund_mixer.for_each(|(code, fund_data)| {
-        if fund_data.date.day() >= day
-            && fund_data.date.month() != *prev_fund_month.get(&code).unwrap()
-        {
-            fund_accounts.buy_with_cost(code, &fund_data, *fund_budget[&code]);
-            let entry = prev_fund_month.get_mut(&code).unwrap();
-            *entry = fund_data.date.month();
-        } else {
-            fund_accounts.update_account(code, fund_data);
-        }
-    });
-    let cur_price: u64 = fund_accounts
-        .hold_detail
-        .values()
-        .map(|x| x.total_value)
-        .sum();
-    fund_accounts.account_value = cur_price;
-    fund_accounts
+    budget: &[Money],
+) -> Result<Account<FundAccount>> {
+    run_fund_aip_strategy_with(
+        &default_provider::<FundData>(),
+        start,
+        end,
+        day,
+        fund,
+        budget,
+        &HashMap::new(),
+        None,
+    )
+}
+
+///  Automatic Investment Plan, 使用指定的行情数据源、各基金的费率和分红方式及现金计划(None表示资金无限)
+#[allow(clippy::too_many_arguments)]
+pub fn run_fund_aip_strategy_with<P>(
+    provider: &P,
+    start: Date,
+    end: Date,
+    day: u8,
+    fund: &[u32],
+    budget: &[Money],
+    settings: &HashMap<u32, FundSetting>,
+    cash: Option<CashPlan>,
+) -> Result<Account<FundAccount>>
+where
+    P: QueryMarketInfo<MarketInfo = FundData>,
+{
+    let mut strategy = AipStrategy::new(FundPlan::new(day, fund, budget));
+    run_fund_strategy_with(provider, start, end, fund, &mut strategy, settings, cash)
+        .map(|x| x.account)
 }
 
 /// buy more at lower price
@@ -53,56 +264,98 @@ pub fn run_fund_buy_more_strategy(
     end: Date,
     day: u8,
     fund: &[u32],
-    budget: &[f32],
-) -> Account<FundAccount> {
-    let fund_mixer = InfoMixer::<FundData>::new(fund, start, end);
-    let mut fund_accounts = Account::<FundAccount>::new();
-    // let mut prev_fund_month = HashMap::<u32, Month>::new();
-    let fund_budget: HashMap<_, _> = fund.iter().zip(budget.iter()).collect();
-
-    let mut prev_fund_month: HashMap<u32, Month> = fund
-        .iter()
-        .map(|x| (*x, start.month().previous()))
-        .collect();
-    // Keep the same with real world, won't use statistical way
-    fund_mixer.for_each(|(code, fund_data)| {
-        if fund_data.date.day() >= day
-            && fund_data.date.month() != *prev_fund_month.get(&code).unwrap()
-        {
-            let mut budget = *fund_budget[&code];
-            /*if fund_accounts.get_object_lowest_price(code).is_some()
-                && fund_accounts.get_object_lowest_price(code).unwrap()
-                    > fund_data.unit_nav as f32 * 0.0001
-            {
-                budget *= 1.5;
-            } else*/
-            if fund_accounts.get_object_average_price(code).is_some()
-                && fund_accounts.get_object_average_price(code).unwrap() > fund_data.unit_nav
-            {
-                let avg = fund_accounts.get_object_average_price(code).unwrap();
-                budget *= (avg / fund_data.unit_nav) as f32;
-            }
-            #[cfg(test)]
-            println!("{}", budget);
-            fund_accounts.buy_with_cost(code, &fund_data, budget);
-            let entry = prev_fund_month.get_mut(&code).unwrap();
-            *entry = fund_data.date.month();
-        } else {
-            fund_accounts.update_account(code, fund_data);
-        }
-    });
-    let cur_price: u64 = fund_accounts
-        .hold_detail
-        .values()
-        .map(|x| x.total_value)
-        .sum();
-    fund_accounts.account_value = cur_price;
-    fund_accounts
+    budget: &[Money],
+) -> Result<Account<FundAccount>> {
+    run_fund_buy_more_strategy_with(
+        &default_provider::<FundData>(),
+        start,
+        end,
+        day,
+        fund,
+        budget,
+        &HashMap::new(),
+        None,
+    )
+}
+
+/// buy more at lower price, 使用指定的行情数据源、各基金的费率和分红方式及现金计划(None表示资金无限)
+#[allow(clippy::too_many_arguments)]
+pub fn run_fund_buy_more_strategy_with<P>(
+    provider: &P,
+    start: Date,
+    end: Date,
+    day: u8,
+    fund: &[u32],
+    budget: &[Money],
+    settings: &HashMap<u32, FundSetting>,
+    cash: Option<CashPlan>,
+) -> Result<Account<FundAccount>>
+where
+    P: QueryMarketInfo<MarketInfo = FundData>,
+{
+    let mut strategy = BuyMoreStrategy::new(FundPlan::new(day, fund, budget));
+    run_fund_strategy_with(provider, start, end, fund, &mut strategy, settings, cash)
+        .map(|x| x.account)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::market::cache::{CachedProvider, MarketCache};
+    use crate::market::provider::EastMoneyProvider;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 东方财富数据源，每次使用单独的临时缓存目录，不读写用户的缓存目录
+    fn temp_provider() -> CachedProvider<EastMoneyProvider<FundData>> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "trade_helper_strategy_{}_{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        CachedProvider::new(EastMoneyProvider::default(), MarketCache::new(dir))
+    }
+
+    fn aip(
+        start: Date,
+        end: Date,
+        day: u8,
+        fund: &[u32],
+        budget: &[Money],
+    ) -> Result<Account<FundAccount>> {
+        let settings = HashMap::new();
+        run_fund_aip_strategy_with(
+            &temp_provider(),
+            start,
+            end,
+            day,
+            fund,
+            budget,
+            &settings,
+            None,
+        )
+    }
+
+    fn buy_more(
+        start: Date,
+        end: Date,
+        day: u8,
+        fund: &[u32],
+        budget: &[Money],
+    ) -> Result<Account<FundAccount>> {
+        let settings = HashMap::new();
+        run_fund_buy_more_strategy_with(
+            &temp_provider(),
+            start,
+            end,
+            day,
+            fund,
+            budget,
+            &settings,
+            None,
+        )
+    }
 
     #[test]
     fn calc_prev_month() {
@@ -111,79 +364,244 @@ mod tests {
         assert_eq!(prev_month, time::Month::December)
     }
 
+    // 每个自然日都有行情，净值在3月1日起从1.0000跌到0.5000
+    struct HalvingProvider;
+
+    impl QueryMarketInfo for HalvingProvider {
+        type MarketInfo = FundData;
+
+        fn query_history_info(
+            &self,
+            _code: u32,
+            start_date: Date,
+            end_date: Date,
+        ) -> Result<Vec<FundData>> {
+            let mut ret = Vec::new();
+            let mut date = start_date;
+            while date <= end_date {
+                let nav = if date < date!(2021 - 3 - 1) {
+                    10000
+                } else {
+                    5000
+                };
+                ret.push(FundData::new(date, nav, nav, None));
+                date = date.next_day().unwrap();
+            }
+            Ok(ret)
+        }
+    }
+
+    #[test]
+    fn test_plan_scheduler() {
+        // 基金2单独指定每周一买入
+        let plan = FundPlan::new(15, &[1, 2], &[Money::ZERO, Money::ZERO])
+            .with_fund_schedule(2, Schedule::Weekly(time::Weekday::Monday));
+        let mut scheduler = plan.scheduler();
+        scheduler.reset([1, 2].into_iter(), date!(2021 - 1 - 1));
+        assert!(!scheduler.is_due(1, date!(2021 - 1 - 4)));
+        assert!(scheduler.is_due(2, date!(2021 - 1 - 4)));
+        assert!(scheduler.is_due(1, date!(2021 - 1 - 15)));
+        assert!(scheduler.is_due(2, date!(2021 - 1 - 11)));
+
+        let mut scheduler = plan.with_schedule(Schedule::MonthEnd).scheduler();
+        scheduler.reset([1].into_iter(), date!(2021 - 1 - 1));
+        assert!(!scheduler.is_due(1, date!(2021 - 1 - 28)));
+        assert!(scheduler.is_due(1, date!(2021 - 1 - 29)));
+    }
+
+    #[test]
+    fn test_aip_and_buy_more_offline() {
+        let budget = [Money::from_raw(100_000_000)];
+        let (start, end) = (date!(2021 - 1 - 1), date!(2021 - 3 - 31));
+        let aip = run_fund_aip_strategy_with(
+            &HalvingProvider,
+            start,
+            end,
+            1,
+            &[1],
+            &budget,
+            &HashMap::new(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(aip.balance_price, Money::from_raw(-300_000_000));
+        assert_eq!(aip.account_value, Money::from_raw(200_000_000));
+
+        // 3月净值为持仓均价的一半，买入两倍金额
+        let buy_more = run_fund_buy_more_strategy_with(
+            &HalvingProvider,
+            start,
+            end,
+            1,
+            &[1],
+            &budget,
+            &HashMap::new(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(buy_more.balance_price, Money::from_raw(-400_000_000));
+        assert_eq!(buy_more.account_value, Money::from_raw(300_000_000));
+
+        // 倍数上限为1时与定期定额相同
+        let plan = FundPlan::new(1, &[1], &budget);
+        let mut strategy = BuyMoreStrategy::new(plan).with_max_multiple(1);
+        let capped = run_fund_strategy_with(
+            &HalvingProvider,
+            start,
+            end,
+            &[1],
+            &mut strategy,
+            &HashMap::new(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(capped.account.balance_price, Money::from_raw(-300_000_000));
+    }
+
+    #[test]
+    fn test_replay_flows() {
+        // 开始日之前的现金流顺延到第一个交易日
+        let flows = BTreeMap::from([
+            (date!(2020 - 12 - 31), Money::from_raw(100_000_000)),
+            (date!(2021 - 3 - 1), Money::from_raw(100_000_000)),
+            (date!(2021 - 3 - 15), Money::from_raw(-75_000_000)),
+        ]);
+        let result = run_benchmark_with(
+            &HalvingProvider,
+            date!(2021 - 1 - 1),
+            date!(2021 - 3 - 31),
+            1,
+            flows.clone(),
+            &FundSetting::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            result
+                .account
+                .trade_flows()
+                .values()
+                .copied()
+                .sum::<Money>(),
+            { flows.values().copied().sum::<Money>() }
+        );
+        assert_eq!(
+            result.account.get_object_volume(1),
+            Some("150".parse().unwrap())
+        );
+        assert_eq!(result.account.account_value, Money::from_raw(75_000_000));
+    }
+
     #[test]
     fn test_single_aip_002021() {
         let start_date = date!(2021 - 5 - 1);
         let end_date = date!(2022 - 3 - 11);
-        let result = run_fund_aip_strategy(start_date, end_date, 1, &[002021u32], &[100.0]);
-        assert!(result.balance_price == -1099945385);
-        assert!(result.account_value == 984622500);
+        let result = aip(
+            start_date,
+            end_date,
+            1,
+            &[002021u32],
+            &[Money::from_raw(100_000_000)],
+        )
+        .unwrap();
+        assert!(result.balance_price == Money::from_raw(-1099945385));
+        assert!(result.account_value == Money::from_raw(984622500));
     }
 
     #[test]
     fn test_single_aip_007994() {
         let start_date = date!(2010 - 1 - 1);
         let end_date = date!(2021 - 1 - 1);
-        let result = run_fund_aip_strategy(start_date, end_date, 1, &[007994u32], &[100.0]);
-        assert!(result.balance_price == -999953076);
-        assert!(result.account_value == 1165856998);
+        let result = aip(
+            start_date,
+            end_date,
+            1,
+            &[007994u32],
+            &[Money::from_raw(100_000_000)],
+        )
+        .unwrap();
+        assert!(result.balance_price == Money::from_raw(-999953076));
+        assert!(result.account_value == Money::from_raw(1165856998));
     }
 
     #[test]
     fn test_single_aip_070032() {
         let start_date = date!(2010 - 1 - 1);
         let end_date = date!(2021 - 1 - 1);
-        let result = run_fund_aip_strategy(start_date, end_date, 1, &[070032u32], &[100.0]);
-        assert!(result.balance_price == -10299379825);
-        assert!(result.account_value == 35869824690);
+        let result = aip(
+            start_date,
+            end_date,
+            1,
+            &[070032u32],
+            &[Money::from_raw(100_000_000)],
+        )
+        .unwrap();
+        assert!(result.balance_price == Money::from_raw(-10299379825));
+        assert!(result.account_value == Money::from_raw(35869824690));
     }
 
     #[test]
     fn test_single_aip_001875() {
         let start_date = date!(2010 - 1 - 1);
         let end_date = date!(2021 - 1 - 1);
-        let result = run_fund_aip_strategy(start_date, end_date, 1, &[001875u32], &[100.0]);
+        let result = aip(
+            start_date,
+            end_date,
+            1,
+            &[001875u32],
+            &[Money::from_raw(100_000_000)],
+        )
+        .unwrap();
         dbg!(result.balance_price);
         dbg!(result.account_value);
-        assert!(result.balance_price == -5699578241);
-        assert!(result.account_value == 15579877710);
+        assert!(result.balance_price == Money::from_raw(-5699578241));
+        assert!(result.account_value == Money::from_raw(15579877710));
     }
 
     #[test]
     fn test_double_aip() {
         let start_date = date!(2021 - 5 - 1);
         let end_date = date!(2022 - 3 - 11);
-        let result = run_fund_aip_strategy(
+        let result = aip(
             start_date,
             end_date,
             1,
             &[002021u32, 070032u32],
-            &[100.0, 200.0],
-        );
-        assert!(result.balance_price == -3299853658);
-        assert!(result.account_value == 2903747740);
+            &[Money::from_raw(100_000_000), Money::from_raw(200_000_000)],
+        )
+        .unwrap();
+        assert!(result.balance_price == Money::from_raw(-3299853658));
+        assert!(result.account_value == Money::from_raw(2903747740));
     }
 
     #[test]
     fn test_triple_aip() {
         let start_date = date!(2020 - 1 - 1);
         let end_date = date!(2021 - 1 - 1);
-        let result = run_fund_aip_strategy(
+        let result = aip(
             start_date,
             end_date,
             1,
             &[007994u32, 001875u32, 070032u32],
-            &[100.0, 100.0, 100.0],
-        );
-        assert!(result.balance_price == -3399714285);
-        assert!(result.account_value == 4703306658);
+            &[Money::from_raw(100_000_000); 3],
+        )
+        .unwrap();
+        assert!(result.balance_price == Money::from_raw(-3399714285));
+        assert!(result.account_value == Money::from_raw(4703306658));
     }
 
     #[test]
     fn test_single_buy_more_002021() {
         let start_date = date!(2010 - 1 - 1);
         let end_date = date!(2021 - 1 - 1);
-        let result = run_fund_buy_more_strategy(start_date, end_date, 1, &[002021u32], &[100.0]);
+        let result = buy_more(
+            start_date,
+            end_date,
+            1,
+            &[002021u32],
+            &[Money::from_raw(100_000_000)],
+        )
+        .unwrap();
         println!("{:?}", result);
         // assert!((result.balance_price + 13200.0).abs() < 2.0);
         // assert!((result.account_value - 33706.85).abs() < 2.0);
@@ -193,7 +611,14 @@ mod tests {
     fn test_single_buy_more_070032() {
         let start_date = date!(2010 - 1 - 1);
         let end_date = date!(2021 - 1 - 1);
-        let result = run_fund_buy_more_strategy(start_date, end_date, 1, &[070032u32], &[100.0]);
+        let result = buy_more(
+            start_date,
+            end_date,
+            1,
+            &[070032u32],
+            &[Money::from_raw(100_000_000)],
+        )
+        .unwrap();
         println!("{:?}", result);
         // assert!((result.balance_price + 1000.0).abs() < 2.0);
         // assert!((result.account_value - 1165.89).abs() < 2.0);
@@ -1,3 +1,43 @@
-// use crossbeam_channel::Receiver;
+//! ## 交易策略
+//! ----
+//!
+//! ### Trait Strategy
+//! ----
+//! 策略只负责在每条行情到来时决定如何交易，行情的遍历、账户的逐日更新由回测引擎(见engine模块)完成
+//! + name: 策略名称
+//! + on_start: 回测开始前调用，可用于初始化持仓设置
+//! + on_bars: 同一时刻所有标的的行情(截面)到来时调用，此时账户已按整个截面更新，默认逐条调用on_bar
+//! + on_bar: 每条行情到来时调用
+//! + on_finish: 回测结束后调用，此时账户已结算到结束日
+//!
+//! 已实现的策略可通过registry模块按名称查找，买入日期的规则见schedule模块
 
+use crate::account::{Account, UpdateAccountItem};
+use time::Date;
+
+pub mod engine;
 pub mod fund_strategy;
+pub mod registry;
+pub mod schedule;
+
+/// 交易策略，T为策略操作的账户类型
+pub trait Strategy<T: UpdateAccountItem + Default + Clone> {
+    /// 策略名称
+    fn name(&self) -> &str;
+
+    /// 回测开始前调用
+    fn on_start(&mut self, _start: Date, _account: &mut Account<T>) {}
+
+    /// 同一时刻所有标的的行情到来时调用，需要同时考虑多个标的的策略可以重写
+    fn on_bars(&mut self, bars: &[(u32, T::MarketData)], account: &mut Account<T>) {
+        for (code, data) in bars {
+            self.on_bar(*code, data, account);
+        }
+    }
+
+    /// 每条行情到来时调用
+    fn on_bar(&mut self, code: u32, data: &T::MarketData, account: &mut Account<T>);
+
+    /// 回测结束后调用
+    fn on_finish(&mut self, _end: Date, _account: &mut Account<T>) {}
+}
@@ -0,0 +1,106 @@
+//! ## 策略注册表
+//! ----
+//!
+//! 按名称查找已实现的策略，命令行通过名称选择要运行的策略，配置文件中还可以为策略设置参数
+
+use super::fund_strategy::{AipStrategy, BuyMoreStrategy, FundPlan};
+use super::Strategy;
+use crate::account::fund_account::FundAccount;
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeMap;
+
+/// 策略参数，参数名到数值
+pub type StrategyParams = BTreeMap<String, f64>;
+
+/// 基金策略的注册信息
+pub struct FundStrategyEntry {
+    pub name: &'static str,
+    pub params: &'static [&'static str], // 支持的参数
+    build: fn(FundPlan, &StrategyParams) -> Box<dyn Strategy<FundAccount>>,
+}
+
+/// 已注册的基金策略，第一个为默认策略
+pub const FUND_STRATEGIES: &[FundStrategyEntry] = &[
+    // 定期定额投资，每期买入固定金额
+    FundStrategyEntry {
+        name: "aip",
+        params: &[],
+        build: |plan, _| Box::new(AipStrategy::new(plan)),
+    },
+    // 越跌越买，净值低于持仓均价时加倍买入，max_multiple为每期买入金额的倍数上限
+    FundStrategyEntry {
+        name: "buy-more",
+        params: &["max_multiple"],
+        build: |plan, params| {
+            let strategy = BuyMoreStrategy::new(plan);
+            match params.get("max_multiple") {
+                Some(multiple) => Box::new(strategy.with_max_multiple(*multiple as u64)),
+                None => Box::new(strategy),
+            }
+        },
+    },
+];
+
+/// 已注册的基金策略名称
+pub fn fund_strategy_names() -> Vec<&'static str> {
+    FUND_STRATEGIES.iter().map(|x| x.name).collect()
+}
+
+/// 按名称构造基金策略，params中有策略不支持的参数时返回错误
+pub fn build_fund_strategy(
+    name: &str,
+    plan: FundPlan,
+    params: &StrategyParams,
+) -> Result<Box<dyn Strategy<FundAccount>>> {
+    let entry = FUND_STRATEGIES
+        .iter()
+        .find(|x| x.name == name)
+        .ok_or_else(|| {
+            anyhow!(
+                "unknown strategy {}, available: {}",
+                name,
+                fund_strategy_names().join(", ")
+            )
+        })?;
+    if let Some(param) = params.keys().find(|x| !entry.params.contains(&x.as_str())) {
+        bail!(
+            "unknown parameter {} for strategy {}, available: {}",
+            param,
+            name,
+            entry.params.join(", ")
+        );
+    }
+    Ok((entry.build)(plan, params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_fund_strategy() {
+        let plan = FundPlan::new(1, &[1], &["100".parse().unwrap()]);
+        for name in fund_strategy_names() {
+            assert_eq!(
+                build_fund_strategy(name, plan.clone(), &StrategyParams::new())
+                    .unwrap()
+                    .name(),
+                name
+            );
+        }
+        let err = build_fund_strategy("unknown", plan.clone(), &StrategyParams::new())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown strategy unknown, available: aip, buy-more"
+        );
+        let params = StrategyParams::from([("max_multiple".to_string(), 3.0)]);
+        assert!(build_fund_strategy("buy-more", plan.clone(), &params).is_ok());
+        let err = build_fund_strategy("aip", plan, &params).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "unknown parameter max_multiple for strategy aip, available: "
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! ## 买入计划
+//! ----
+//!
+//! Schedule描述在哪些日期买入，目标日为非交易日(见market::calendar)时顺延到之后的第一个交易日，
+//! 基金在目标日没有行情时在之后第一个有行情的交易日买入，期间错过的多个目标日只买入一次：
+//! + weekly:<星期>: 每周的星期几，如weekly:mon
+//! + biweekly:<星期>: 从回测开始日起每两周的星期几，如biweekly:fri
+//! + monthly:<日>: 每月第几日，该月没有这一天时为月末，如monthly:15
+//! + month-end: 每月最后一个交易日
+//! + dates:<yyyymmdd>,...: 指定的日期，如dates:20210105,20210610
+//!
+//! Scheduler按Schedule记录各基金下一次买入的目标日，任何策略都可以用它决定何时买入
+
+use crate::market::calendar::TradingCalendar;
+use anyhow::{anyhow, bail, Error, Result};
+use serde::{de, Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::str::FromStr;
+use time::macros::format_description;
+use time::{Date, Duration, Month, Weekday};
+
+/// 买入日期的规则
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    Weekly(Weekday),
+    BiWeekly(Weekday),
+    Monthly(u8),
+    MonthEnd,
+    Dates(Vec<Date>), // 升序且不重复
+}
+
+impl Default for Schedule {
+    /// 每月第一天
+    fn default() -> Self {
+        Schedule::Monthly(1)
+    }
+}
+
+impl Schedule {
+    /// 不早于from的第一个目标日(已顺延到交易日)，anchor为回测开始日，用于确定每两周的起点
+    pub fn next_target(&self, from: Date, anchor: Date) -> Option<Date> {
+        let calendar = TradingCalendar::china();
+        let target = match self {
+            Schedule::Weekly(weekday) => Some(next_weekday(from, *weekday)),
+            Schedule::BiWeekly(weekday) => {
+                let first = next_weekday(anchor, *weekday);
+                let weeks = ((from - first).whole_days().max(0) + 13) / 14;
+                Some(first + Duration::weeks(weeks * 2))
+            }
+            Schedule::Monthly(day) => {
+                let target = day_of_month(from.year(), from.month(), *day);
+                if target >= from {
+                    Some(target)
+                } else {
+                    let (year, month) = next_month(from.year(), from.month());
+                    Some(day_of_month(year, month, *day))
+                }
+            }
+            Schedule::MonthEnd => {
+                let (year, month) = next_month(from.year(), from.month());
+                let target = calendar.previous_trading_day(day_of_month(year, month, 1));
+                if target >= from {
+                    Some(target)
+                } else {
+                    let (year, month) = next_month(year, month);
+                    Some(calendar.previous_trading_day(day_of_month(year, month, 1)))
+                }
+            }
+            Schedule::Dates(dates) => dates
+                .iter()
+                .copied()
+                .find(|x| roll_forward(calendar, *x) >= from),
+        }?;
+        Some(roll_forward(calendar, target))
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_ascii_lowercase();
+        let (kind, value) = match s.split_once(':') {
+            Some((kind, value)) => (kind.trim(), value.trim()),
+            None => (s.as_str(), ""),
+        };
+        match (kind, value) {
+            ("weekly", value) => Ok(Schedule::Weekly(parse_weekday(value)?)),
+            ("biweekly", value) => Ok(Schedule::BiWeekly(parse_weekday(value)?)),
+            ("monthly", value) => match value.parse::<u8>() {
+                Ok(day @ 1..=31) => Ok(Schedule::Monthly(day)),
+                _ => bail!("invalid day of month {:?}, expected 1 to 31", value),
+            },
+            ("month-end", "") => Ok(Schedule::MonthEnd),
+            ("dates", value) => {
+                let format = format_description!("[year][month][day]");
+                let mut dates = value
+                    .split(',')
+                    .map(|x| {
+                        Date::parse(x.trim(), &format)
+                            .map_err(|_| anyhow!("invalid date {:?}, expected yyyymmdd", x))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                dates.sort();
+                dates.dedup();
+                Ok(Schedule::Dates(dates))
+            }
+            _ => bail!(
+                "invalid schedule {:?}, expected weekly:<WEEKDAY>, biweekly:<WEEKDAY>, monthly:<DAY>, month-end or dates:<YYYYMMDD>,...",
+                s
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// 各基金的买入计划，记录每只基金下一次买入的目标日
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    schedule: Schedule,
+    schedules: HashMap<u32, Schedule>, // 单独指定买入计划的基金
+    anchor: Option<Date>,
+    next: HashMap<u32, Date>, // 没有下一个目标日的基金不再买入
+}
+
+impl Scheduler {
+    /// 所有基金默认按schedule买入，fund_schedules中的基金按各自的计划买入
+    pub fn new(schedule: Schedule, fund_schedules: HashMap<u32, Schedule>) -> Self {
+        Scheduler {
+            schedule,
+            schedules: fund_schedules,
+            ..Default::default()
+        }
+    }
+
+    fn schedule(&self, code: u32) -> &Schedule {
+        self.schedules.get(&code).unwrap_or(&self.schedule)
+    }
+
+    /// 回测开始时，以开始日之后的第一个目标日作为各基金的下一次买入日
+    pub fn reset(&mut self, codes: impl Iterator<Item = u32>, start: Date) {
+        self.anchor = Some(start);
+        self.next = codes
+            .filter_map(|code| Some((code, self.schedule(code).next_target(start, start)?)))
+            .collect();
+    }
+
+    /// 当日是否应当买入，应当买入时将下一次买入日推进到当日之后的目标日
+    pub fn is_due(&mut self, code: u32, date: Date) -> bool {
+        match self.next.get(&code) {
+            Some(next) if *next <= date => {}
+            _ => return false,
+        }
+        let anchor = self.anchor.unwrap_or(date);
+        match date
+            .next_day()
+            .and_then(|from| self.schedule(code).next_target(from, anchor))
+        {
+            Some(next) => self.next.insert(code, next),
+            None => self.next.remove(&code),
+        };
+        true
+    }
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday> {
+    let weekday = match value {
+        "mon" | "monday" => Weekday::Monday,
+        "tue" | "tuesday" => Weekday::Tuesday,
+        "wed" | "wednesday" => Weekday::Wednesday,
+        "thu" | "thursday" => Weekday::Thursday,
+        "fri" | "friday" => Weekday::Friday,
+        "sat" | "saturday" => Weekday::Saturday,
+        "sun" | "sunday" => Weekday::Sunday,
+        _ => bail!("invalid weekday {:?}, expected mon to sun", value),
+    };
+    Ok(weekday)
+}
+
+/// 不早于date的第一个指定星期几
+fn next_weekday(date: Date, weekday: Weekday) -> Date {
+    let days =
+        (weekday.number_days_from_monday() + 7 - date.weekday().number_days_from_monday()) % 7;
+    date + Duration::days(days as i64)
+}
+
+fn next_month(year: i32, month: Month) -> (i32, Month) {
+    match month {
+        Month::December => (year + 1, Month::January),
+        _ => (year, month.next()),
+    }
+}
+
+/// 该月第day日，该月没有这一天时为月末
+fn day_of_month(year: i32, month: Month, day: u8) -> Date {
+    let day = day.min(month.length(year));
+    Date::from_calendar_date(year, month, day).unwrap()
+}
+
+/// 非交易日顺延到之后的第一个交易日
+fn roll_forward(calendar: &TradingCalendar, date: Date) -> Date {
+    if calendar.is_trading_day(date) {
+        date
+    } else {
+        calendar.next_trading_day(date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!(
+            "weekly:Mon".parse::<Schedule>().unwrap(),
+            Schedule::Weekly(Weekday::Monday)
+        );
+        assert_eq!(
+            "biweekly: friday".parse::<Schedule>().unwrap(),
+            Schedule::BiWeekly(Weekday::Friday)
+        );
+        assert_eq!(
+            "monthly:31".parse::<Schedule>().unwrap(),
+            Schedule::Monthly(31)
+        );
+        assert_eq!("month-end".parse::<Schedule>().unwrap(), Schedule::MonthEnd);
+        assert_eq!(
+            "dates:20210610,20210105,20210610"
+                .parse::<Schedule>()
+                .unwrap(),
+            Schedule::Dates(vec![date!(2021 - 1 - 5), date!(2021 - 6 - 10)])
+        );
+        for invalid in ["weekly", "monthly:0", "monthly:32", "dates:2021", "daily"] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_next_target() {
+        let anchor = date!(2021 - 1 - 1); // 周五
+        let weekly = Schedule::Weekly(Weekday::Monday);
+        assert_eq!(
+            weekly.next_target(anchor, anchor),
+            Some(date!(2021 - 1 - 4))
+        );
+        assert_eq!(
+            weekly.next_target(date!(2021 - 1 - 4), anchor),
+            Some(date!(2021 - 1 - 4))
+        );
+        let biweekly = Schedule::BiWeekly(Weekday::Friday);
+        // 1月1日元旦休市，顺延到4日
+        assert_eq!(
+            biweekly.next_target(anchor, anchor),
+            Some(date!(2021 - 1 - 4))
+        );
+        assert_eq!(
+            biweekly.next_target(date!(2021 - 1 - 2), anchor),
+            Some(date!(2021 - 1 - 15))
+        );
+        assert_eq!(
+            biweekly.next_target(date!(2021 - 1 - 16), anchor),
+            Some(date!(2021 - 1 - 29))
+        );
+        // 2月没有31日，在月末买入，2月28日是周日，顺延到3月1日；跨年后继续
+        let monthly = Schedule::Monthly(31);
+        assert_eq!(
+            monthly.next_target(date!(2021 - 1 - 5), anchor),
+            Some(date!(2021 - 2 - 1))
+        );
+        assert_eq!(
+            monthly.next_target(date!(2021 - 2 - 2), anchor),
+            Some(date!(2021 - 3 - 1))
+        );
+        assert_eq!(
+            Schedule::Monthly(15).next_target(date!(2021 - 12 - 16), anchor),
+            Some(date!(2022 - 1 - 17))
+        );
+        // 2021年7月31日是周六；2023年9月29日起中秋、国庆休市
+        assert_eq!(
+            Schedule::MonthEnd.next_target(date!(2021 - 7 - 1), anchor),
+            Some(date!(2021 - 7 - 30))
+        );
+        assert_eq!(
+            Schedule::MonthEnd.next_target(date!(2023 - 9 - 1), anchor),
+            Some(date!(2023 - 9 - 28))
+        );
+        assert_eq!(
+            Schedule::MonthEnd.next_target(date!(2021 - 12 - 31), anchor),
+            Some(date!(2021 - 12 - 31))
+        );
+        let dates = Schedule::Dates(vec![date!(2021 - 1 - 5)]);
+        assert_eq!(dates.next_target(anchor, anchor), Some(date!(2021 - 1 - 5)));
+        assert_eq!(dates.next_target(date!(2021 - 1 - 6), anchor), None);
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let mut scheduler = Scheduler::new(
+            Schedule::Monthly(15),
+            HashMap::from([(2, Schedule::Dates(vec![date!(2021 - 1 - 20)]))]),
+        );
+        scheduler.reset([1, 2].into_iter(), date!(2021 - 1 - 1));
+        assert!(!scheduler.is_due(1, date!(2021 - 1 - 14)));
+        // 15日没有行情，在之后第一条行情买入
+        assert!(scheduler.is_due(1, date!(2021 - 1 - 18)));
+        assert!(!scheduler.is_due(1, date!(2021 - 1 - 19)));
+        // 2月15日春节休市，顺延到18日
+        assert!(!scheduler.is_due(1, date!(2021 - 2 - 15)));
+        assert!(scheduler.is_due(1, date!(2021 - 2 - 18)));
+        // 同一月份的不同年份分别买入
+        assert!(scheduler.is_due(1, date!(2021 - 3 - 15)));
+        assert!(scheduler.is_due(1, date!(2022 - 3 - 15)));
+        assert!(!scheduler.is_due(1, date!(2022 - 3 - 16)));
+        // 错过多个目标日只买入一次
+        assert!(scheduler.is_due(1, date!(2022 - 6 - 1)));
+        assert!(!scheduler.is_due(1, date!(2022 - 6 - 14)));
+
+        assert!(!scheduler.is_due(2, date!(2021 - 1 - 15)));
+        assert!(scheduler.is_due(2, date!(2021 - 1 - 20)));
+        assert!(!scheduler.is_due(2, date!(2021 - 2 - 20)));
+        // 未参与回测的基金不买入
+        assert!(!scheduler.is_due(3, date!(2021 - 1 - 15)));
+    }
+}